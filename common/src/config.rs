@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::Read;
@@ -7,6 +8,10 @@ use serde::Deserialize;
 pub struct Config {
     pub database: Database,
     pub log: Log,
+    #[serde(default)]
+    pub workflow: Workflow,
+    #[serde(default)]
+    pub storage: Storage,
 }
 
 
@@ -33,6 +38,66 @@ pub struct Log {
     pub debug: bool,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct Workflow {
+    // 任务流转动作允许的角色编号或者名称，key为动作，review、approve和reject没有配置时所有角色都不能执行
+    #[serde(default)]
+    pub roles: HashMap<String, Vec<String>>,
+}
+
+/// 文件存储类型
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageType {
+    // 本地文件
+    #[default]
+    Local,
+    // 兼容S3的对象存储
+    S3,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Storage {
+    // 存储类型，local或者s3
+    pub r#type: StorageType,
+    // 本地存储根目录
+    pub root: String,
+    // 本地存储预签名密钥，为空时预签名地址不可用
+    pub secret: String,
+    // 本地存储预签名地址前缀，例如http://localhost:8080
+    pub base_url: String,
+    // 对象存储配置
+    pub s3: S3,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self {
+            r#type: StorageType::Local,
+            root: "storage".to_string(),
+            secret: "".to_string(),
+            base_url: "".to_string(),
+            s3: S3::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct S3 {
+    // 服务地址，例如http://localhost:9000
+    pub endpoint: String,
+    // 区域
+    pub region: String,
+    // 桶名称
+    pub bucket: String,
+    // 访问密钥编号
+    pub access_key: String,
+    // 访问密钥
+    pub secret_key: String,
+}
+
 impl Config {
 
     /// 默认读取项目目录下的配置文件config.toml
@@ -1,10 +1,25 @@
+pub mod aggregate;
 pub mod db_type;
+pub mod filter;
 pub mod form;
+pub mod history;
+pub mod schema;
 pub mod select;
+pub mod sync;
+pub mod version;
+pub mod workflow;
 
 use crate::config::Config;
-use crate::db::db_type::DbType;
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use crate::db::db_type::{ColumnType, DbType};
+use crate::db::form::FormCommonField;
+use crate::db::history::{history_returning, HistoryAction};
+use crate::db::schema::TableSchema;
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{
+    Alias, Asterisk, ColumnDef, Cond, Expr, Func, JoinType, Order, PostgresQueryBuilder, Query,
+    SimpleExpr, Table,
+};
+use sea_orm::{ConnectOptions, Database, DatabaseBackend, DatabaseConnection, Statement};
 use std::collections::HashMap;
 use std::time::Duration;
 use tokio::sync::OnceCell;
@@ -36,80 +51,327 @@ pub async fn get_init_db_pool(config: &Config) -> DatabaseConnection {
 }
 
 /// 创建表sql函数
-pub fn create_table_sql(table_name: &str, fields: &[String], is_form: bool) -> String {
-    let mut create_sql = format!("create table if not exists {}(", table_name);
-    // 添加 主键
-    create_sql.push_str("id varchar not null primary key,");
-    for field in fields.iter() {
-        create_sql.push_str(&format!("{} varchar,", field));
+/// 表名和字段名由sea-query加引号处理，避免拼接非法标识符
+/// @param table_name 表名
+/// @param fields 字段名和字段类型列表
+/// @param is_form 是否添加表单公共字段
+pub fn create_table_sql(table_name: &str, fields: &[(String, ColumnType)], is_form: bool) -> String {
+    let mut table = Table::create();
+    table
+        .table(Alias::new(table_name))
+        .if_not_exists()
+        // 添加 主键
+        .col(
+            ColumnDef::new(Alias::new(FormCommonField::Id.to_string()))
+                .string()
+                .not_null()
+                .primary_key(),
+        );
+    for (field, column_type) in fields.iter() {
+        table.col(&mut column_type.column_def(field));
     }
 
     if is_form {
         // 表单公共字段
-        let common_fields = create_common_fields();
-        for common_field in common_fields.into_iter() {
-            create_sql.push_str(common_field);
+        for mut common_field in create_common_fields().into_iter() {
+            table.col(&mut common_field);
         }
-    } else {
-        // 移除最后一位逗号
-        let current_sql = &create_sql[..create_sql.len() - 1];
-        create_sql = current_sql.to_string();
     }
-    create_sql.push_str(");");
 
-    create_sql
+    table.to_string(PostgresQueryBuilder)
+}
+
+/// 添加字段sql函数
+/// @param table_name 表名
+/// @param field 字段名
+/// @param column_type 字段类型
+pub fn add_column_sql(table_name: &str, field: &str, column_type: ColumnType) -> String {
+    Table::alter()
+        .table(Alias::new(table_name))
+        .add_column(&mut column_type.column_def(field))
+        .to_string(PostgresQueryBuilder)
+}
+
+/// 重命名字段sql函数
+/// @param table_name 表名
+/// @param from 原字段名
+/// @param to 新字段名
+pub fn rename_column_sql(table_name: &str, from: &str, to: &str) -> String {
+    Table::alter()
+        .table(Alias::new(table_name))
+        .rename_column(Alias::new(from), Alias::new(to))
+        .to_string(PostgresQueryBuilder)
+}
+
+/// 删除字段sql函数
+/// @param table_name 表名
+/// @param field 字段名
+pub fn drop_column_sql(table_name: &str, field: &str) -> String {
+    Table::alter()
+        .table(Alias::new(table_name))
+        .drop_column(Alias::new(field))
+        .to_string(PostgresQueryBuilder)
 }
 
 /// 插入数据sql函数
-pub fn insert_data_sql(table_name: &str, fields: &[String], data: &[Box<dyn DbType>]) -> String {
-    let mut sql = format!("INSERT INTO {} ", table_name);
-    sql.push('(');
-    for field in fields.iter() {
-        sql.push_str(&format!("{},", field));
+/// 字段名根据表结构校验，数据使用绑定参数
+/// @param schema 表结构
+/// @param fields 字段列表
+/// @param data 数据列表
+pub fn insert_data_sql(
+    schema: &TableSchema,
+    fields: &[String],
+    data: &[Box<dyn DbType>],
+) -> Result<Statement> {
+    if fields.len() != data.len() {
+        bail!("表头列表和数据列表添加数量必须一致")
     }
-    // 移除最后的逗号
-    let current_sql = &sql[..sql.len() - 1];
-    let mut sql = current_sql.to_string();
-    sql.push_str(") VALUES ( ");
-    // 遍历数据
-    for datum in data.iter() {
-        sql.push_str(&format!("{},", datum.display()));
+    let mut values = Vec::new();
+    for (field, datum) in fields.iter().zip(data.iter()) {
+        values.push(schema.field(field)?.bind(datum.value())?);
+    }
+    let mut insert = Query::insert();
+    insert
+        .into_table(Alias::new(&schema.table_name))
+        .columns(fields.iter().map(Alias::new))
+        .values(values)?;
+    Ok(DatabaseBackend::Postgres.build(&insert))
+}
+
+/// 批量插入数据sql函数，多行数据生成一条插入语句，返回记录变更历史的数据
+/// 字段名根据表结构校验，数据使用绑定参数
+/// @param schema 表结构
+/// @param fields 字段列表
+/// @param rows 数据列表，None代表NULL
+pub fn insert_batch_sql(
+    schema: &TableSchema,
+    fields: &[String],
+    rows: &[Vec<Option<sea_orm::Value>>],
+) -> Result<Statement> {
+    let columns = fields
+        .iter()
+        .map(|field| schema.field(field))
+        .collect::<Result<Vec<_>>>()?;
+    let mut insert = Query::insert();
+    insert
+        .into_table(Alias::new(&schema.table_name))
+        .columns(fields.iter().map(Alias::new));
+    for row in rows.iter() {
+        if fields.len() != row.len() {
+            bail!("表头列表和数据列表添加数量必须一致")
+        }
+        let mut values = Vec::new();
+        for (column, datum) in columns.iter().zip(row.iter()) {
+            values.push(column.bind(datum.clone())?);
+        }
+        insert.values(values)?;
     }
-    // 移除最后的逗号
-    let current_sql = &sql[..sql.len() - 1];
-    let mut sql = current_sql.to_string();
-    sql.push(')');
-    sql
+    insert.returning(history_returning(&schema.table_name, HistoryAction::Insert));
+    Ok(DatabaseBackend::Postgres.build(&insert))
 }
 
 /// 更新数据sql函数
+/// 字段名根据表结构校验，数据和编号使用绑定参数
+/// @param schema 表结构
+/// @param id 数据编号
+/// @param data 更新的数据
 pub fn update_data_sql(
-    table_name: &str,
+    schema: &TableSchema,
     id: &str,
     data: HashMap<String, Box<dyn DbType>>,
-) -> String {
-    let mut sql = format!("UPDATE {} SET ", table_name);
+) -> Result<Statement> {
+    let mut values = Vec::new();
     for (key, value) in data.iter() {
-        sql.push_str(&format!("{}={},", key, value.display()));
+        values.push((Alias::new(key), schema.field(key)?.bind(value.value())?));
+    }
+    let id_field = FormCommonField::Id.to_string();
+    let mut update = Query::update();
+    update
+        .table(Alias::new(&schema.table_name))
+        .values(values)
+        .and_where(Expr::col(Alias::new(&id_field)).eq(schema.field(&id_field)?.cast(Some(id.into()))));
+    Ok(DatabaseBackend::Postgres.build(&update))
+}
+
+/// 根据编号更新一行数据sql函数，返回记录变更历史的数据
+/// 字段名根据表结构校验，数据和编号使用绑定参数
+/// @param schema 表结构
+/// @param id 数据编号
+/// @param fields 字段列表
+/// @param row 数据列表，None代表NULL
+pub fn update_row_sql(
+    schema: &TableSchema,
+    id: &str,
+    fields: &[String],
+    row: &[Option<sea_orm::Value>],
+) -> Result<Statement> {
+    if fields.len() != row.len() {
+        bail!("表头列表和数据列表添加数量必须一致")
+    }
+    let mut values = Vec::new();
+    for (field, datum) in fields.iter().zip(row.iter()) {
+        values.push((Alias::new(field), schema.field(field)?.bind(datum.clone())?));
     }
-    // 移除最后的逗号
-    let current_sql = &sql[..sql.len() - 1];
-    let mut sql = current_sql.to_string();
-    sql.push_str(&format!(" WHERE id = '{}'", id));
-    sql
+    let id_field = FormCommonField::Id.to_string();
+    let mut update = Query::update();
+    update
+        .table(Alias::new(&schema.table_name))
+        .values(values)
+        .and_where(Expr::col(Alias::new(&id_field)).eq(schema.field(&id_field)?.cast(Some(id.into()))))
+        .returning(history_returning(&schema.table_name, HistoryAction::Update));
+    Ok(DatabaseBackend::Postgres.build(&update))
 }
 
-fn create_common_fields() -> Vec<&'static str> {
+/// 根据条件更新数据sql函数，排除删除的数据，返回记录变更历史的数据
+/// 字段名根据表结构校验，数据使用绑定参数
+/// @param schema 表结构
+/// @param fields 字段列表
+/// @param row 数据列表，None代表NULL
+/// @param condition 更新条件
+pub fn update_where_sql(
+    schema: &TableSchema,
+    fields: &[String],
+    row: &[Option<sea_orm::Value>],
+    condition: Cond,
+) -> Result<Statement> {
+    if fields.len() != row.len() {
+        bail!("表头列表和数据列表添加数量必须一致")
+    }
+    let mut values = Vec::new();
+    for (field, datum) in fields.iter().zip(row.iter()) {
+        values.push((Alias::new(field), schema.field(field)?.bind(datum.clone())?));
+    }
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    schema.field(&deleted_at)?;
+    let mut update = Query::update();
+    update
+        .table(Alias::new(&schema.table_name))
+        .values(values)
+        .and_where(Expr::col(Alias::new(&deleted_at)).is_null())
+        .cond_where(condition)
+        .returning(history_returning(&schema.table_name, HistoryAction::Update));
+    Ok(DatabaseBackend::Postgres.build(&update))
+}
+
+/// 删除全部数据sql函数，更新未删除数据的删除时间，返回记录变更历史的数据
+/// @param schema 表结构
+pub fn delete_all_sql(schema: &TableSchema) -> Result<Statement> {
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    let date = chrono::Local::now().naive_local();
+    let mut update = Query::update();
+    update
+        .table(Alias::new(&schema.table_name))
+        .value(
+            Alias::new(&deleted_at),
+            schema.field(&deleted_at)?.cast(Some(date.into())),
+        )
+        .and_where(Expr::col(Alias::new(&deleted_at)).is_null())
+        .returning(history_returning(&schema.table_name, HistoryAction::Delete));
+    Ok(DatabaseBackend::Postgres.build(&update))
+}
+
+/// 任务表和数据表根据编号关联查询sql函数，排除删除的数据，按照任务创建时间排序
+/// 字段名根据表结构校验，查询结果使用别名，numeric字段转换成float8
+/// @param task 任务表结构
+/// @param data 数据表结构
+/// @param task_fields 任务表字段和别名
+/// @param data_fields 数据表字段和别名
+/// @param all_tasks 是否查询全部任务，没有数据的任务数据字段为空
+pub fn join_task_data_sql(
+    task: &TableSchema,
+    data: &TableSchema,
+    task_fields: &[(String, String)],
+    data_fields: &[(String, String)],
+    all_tasks: bool,
+) -> Result<Statement> {
+    let (task_alias, data_alias) = (Alias::new("t"), Alias::new("d"));
+    let mut select = Query::select();
+    for (schema, table_alias, fields) in [
+        (task, &task_alias, task_fields),
+        (data, &data_alias, data_fields),
+    ] {
+        for (field, alias) in fields.iter() {
+            let table_field = schema.field(field)?;
+            let column = Expr::col((table_alias.clone(), Alias::new(field)));
+            if table_field.base_type() == "numeric" {
+                select.expr_as(column.cast_as(Alias::new("float8")), Alias::new(alias));
+            } else {
+                select.expr_as(column, Alias::new(alias));
+            }
+        }
+    }
+    let id = FormCommonField::Id.to_string();
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    task.field(&deleted_at)?;
+    data.field(&deleted_at)?;
+    let on = Expr::col((data_alias.clone(), Alias::new(&id)))
+        .equals((task_alias.clone(), Alias::new(&id)));
+    let data_deleted = Expr::col((data_alias.clone(), Alias::new(&deleted_at))).is_null();
+    // 查询全部任务时使用左连接，删除数据的条件放到连接条件中
+    let (join_type, on) = match all_tasks {
+        true => (JoinType::LeftJoin, on.and(data_deleted)),
+        false => {
+            select.and_where(data_deleted);
+            (JoinType::InnerJoin, on)
+        }
+    };
+    select
+        .from_as(Alias::new(&task.table_name), task_alias.clone())
+        .join_as(join_type, Alias::new(&data.table_name), data_alias, on)
+        .and_where(Expr::col((task_alias.clone(), Alias::new(&deleted_at))).is_null())
+        .order_by(
+            (task_alias.clone(), Alias::new(FormCommonField::CreatedAt.to_string())),
+            Order::Asc,
+        )
+        .order_by((task_alias, Alias::new(&id)), Order::Asc);
+    Ok(DatabaseBackend::Postgres.build(&select))
+}
+
+/// 分组统计sql函数，排除删除的数据，按照分组值排序
+/// 查询结果字段为key和count，有完成条件时增加completed
+/// @param schema 表结构
+/// @param key 分组表达式
+/// @param completed 完成条件，满足条件的数量
+pub fn group_count_sql(
+    schema: &TableSchema,
+    key: SimpleExpr,
+    completed: Option<SimpleExpr>,
+) -> Result<Statement> {
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    schema.field(&deleted_at)?;
+    let mut select = Query::select();
+    select
+        .expr_as(key, Alias::new("key"))
+        .expr_as(Func::count(Expr::col(Asterisk)), Alias::new("count"));
+    if let Some(condition) = completed {
+        let count = Func::sum(Expr::case(condition, 1).finally(0));
+        select.expr_as(
+            Expr::expr(count).cast_as(Alias::new("int8")),
+            Alias::new("completed"),
+        );
+    }
+    select
+        .from(Alias::new(&schema.table_name))
+        .and_where(Expr::col(Alias::new(&deleted_at)).is_null())
+        // 分组表达式可能包含绑定参数，使用字段序号分组和排序
+        .add_group_by([Expr::cust("1")])
+        .order_by_expr(Expr::cust("1"), Order::Asc);
+    Ok(DatabaseBackend::Postgres.build(&select))
+}
+
+fn create_common_fields() -> Vec<ColumnDef> {
     let result = vec![
-        "code varchar,",
-        "lon varchar,",
-        "lat varchar,",
-        "status integer,",
-        "user_id varchar,",
-        "created_at timestamp not null,",
-        "updated_at timestamp,",
-        "deleted_at timestamp",
+        ColumnDef::new(Alias::new(FormCommonField::Code.to_string())).string().to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::Lon.to_string())).string().to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::Lat.to_string())).string().to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::Status.to_string())).integer().to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::UserId.to_string())).string().to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::CreatedAt.to_string()))
+            .timestamp()
+            .not_null()
+            .to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::UpdatedAt.to_string())).timestamp().to_owned(),
+        ColumnDef::new(Alias::new(FormCommonField::DeletedAt.to_string())).timestamp().to_owned(),
     ];
     result
 }
-
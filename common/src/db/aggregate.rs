@@ -0,0 +1,170 @@
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{
+    Alias, Asterisk, Cond, Expr, Func, Order, Query, SelectStatement, SimpleExpr,
+};
+use sea_orm::{DatabaseBackend, Statement};
+use serde::{Deserialize, Serialize};
+
+use crate::db::form::FormCommonField;
+use crate::db::schema::TableSchema;
+
+/// 最多分组字段数量
+pub const MAX_GROUP_FIELDS: usize = 2;
+
+/// 统计方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateOp {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateOp {
+    /// 是否只能统计数值字段，count可以统计任意字段
+    pub fn numeric(&self) -> bool {
+        !matches!(self, AggregateOp::Count)
+    }
+
+    /// 统计表达式，数值结果转换成float8
+    /// @param field 字段名
+    fn expr(&self, field: &str) -> SimpleExpr {
+        let column = Expr::col(Alias::new(field));
+        let function = match self {
+            AggregateOp::Count => return Func::count(column).into(),
+            AggregateOp::Sum => Func::sum(column),
+            AggregateOp::Avg => Func::avg(column),
+            AggregateOp::Min => Func::min(column),
+            AggregateOp::Max => Func::max(column),
+        };
+        Expr::expr(function).cast_as(Alias::new("float8"))
+    }
+}
+
+/// 统计指标
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggregateMetric {
+    pub field: String,
+    pub op: AggregateOp,
+}
+
+/// 分组统计sql函数，排除删除的数据，按照分组字段排序
+/// 查询结果中分组字段别名为g0、g1，数量为count，指标别名为m0、m1...
+/// @param schema 表结构
+/// @param group_by 分组字段
+/// @param metrics 统计指标
+/// @param condition 查询条件
+pub fn aggregate_sql(
+    schema: &TableSchema,
+    group_by: &[String],
+    metrics: &[AggregateMetric],
+    condition: Cond,
+) -> Result<Statement> {
+    let mut select = Query::select();
+    group_select(&mut select, schema, group_by, condition)?;
+    select.expr_as(Func::count(Expr::col(Asterisk)), Alias::new("count"));
+    for (index, metric) in metrics.iter().enumerate() {
+        schema.field(&metric.field)?;
+        select.expr_as(
+            metric.op.expr(&metric.field),
+            Alias::new(format!("m{}", index)),
+        );
+    }
+    Ok(DatabaseBackend::Postgres.build(&select))
+}
+
+/// 频数统计sql函数，按照分组字段和字段值统计数量，数量多的在前
+/// 查询结果中分组字段别名为g0、g1，字段值为value，数量为count
+/// @param schema 表结构
+/// @param field 统计的字段
+/// @param group_by 分组字段
+/// @param condition 查询条件
+pub fn frequency_sql(
+    schema: &TableSchema,
+    field: &str,
+    group_by: &[String],
+    condition: Cond,
+) -> Result<Statement> {
+    schema.field(field)?;
+    let mut select = Query::select();
+    group_select(&mut select, schema, group_by, condition)?;
+    let count = Func::count(Expr::col(Asterisk));
+    select
+        .expr_as(Expr::col(Alias::new(field)), Alias::new("value"))
+        .expr_as(count.clone(), Alias::new("count"))
+        .add_group_by([Expr::col(Alias::new(field)).into()])
+        .order_by_expr(count.into(), Order::Desc);
+    Ok(DatabaseBackend::Postgres.build(&select))
+}
+
+/// 添加分组字段、查询条件和排序
+fn group_select(
+    select: &mut SelectStatement,
+    schema: &TableSchema,
+    group_by: &[String],
+    condition: Cond,
+) -> Result<()> {
+    if group_by.len() > MAX_GROUP_FIELDS {
+        bail!("最多按照{}个字段分组", MAX_GROUP_FIELDS)
+    }
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    schema.field(&deleted_at)?;
+    select
+        .from(Alias::new(&schema.table_name))
+        .and_where(Expr::col(Alias::new(&deleted_at)).is_null())
+        .cond_where(condition);
+    for (index, field) in group_by.iter().enumerate() {
+        schema.field(field)?;
+        select
+            .expr_as(
+                Expr::col(Alias::new(field)),
+                Alias::new(format!("g{}", index)),
+            )
+            .add_group_by([Expr::col(Alias::new(field)).into()])
+            .order_by(Alias::new(field), Order::Asc);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::TableField;
+
+    fn schema() -> TableSchema {
+        let field = |name: &str, r#type: &str| TableField {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+        };
+        TableSchema {
+            table_name: "data_1".to_string(),
+            fields: vec![
+                field("name1", "varchar"),
+                field("name2", "numeric"),
+                field("deleted_at", "timestamp"),
+            ],
+        }
+    }
+
+    #[test]
+    fn compile_aggregate() {
+        let metrics = vec![AggregateMetric {
+            field: "name2".to_string(),
+            op: AggregateOp::Sum,
+        }];
+        let group_by = vec!["name1".to_string()];
+        let statement = aggregate_sql(&schema(), &group_by, &metrics, Cond::all()).unwrap();
+        assert_eq!(
+            statement.sql,
+            r#"SELECT "name1" AS "g0", COUNT(*) AS "count", CAST(SUM("name2") AS float8) AS "m0" FROM "data_1" WHERE "deleted_at" IS NULL GROUP BY "name1" ORDER BY "name1" ASC"#
+        );
+        let group_by = vec![
+            "name1".to_string(),
+            "name2".to_string(),
+            "name3".to_string(),
+        ];
+        assert!(aggregate_sql(&schema(), &group_by, &metrics, Cond::all()).is_err());
+    }
+}
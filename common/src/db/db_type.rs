@@ -1,4 +1,6 @@
 use chrono::NaiveDateTime;
+use sea_orm::sea_query::{Alias, ColumnDef, Keyword, SimpleExpr};
+use sea_orm::Value;
 use std::fmt::{Debug, Display, Formatter};
 
 #[derive(Debug)]
@@ -10,56 +12,107 @@ impl Display for Null {
     }
 }
 
+/// 数据表字段类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// 文本，varchar
+    Varchar,
+    /// 数值，numeric
+    Numeric,
+    /// 浮点数，double precision
+    Double,
+    /// json，jsonb
+    Jsonb,
+}
+
+impl ColumnType {
+    /// 生成字段定义
+    /// @param name 字段名
+    pub fn column_def(&self, name: &str) -> ColumnDef {
+        let mut column = ColumnDef::new(Alias::new(name));
+        match self {
+            ColumnType::Varchar => column.string(),
+            ColumnType::Numeric => column.decimal(),
+            ColumnType::Double => column.double(),
+            ColumnType::Jsonb => column.json_binary(),
+        };
+        column
+    }
+
+    /// 数据库中的类型名称，对应表结构查询返回的类型
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ColumnType::Varchar => "varchar",
+            ColumnType::Numeric => "numeric",
+            ColumnType::Double => "float8",
+            ColumnType::Jsonb => "jsonb",
+        }
+    }
+}
+
 pub trait DbType: Display + Debug {
-    fn display(&self) -> String;
+    /// 转换成绑定参数值，返回None代表NULL
+    fn value(&self) -> Option<Value>;
     fn source(&self) -> String {
         format!("{self}")
     }
+    /// 转换成sql表达式，值以绑定参数的形式传入
+    fn expr(&self) -> SimpleExpr {
+        match self.value() {
+            Some(value) => SimpleExpr::Value(value),
+            None => SimpleExpr::Keyword(Keyword::Null),
+        }
+    }
 }
 
 impl DbType for String {
-    fn display(&self) -> String {
-        format!("'{self}'")
+    fn value(&self) -> Option<Value> {
+        Some(self.clone().into())
     }
 }
 
 impl DbType for bool {
-    fn display(&self) -> String {
-        format!("{self}")
+    fn value(&self) -> Option<Value> {
+        Some((*self).into())
     }
 }
 
 impl DbType for i32 {
-    fn display(&self) -> String {
-        format!("{self}")
+    fn value(&self) -> Option<Value> {
+        Some((*self).into())
     }
 }
 impl DbType for f64 {
-    fn display(&self) -> String {
-        format!("{self}")
+    fn value(&self) -> Option<Value> {
+        Some((*self).into())
     }
 }
 impl DbType for i64 {
-    fn display(&self) -> String {
-        format!("{self}")
+    fn value(&self) -> Option<Value> {
+        Some((*self).into())
     }
 }
 
 impl DbType for f32 {
-    fn display(&self) -> String {
-        format!("{self}")
+    fn value(&self) -> Option<Value> {
+        Some((*self).into())
     }
 }
 
 impl DbType for NaiveDateTime {
-    fn display(&self) -> String {
-        format!("'{self}'")
+    fn value(&self) -> Option<Value> {
+        Some((*self).into())
     }
 }
 
-impl DbType for Null {
-    fn display(&self) -> String {
-        "NUlL".to_string()
+impl DbType for serde_json::Value {
+    fn value(&self) -> Option<Value> {
+        Some(self.clone().into())
     }
 }
 
+impl DbType for Null {
+    fn value(&self) -> Option<Value> {
+        None
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{Alias, Cond, Expr, SimpleExpr};
+use sea_orm::Value as DbValue;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::db::schema::{TableField, TableSchema};
+
+/// 查询条件树，例如：
+/// {"and":[{"field":"name21","op":"eq","value":"银川"},{"field":"status","op":"in","value":[0,1]}]}
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FilterTree {
+    And { and: Vec<FilterTree> },
+    Or { or: Vec<FilterTree> },
+    Field(FieldFilter),
+}
+
+/// 字段条件
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldFilter {
+    pub field: String,
+    pub op: FilterOp,
+    #[serde(default)]
+    pub value: Value,
+}
+
+/// 条件操作符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Like,
+    In,
+    Between,
+    IsNull,
+}
+
+impl FilterTree {
+    /// 创建字段条件
+    /// @param field 字段名
+    /// @param op 操作符
+    /// @param value 条件值
+    pub fn field(field: &str, op: FilterOp, value: Value) -> Self {
+        FilterTree::Field(FieldFilter {
+            field: field.to_string(),
+            op,
+            value,
+        })
+    }
+
+    /// 转换成sql条件，字段根据表结构校验，值使用绑定参数
+    /// @param schema 表结构
+    pub fn condition(&self, schema: &TableSchema) -> Result<Cond> {
+        let condition = match self {
+            FilterTree::And { and } => {
+                let mut condition = Cond::all();
+                for item in and.iter() {
+                    condition = condition.add(item.condition(schema)?);
+                }
+                condition
+            }
+            FilterTree::Or { or } => {
+                let mut condition = Cond::any();
+                for item in or.iter() {
+                    condition = condition.add(item.condition(schema)?);
+                }
+                condition
+            }
+            FilterTree::Field(filter) => Cond::all().add(filter.expr(schema)?),
+        };
+        Ok(condition)
+    }
+}
+
+impl FieldFilter {
+    /// 转换成sql表达式
+    /// @param schema 表结构
+    pub fn expr(&self, schema: &TableSchema) -> Result<SimpleExpr> {
+        let column = schema.field(&self.field)?;
+        let col = Expr::col(Alias::new(&self.field));
+        let expr = match self.op {
+            FilterOp::Eq => match json_to_value(&self.value) {
+                None => col.is_null(),
+                Some(value) => col.eq(column.cast(Some(value))),
+            },
+            FilterOp::Ne => match json_to_value(&self.value) {
+                None => col.is_not_null(),
+                Some(value) => col.ne(column.cast(Some(value))),
+            },
+            FilterOp::Lt => col.lt(self.scalar(column, &self.value)?),
+            FilterOp::Gt => col.gt(self.scalar(column, &self.value)?),
+            FilterOp::Like => match &self.value {
+                // 非文本字段转换成文本后匹配
+                Value::String(pattern) => {
+                    Expr::expr(col.cast_as(Alias::new("text"))).like(pattern.as_str())
+                }
+                _ => bail!("字段：{}，like条件值必须是字符串", self.field),
+            },
+            FilterOp::In => match &self.value {
+                Value::Array(values) if !values.is_empty() => {
+                    let mut items = Vec::new();
+                    for value in values.iter() {
+                        items.push(self.scalar(column, value)?);
+                    }
+                    col.is_in(items)
+                }
+                _ => bail!("字段：{}，in条件值必须是非空数组", self.field),
+            },
+            FilterOp::Between => match &self.value {
+                Value::Array(values) if values.len() == 2 => col.between(
+                    self.scalar(column, &values[0])?,
+                    self.scalar(column, &values[1])?,
+                ),
+                _ => bail!("字段：{}，between条件值必须是两个元素的数组", self.field),
+            },
+            FilterOp::IsNull => match &self.value {
+                Value::Bool(false) => col.is_not_null(),
+                Value::Null | Value::Bool(true) => col.is_null(),
+                _ => bail!("字段：{}，is_null条件值必须是布尔值", self.field),
+            },
+        };
+        Ok(expr)
+    }
+
+    /// 转换非空的简单值
+    fn scalar(&self, column: &TableField, value: &Value) -> Result<SimpleExpr> {
+        match value {
+            Value::Null | Value::Array(_) | Value::Object(_) => {
+                bail!("字段：{}，条件值必须是字符串、数字或者布尔值", self.field)
+            }
+            _ => Ok(column.cast(json_to_value(value))),
+        }
+    }
+}
+
+/// json值转换成绑定参数值，null返回None
+pub fn json_to_value(value: &Value) -> Option<DbValue> {
+    match value {
+        Value::Null => None,
+        Value::Bool(item) => Some((*item).into()),
+        Value::Number(item) => match item.as_i64() {
+            Some(number) => Some(number.into()),
+            None => item.as_f64().map(|number| number.into()),
+        },
+        Value::String(item) => Some(item.clone().into()),
+        _ => Some(value.clone().into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::sea_query::{PostgresQueryBuilder, Query};
+
+    fn schema() -> TableSchema {
+        let field = |name: &str, r#type: &str| TableField {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+        };
+        TableSchema {
+            table_name: "data_1".to_string(),
+            fields: vec![field("name21", "varchar"), field("status", "int4")],
+        }
+    }
+
+    #[test]
+    fn compile_filter_tree() {
+        let tree: FilterTree = serde_json::from_str(
+            r#"{"and":[{"field":"name21","op":"eq","value":"银川"},
+            {"or":[{"field":"status","op":"in","value":[0,1]},{"field":"status","op":"is_null"}]}]}"#,
+        )
+        .unwrap();
+        let (sql, values) = Query::select()
+            .column(Alias::new("name21"))
+            .from(Alias::new("data_1"))
+            .cond_where(tree.condition(&schema()).unwrap())
+            .build(PostgresQueryBuilder);
+        assert_eq!(
+            sql,
+            r#"SELECT "name21" FROM "data_1" WHERE "name21" = CAST($1 AS varchar) AND ("status" IN (CAST($2 AS int4), CAST($3 AS int4)) OR "status" IS NULL)"#
+        );
+        assert_eq!(values.0.len(), 3);
+    }
+
+    #[test]
+    fn reject_invalid_filter() {
+        let invalid = [
+            r#"{"field":"name21 or 1=1","op":"eq","value":"a"}"#,
+            r#"{"field":"status","op":"between","value":[1]}"#,
+            r#"{"field":"status","op":"in","value":[]}"#,
+        ];
+        for item in invalid.iter() {
+            let tree: FilterTree = serde_json::from_str(item).unwrap();
+            assert!(tree.condition(&schema()).is_err());
+        }
+        assert!(serde_json::from_str::<FilterTree>(r#"{"field":"status","op":"exists"}"#).is_err());
+    }
+}
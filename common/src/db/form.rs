@@ -3,12 +3,14 @@ use std::fmt::{Display, Formatter};
 
 use anyhow::{bail, Result};
 use chrono::Local;
+use sea_orm::Statement;
 use serde_json::Value;
 use tracing::info;
 use uuid::Uuid;
 
 use crate::db::{insert_data_sql, update_data_sql};
 use crate::db::db_type::{DbType, Null};
+use crate::db::schema::TableSchema;
 
 /// 获取完整表名
 /// @param table_id 表id
@@ -98,6 +100,80 @@ impl FormCommonField {
     }
 }
 
+/// 任务状态，保存在任务表status字段
+/// 状态流转：未分配 → 已分配 → 已提交 → 审核中 → 审核通过/驳回 → 重新提交
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// 未分配，没有采集
+    Unassigned,
+    /// 已提交
+    Submitted,
+    /// 已分配
+    Assigned,
+    /// 审核中
+    UnderReview,
+    /// 审核通过
+    Approved,
+    /// 驳回
+    Rejected,
+    /// 驳回后重新提交
+    Resubmitted,
+}
+
+impl TaskStatus {
+    /// 全部状态
+    pub const ALL: [TaskStatus; 7] = [
+        TaskStatus::Unassigned,
+        TaskStatus::Submitted,
+        TaskStatus::Assigned,
+        TaskStatus::UnderReview,
+        TaskStatus::Approved,
+        TaskStatus::Rejected,
+        TaskStatus::Resubmitted,
+    ];
+
+    /// 状态值，已有数据中0为未采集、1为已采集，保持不变
+    pub fn value(&self) -> i32 {
+        match self {
+            TaskStatus::Unassigned => 0,
+            TaskStatus::Submitted => 1,
+            TaskStatus::Assigned => 2,
+            TaskStatus::UnderReview => 3,
+            TaskStatus::Approved => 4,
+            TaskStatus::Rejected => 5,
+            TaskStatus::Resubmitted => 6,
+        }
+    }
+
+    /// 根据状态值获取状态，空值为未分配
+    /// @param value 状态值
+    pub fn from_value(value: Option<i64>) -> Result<Self> {
+        let value = value.unwrap_or(0);
+        match TaskStatus::ALL.iter().find(|item| item.value() as i64 == value) {
+            Some(status) => Ok(*status),
+            None => bail!("任务状态：{}，不正确", value),
+        }
+    }
+
+    /// 状态名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskStatus::Unassigned => "未分配",
+            TaskStatus::Submitted => "已提交",
+            TaskStatus::Assigned => "已分配",
+            TaskStatus::UnderReview => "审核中",
+            TaskStatus::Approved => "审核通过",
+            TaskStatus::Rejected => "驳回",
+            TaskStatus::Resubmitted => "重新提交",
+        }
+    }
+
+    /// 是否已经采集，提交后的状态都是已采集
+    pub fn is_collected(&self) -> bool {
+        !matches!(self, TaskStatus::Unassigned | TaskStatus::Assigned)
+    }
+}
+
 
 /// 解析表单数据对象
 pub fn parse_data(data: Value) -> Result<HashMap<String, Box<dyn DbType>>> {
@@ -136,29 +212,31 @@ fn parse_value(data: Value) -> Option<Box<dyn DbType>> {
         Value::String(item) => {
             Some(Box::new(item))
         }
-        _ => {
-            None
+        // 多选、图片等数据保存为json
+        Value::Array(_) | Value::Object(_) => {
+            Some(Box::new(data))
         }
     }
 }
 
 /// 解析value数据生成插入sql
-/// @param table_name 表名
+/// @param schema 表结构
 /// @param data 提交的数据
-/// @param is_data 是否是表单数据，如果不是，代表任务数据
+/// @param validate 验证数据是否满足要求
+/// @param push 添加其他字段
 pub fn parse_value_to_insert_sql<F, P>(
-    table_name: String,
+    schema: &TableSchema,
     data: Value,
     validate: F,
     push: P,
-) -> Result<(String, String)>
+) -> Result<(String, Statement)>
 where
     F: Fn(&HashMap<String, Box<dyn DbType>>) -> Result<()>,
     P: Fn(&mut Vec<String>, &mut Vec<Box<dyn DbType>>),
 {
     // 解析任务数据
     let map_data = parse_data(data)?;
-    // todo 检查字段是否有效
+    // 检查字段是否有效，表单数据已在服务层根据表单模板校验
     validate(&map_data)?;
     let mut headers = Vec::new();
     let mut columns = Vec::new();
@@ -186,39 +264,35 @@ where
     columns.push(Box::new(date));
     // 判断是否添加其他字段，
     push(&mut headers, &mut columns);
-    // 检查两个列表长度是否一致
-    if headers.len() != columns.len() {
-        bail!("表头列表和数据列表添加数量必须一致")
-    }
-    // 生成插入数据sql
-    let insert_sql = insert_data_sql(&table_name, &headers, &columns);
+    // 生成插入数据sql，字段根据表结构校验
+    let insert_sql = insert_data_sql(schema, &headers, &columns)?;
     info!("{}", insert_sql);
     Ok((uuid, insert_sql))
 }
 
 /// 解析value数据生成更新sql
-/// @param table_name 表名
+/// @param schema 表结构
 /// @param id 更新数据的编号
 /// @param data 提交的数据
 /// @param validate 验证数据是否满足要求
 pub fn parse_value_to_update_sql<F>(
-    table_name: String,
-    id: String,
+    schema: &TableSchema,
+    id: &str,
     data: Value,
     validate: F,
-) -> Result<String>
+) -> Result<Statement>
 where
     F: Fn(&HashMap<String, Box<dyn DbType>>) -> Result<()>,
 {
     // 解析任务数据
     let mut map_data = parse_data(data)?;
-    // todo 检查字段是否有效
+    // 检查字段是否有效，表单数据已在服务层根据表单模板校验
     validate(&map_data)?;
     // 任务表名
     // 插入时间值
     let date = Local::now().naive_local();
     map_data.insert(FormCommonField::UpdatedAt.to_string(), Box::new(date));
-    // 生成插入数据sql
-    let update_sql = update_data_sql(&table_name, &id, map_data);
+    // 生成更新数据sql，字段根据表结构校验
+    let update_sql = update_data_sql(schema, id, map_data)?;
     Ok(update_sql)
 }
\ No newline at end of file
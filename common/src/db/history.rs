@@ -0,0 +1,264 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::{bail, Result};
+use chrono::Local;
+use sea_orm::sea_query::{Alias, Expr, Query, ReturningClause, SimpleExpr};
+use sea_orm::{ConnectionTrait, DatabaseBackend, FromQueryResult, JsonValue, Statement};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::db::form::FormCommonField;
+
+/// 数据变更历史表名
+pub const HISTORY_TABLE_NAME: &str = "record_history";
+
+/// 批量语句返回的变更前数据字段名
+const BEFORE_FIELD: &str = "before";
+
+/// 批量语句返回的变更后数据字段名
+const AFTER_FIELD: &str = "after";
+
+/// 每条语句添加的变更历史数量，每条变更历史8个绑定参数
+const HISTORY_BATCH_SIZE: usize = 1000;
+
+/// 数据变更动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryAction {
+    /// 添加
+    Insert,
+    /// 更新
+    Update,
+    /// 删除，包括逻辑删除
+    Delete,
+}
+
+impl Display for HistoryAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HistoryAction::Insert => "insert",
+            HistoryAction::Update => "update",
+            HistoryAction::Delete => "delete",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 比较变更前后的数据，只保留变化的字段
+/// 变更前或者变更后不存在时，返回另一方的全部字段
+/// @param before 变更前的数据
+/// @param after 变更后的数据
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> (Option<Value>, Option<Value>) {
+    let (before, after) = match (before, after) {
+        (Some(Value::Object(before)), Some(Value::Object(after))) => (before, after),
+        (before, after) => return (before.cloned(), after.cloned()),
+    };
+    let mut changed_before = Map::new();
+    let mut changed_after = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new && !changed_before.contains_key(key) {
+            changed_before.insert(key.clone(), old.clone());
+            changed_after.insert(key.clone(), new.clone());
+        }
+    }
+    (
+        Some(Value::Object(changed_before)),
+        Some(Value::Object(changed_after)),
+    )
+}
+
+/// 撤销一次变更，将变更前的字段值写回数据
+/// @param data 当前数据，数据不存在时为空对象
+/// @param before 变更前的字段值
+pub fn revert(data: &mut Map<String, Value>, before: Option<&Value>) -> Result<()> {
+    match before {
+        Some(Value::Object(before)) => {
+            for (key, value) in before.iter() {
+                data.insert(key.clone(), value.clone());
+            }
+            Ok(())
+        }
+        _ => bail!("不能恢复到数据添加之前的版本"),
+    }
+}
+
+/// 添加变更历史sql函数
+/// @param table_name 数据表名
+/// @param record_id 数据编号
+/// @param action 变更动作
+/// @param user_id 操作用户编号
+/// @param before 变更前的数据
+/// @param after 变更后的数据
+pub fn history_sql(
+    table_name: &str,
+    record_id: &str,
+    action: HistoryAction,
+    user_id: Option<&str>,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> Result<Statement> {
+    history_batch_sql(
+        table_name,
+        action,
+        user_id,
+        &[(record_id.to_string(), before, after)],
+    )
+}
+
+/// 批量添加变更历史sql函数，多条变更历史生成一条插入语句
+/// @param table_name 数据表名
+/// @param action 变更动作
+/// @param user_id 操作用户编号
+/// @param records 数据编号、变更前和变更后的数据
+pub fn history_batch_sql(
+    table_name: &str,
+    action: HistoryAction,
+    user_id: Option<&str>,
+    records: &[(String, Option<Value>, Option<Value>)],
+) -> Result<Statement> {
+    let columns = [
+        "id",
+        "table_name",
+        "record_id",
+        "action",
+        "user_id",
+        "before",
+        "after",
+        "created_at",
+    ];
+    let created_at = Local::now().naive_local();
+    let mut insert = Query::insert();
+    insert
+        .into_table(Alias::new(HISTORY_TABLE_NAME))
+        .columns(columns.into_iter().map(Alias::new));
+    for (record_id, before, after) in records.iter() {
+        insert.values([
+            Uuid::new_v4().to_string().into(),
+            table_name.into(),
+            record_id.as_str().into(),
+            action.to_string().into(),
+            user_id.map(|user_id| user_id.to_string()).into(),
+            before.clone().into(),
+            after.clone().into(),
+            created_at.into(),
+        ])?;
+    }
+    Ok(DatabaseBackend::Postgres.build(&insert))
+}
+
+/// 批量语句返回数据编号和变更前后的整行数据，用于记录变更历史
+/// 变更前的数据使用子查询读取，子查询使用语句开始时的快照，读取到的是变更前的数据
+/// @param table_name 数据表名
+/// @param action 变更动作，添加时没有变更前的数据
+pub fn history_returning(table_name: &str, action: HistoryAction) -> ReturningClause {
+    let table = quote(table_name);
+    let id = quote(&FormCommonField::Id.to_string());
+    let mut exprs: Vec<SimpleExpr> = vec![
+        Expr::col(Alias::new(FormCommonField::Id.to_string())).into(),
+        Expr::cust(format!("to_jsonb({}) AS {}", table, quote(AFTER_FIELD))),
+    ];
+    if action != HistoryAction::Insert {
+        exprs.push(Expr::cust(format!(
+            r#"(SELECT to_jsonb("old") FROM {table} AS "old" WHERE "old".{id} = {table}.{id}) AS {}"#,
+            quote(BEFORE_FIELD)
+        )));
+    }
+    Query::returning().exprs(exprs)
+}
+
+/// 执行使用history_returning返回变更数据的批量语句，并记录每行数据的变更历史
+/// 变更前后的数据只保留变化的字段，返回变更的数据编号
+/// @param db 数据库事务
+/// @param statement 批量语句
+/// @param table_name 数据表名
+/// @param action 变更动作
+/// @param user_id 操作用户编号
+pub async fn execute_with_history<C>(
+    db: &C,
+    statement: Statement,
+    table_name: &str,
+    action: HistoryAction,
+    user_id: Option<&str>,
+) -> Result<Vec<String>>
+where
+    C: ConnectionTrait,
+{
+    let rows = JsonValue::find_by_statement(statement).all(db).await?;
+    let id_field = FormCommonField::Id.to_string();
+    let mut ids = Vec::with_capacity(rows.len());
+    let mut records = Vec::with_capacity(rows.len());
+    for row in rows.iter() {
+        let id = match row.get(&id_field) {
+            Some(Value::String(id)) => id.clone(),
+            Some(id) if !id.is_null() => id.to_string(),
+            _ => bail!("数据表：{}，变更的数据没有编号", table_name),
+        };
+        let before = row.get(BEFORE_FIELD).filter(|before| !before.is_null());
+        let (before, after) = diff(before, row.get(AFTER_FIELD));
+        ids.push(id.clone());
+        records.push((id, before, after));
+    }
+    for chunk in records.chunks(HISTORY_BATCH_SIZE) {
+        db.execute(history_batch_sql(table_name, action, user_id, chunk)?)
+            .await?;
+    }
+    Ok(ids)
+}
+
+/// 标识符加双引号
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diff_and_revert() {
+        let before = json!({"id": "1", "name": "a", "count": 1});
+        let after = json!({"id": "1", "name": "b", "count": 1, "remark": "c"});
+        let (changed_before, changed_after) = diff(Some(&before), Some(&after));
+        assert_eq!(changed_before, Some(json!({"name": "a", "remark": null})));
+        assert_eq!(changed_after, Some(json!({"name": "b", "remark": "c"})));
+        let mut data = after.as_object().unwrap().clone();
+        revert(&mut data, changed_before.as_ref()).unwrap();
+        assert_eq!(data.get("name"), Some(&json!("a")));
+        assert!(revert(&mut data, None).is_err());
+    }
+
+    #[test]
+    fn compile_returning() {
+        let mut update = Query::update();
+        update
+            .table(Alias::new("task_1"))
+            .value(Alias::new("user_id"), "2")
+            .returning(history_returning("task_1", HistoryAction::Update));
+        assert_eq!(
+            DatabaseBackend::Postgres.build(&update).sql,
+            r#"UPDATE "task_1" SET "user_id" = $1 RETURNING "id", to_jsonb("task_1") AS "after", (SELECT to_jsonb("old") FROM "task_1" AS "old" WHERE "old"."id" = "task_1"."id") AS "before""#
+        );
+        let statement = history_batch_sql(
+            "task_1",
+            HistoryAction::Update,
+            Some("1"),
+            &[
+                (
+                    "1".to_string(),
+                    Some(json!({"user_id": null})),
+                    Some(json!({"user_id": "2"})),
+                ),
+                (
+                    "2".to_string(),
+                    Some(json!({"user_id": "3"})),
+                    Some(json!({"user_id": "2"})),
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(statement.values.unwrap().0.len(), 16);
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{Alias, Expr, Keyword, SimpleExpr};
+use sea_orm::{ConnectionTrait, DatabaseBackend, FromQueryResult, Statement, Value};
+
+/// 查询表结构sql，表名使用绑定参数
+pub const TABLE_FIELD_SQL: &str = r#"
+select
+	a.attnum as "id",
+	a.attname::varchar as "name",
+	concat_ws('', t.typname, SUBSTRING(format_type(a.atttypid, a.atttypmod) from '\(.*\)')) as "type"
+from
+	pg_attribute a
+left join pg_description d on
+	d.objoid = a.attrelid
+	and d.objsubid = a.attnum
+left join pg_class c on
+	a.attrelid = c.oid
+left join pg_type t on
+	a.atttypid = t.oid
+where
+	a.attnum >= 0
+	and not a.attisdropped
+	and c.relname = $1
+order by
+	c.relname desc,
+	a.attnum asc
+"#;
+
+/// 生成查询表结构的语句
+/// @param table_name 表名
+pub fn table_field_statement(table_name: &str) -> Statement {
+    Statement::from_sql_and_values(DatabaseBackend::Postgres, TABLE_FIELD_SQL, [table_name.into()])
+}
+
+/// 表字段
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct TableField {
+    pub name: String,
+    pub r#type: String,
+}
+
+impl TableField {
+    /// 字段基础类型，去掉长度精度等修饰，例如 varchar(255) 返回 varchar
+    pub fn base_type(&self) -> &str {
+        match self.r#type.find('(') {
+            Some(index) => &self.r#type[..index],
+            None => &self.r#type,
+        }
+    }
+
+    /// 将提交的值转换成该字段类型对应的值，NULL和空字符串转换成NULL
+    /// @param value 参数值，None代表NULL
+    pub fn convert(&self, value: Option<Value>) -> Result<Option<Value>> {
+        let value = match value {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        let result = match self.base_type() {
+            "numeric" | "float4" | "float8" | "int2" | "int4" | "int8" => match value {
+                Value::String(Some(item)) => {
+                    let item = item.trim();
+                    if item.is_empty() {
+                        None
+                    } else if item.parse::<f64>().is_ok() {
+                        // 保留原始文本，由数据库转换，避免精度丢失
+                        Some(item.to_string().into())
+                    } else {
+                        bail!("字段：{}，值：{}，不是有效的数值", self.name, item)
+                    }
+                }
+                Value::Json(Some(item)) => match *item {
+                    serde_json::Value::Number(number) => Some(number.to_string().into()),
+                    item => bail!("字段：{}，值：{}，不是有效的数值", self.name, item),
+                },
+                Value::Bool(Some(item)) => {
+                    bail!("字段：{}，值：{}，不是有效的数值", self.name, item)
+                }
+                value => Some(value),
+            },
+            "json" | "jsonb" => match value {
+                // 字符串如果是json格式按照json保存，否则保存为json字符串
+                Value::String(Some(item)) => {
+                    if item.trim().is_empty() {
+                        None
+                    } else {
+                        let json = serde_json::from_str(&item)
+                            .unwrap_or(serde_json::Value::String(*item));
+                        Some(json.into())
+                    }
+                }
+                Value::Json(item) => Some(Value::Json(item)),
+                Value::Bool(Some(item)) => Some(serde_json::Value::Bool(item).into()),
+                Value::BigInt(Some(item)) => Some(serde_json::Value::from(item).into()),
+                Value::Int(Some(item)) => Some(serde_json::Value::from(item).into()),
+                Value::Double(Some(item)) => Some(serde_json::Value::from(item).into()),
+                Value::Float(Some(item)) => Some(serde_json::Value::from(item).into()),
+                value => Some(value),
+            },
+            "varchar" | "text" | "bpchar" => match value {
+                Value::Json(Some(item)) => match *item {
+                    serde_json::Value::String(item) => Some(item.into()),
+                    item => Some(item.to_string().into()),
+                },
+                Value::Bool(Some(item)) => Some(item.to_string().into()),
+                Value::BigInt(Some(item)) => Some(item.to_string().into()),
+                Value::Int(Some(item)) => Some(item.to_string().into()),
+                Value::Double(Some(item)) => Some(item.to_string().into()),
+                Value::Float(Some(item)) => Some(item.to_string().into()),
+                value => Some(value),
+            },
+            _ => Some(value),
+        };
+        Ok(result)
+    }
+
+    /// 转换提交的值并生成绑定参数表达式
+    /// @param value 参数值，None代表NULL
+    pub fn bind(&self, value: Option<Value>) -> Result<SimpleExpr> {
+        Ok(self.cast(self.convert(value)?))
+    }
+
+    /// 将绑定参数转换成该字段类型，NULL不做转换
+    /// @param value 参数值，None代表NULL
+    pub fn cast(&self, value: Option<Value>) -> SimpleExpr {
+        match value {
+            None => SimpleExpr::Keyword(Keyword::Null),
+            Some(value) => {
+                let base_type = self.base_type();
+                // 类型名称来自数据库系统表，仍然检查避免拼接非法字符
+                let is_valid = !base_type.is_empty()
+                    && base_type
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if is_valid {
+                    Expr::val(value).cast_as(Alias::new(base_type))
+                } else {
+                    Expr::val(value).into()
+                }
+            }
+        }
+    }
+}
+
+/// 表结构，用于在拼接标识符前校验表名和字段名
+#[derive(Debug, Clone)]
+pub struct TableSchema {
+    pub table_name: String,
+    pub fields: Vec<TableField>,
+}
+
+impl TableSchema {
+    /// 从数据库读取表结构
+    /// @param db 数据库连接
+    /// @param table_name 表名
+    pub async fn load<C>(db: &C, table_name: &str) -> Result<Self>
+    where
+        C: ConnectionTrait,
+    {
+        let fields = TableField::find_by_statement(table_field_statement(table_name))
+            .all(db)
+            .await?;
+        if fields.is_empty() {
+            bail!("数据表：{}，不存在", table_name)
+        }
+        Ok(Self {
+            table_name: table_name.to_string(),
+            fields,
+        })
+    }
+
+    /// 根据字段名查询字段，字段不存在返回错误
+    pub fn field(&self, name: &str) -> Result<&TableField> {
+        match self.fields.iter().find(|field| field.name == name) {
+            Some(field) => Ok(field),
+            None => bail!("字段：{}，在表{}中不存在", name, self.table_name),
+        }
+    }
+
+    /// 判断字段是否存在
+    pub fn contains(&self, name: &str) -> bool {
+        self.fields.iter().any(|field| field.name == name)
+    }
+
+    /// 检查字段列表是否都存在
+    pub fn check_fields(&self, names: &[String]) -> Result<()> {
+        for name in names.iter() {
+            self.field(name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(r#type: &str) -> TableField {
+        TableField {
+            name: "name1".to_string(),
+            r#type: r#type.to_string(),
+        }
+    }
+
+    #[test]
+    fn convert_value() {
+        let numeric = field("numeric(10,2)");
+        assert_eq!(
+            numeric.convert(Some("12.5".into())).unwrap(),
+            Some("12.5".into())
+        );
+        assert_eq!(numeric.convert(Some(" ".into())).unwrap(), None);
+        assert!(numeric.convert(Some("abc".into())).is_err());
+        let jsonb = field("jsonb");
+        assert_eq!(
+            jsonb.convert(Some(r#"["a","b"]"#.into())).unwrap(),
+            Some(serde_json::json!(["a", "b"]).into())
+        );
+        assert_eq!(
+            jsonb.convert(Some("a.png".into())).unwrap(),
+            Some(serde_json::json!("a.png").into())
+        );
+        assert_eq!(
+            field("varchar").convert(Some(1i64.into())).unwrap(),
+            Some("1".into())
+        );
+    }
+}
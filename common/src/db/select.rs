@@ -1,205 +1,169 @@
 use anyhow::{bail, Result};
 use chrono::Local;
+use sea_orm::sea_query::{Alias, Asterisk, Cond, Expr, LockType, Order, Query, SimpleExpr};
 use sea_orm::{
     ConnectionTrait, DatabaseBackend, ExecResult, FromQueryResult, JsonValue, SelectModel,
     SelectorRaw, Statement,
 };
 use serde_json::Value;
+use uuid::Uuid;
 
-use crate::db::db_type::DbType;
-use crate::db::form::{parse_value_to_insert_sql, parse_value_to_update_sql};
+use crate::db::filter::{FilterOp, FilterTree};
+use crate::db::form::{parse_value_to_insert_sql, parse_value_to_update_sql, FormCommonField};
+use crate::db::history::{diff, history_sql, HistoryAction};
+use crate::db::schema::{table_field_statement, TableSchema};
+use crate::db::version::{check_version, row_version};
+
+/// 待执行的操作
+#[derive(Debug, Clone)]
+enum CtsAction {
+    Find,
+    FindById(String),
+    TableField,
+    Delete(String, bool),
+    Update(String, Value),
+    Add(Value),
+}
 
 /// @param 参数1，表名
-/// @param 参数2，select 语句
+/// @param 参数2，查询字段
 /// @param 参数3，where 参数
+/// @param 参数4，排序参数
+/// 表名和字段名在执行前根据表结构校验，数据全部使用绑定参数
+/// 添加、更新和删除数据时记录变更历史，设置版本时更新和删除前检查数据版本
 pub struct CtsSelect {
     pub table_name: String,
-    pub sql: Option<String>,
     pub fields: Option<Vec<String>>,
-    pub wheres: Option<String>,
-    pub order_by: Option<String>,
+    pub wheres: Vec<FilterTree>,
+    pub order_by: Vec<(String, Order)>,
+    // 操作用户编号，记录在变更历史中
+    pub user_id: Option<String>,
+    // 客户端读取数据时的版本，和服务器版本不一致时不执行操作
+    pub version: Option<String>,
+    // 查询时锁定数据，直到事务结束
+    pub lock: bool,
+    action: Option<CtsAction>,
 }
 
 impl CtsSelect {
     pub fn table(table_name: &str) -> Self {
         Self {
             table_name: table_name.to_string(),
-            sql: None,
             fields: None,
-            wheres: None,
-            order_by: None,
+            wheres: Vec::new(),
+            order_by: Vec::new(),
+            user_id: None,
+            version: None,
+            lock: false,
+            action: None,
         }
     }
 
+    /// 设置操作用户
+    /// @param user_id 用户编号
+    pub fn user(&mut self, user_id: &str) -> &Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    /// 设置期望的数据版本，None不检查
+    /// @param version 数据版本
+    pub fn version(&mut self, version: Option<String>) -> &Self {
+        self.version = version;
+        self
+    }
+
+    /// 查询时使用FOR UPDATE锁定数据，需要在事务中执行
+    pub fn lock(&mut self) -> &Self {
+        self.lock = true;
+        self
+    }
+
     pub fn columns(&mut self, fields: Option<Vec<String>>) -> &Self {
         self.fields = fields;
         self
     }
 
-    pub fn filter(&mut self, express: &str) -> &Self {
-        match &self.wheres {
-            Some(wheres) => {
-                self.wheres = Some(format!("{} AND {}", wheres, express));
-            }
-            None => {
-                self.wheres = Some(format!(" WHERE {}", express));
-            }
-        }
+    /// 添加条件，多个条件之间使用and连接
+    /// @param filter 查询条件
+    pub fn filter(&mut self, filter: FilterTree) -> &Self {
+        self.wheres.push(filter);
         self
     }
 
+    /// 添加相等条件
+    /// @param field 字段名
+    /// @param value 字段值
+    pub fn filter_eq(&mut self, field: &str, value: Value) -> &Self {
+        self.filter(FilterTree::field(field, FilterOp::Eq, value))
+    }
+
     pub fn defualt_filter(&mut self) -> &Self {
-        match &self.wheres {
-            Some(wheres) => {
-                self.wheres = Some(format!("{} AND {}", wheres, "DELETED_AT IS NULL"));
-            }
-            None => {
-                self.wheres = Some(format!(" WHERE {}", "DELETED_AT IS NULL"));
-            }
-        }
-        self
+        self.filter(FilterTree::field(
+            &FormCommonField::DeletedAt.to_string(),
+            FilterOp::IsNull,
+            Value::Bool(true),
+        ))
     }
 
-    pub fn order_by(&mut self, express: &str) -> &Self {
-        match &self.order_by {
-            Some(order_by) => {
-                self.order_by = Some(format!("{}, {}", order_by, express));
-            }
-            None => {
-                self.order_by = Some(format!("ORDER BY {}", express));
-            }
-        }
-        self
+    /// 添加排序，格式为：字段名 [asc|desc]
+    /// @param express 排序表达式
+    pub fn order_by(&mut self, express: &str) -> Result<&Self> {
+        let items: Vec<&str> = express.split_whitespace().collect();
+        let order = match items.as_slice() {
+            [field] => (field.to_string(), Order::Asc),
+            [field, order] => match order.to_lowercase().as_str() {
+                "asc" => (field.to_string(), Order::Asc),
+                "desc" => (field.to_string(), Order::Desc),
+                _ => bail!("排序方式：{}，不正确", order),
+            },
+            _ => bail!("排序参数：{}，格式不正确", express),
+        };
+        self.order_by.push(order);
+        Ok(self)
     }
 
     /// 根据编号进行查询
+    /// @param db 数据库连接
     /// @param id 数据编号
     /// return SelectorRaw
-    pub fn select_by_id(&mut self, id: &str) -> SelectorRaw<SelectModel<Value>> {
-        // 条件判断
-        let wheres = self.wheres.clone().unwrap_or(String::from("WHERE 1=1"));
-        let sql = match &self.fields {
-            Some(fields) => {
-                let field_str = fields.join(",");
-                format!(
-                    "SELECT {} FROM {} {} AND id='{}'",
-                    field_str, self.table_name, wheres, id
-                )
-            }
-            None => {
-                format!(
-                    "SELECT * FROM {} {} AND id='{}'",
-                    self.table_name, wheres, id
-                )
-            }
-        };
-        JsonValue::find_by_statement(Statement::from_sql_and_values(
-            DatabaseBackend::Postgres,
-            sql,
-            [],
-        ))
+    pub async fn select_by_id<C>(&self, db: &C, id: &str) -> Result<SelectorRaw<SelectModel<Value>>>
+    where
+        C: ConnectionTrait,
+    {
+        let schema = TableSchema::load(db, &self.table_name).await?;
+        let statement = self.select_statement(&schema, Some(id))?;
+        Ok(JsonValue::find_by_statement(statement))
     }
 
     /// 查询数据
-    pub fn select(&self) -> SelectorRaw<SelectModel<Value>> {
-        let fields = self
-            .fields
-            .clone()
-            .unwrap_or(vec![String::from('*')])
-            .join(",");
-        let order_by = self.order_by.clone().unwrap_or("".to_string());
-        let sql = match &self.wheres {
-            Some(wheres) => {
-                format!(
-                    "SELECT {} FROM {} {} {} ",
-                    fields, self.table_name, wheres, order_by
-                )
-            }
-            None => {
-                let order_by = self.order_by.clone().unwrap_or("".to_string());
-                format!("SELECT {} FROM {} {}", fields, self.table_name, order_by)
-            }
-        };
-        JsonValue::find_by_statement(Statement::from_sql_and_values(
-            DatabaseBackend::Postgres,
-            sql,
-            [],
-        ))
+    /// @param db 数据库连接
+    pub async fn select<C>(&self, db: &C) -> Result<SelectorRaw<SelectModel<Value>>>
+    where
+        C: ConnectionTrait,
+    {
+        let schema = TableSchema::load(db, &self.table_name).await?;
+        let statement = self.select_statement(&schema, None)?;
+        Ok(JsonValue::find_by_statement(statement))
     }
 
     /// 根据编号进行查询
     /// @param id 数据编号
     /// return Self
     pub fn find_by_id(&mut self, id: &str) -> &Self {
-        // 条件判断
-        let wheres = self.wheres.clone().unwrap_or(String::from("WHERE 1=1"));
-
-        let sql = match &self.fields {
-            Some(fields) => {
-                let field_str = fields.join(",");
-                format!(
-                    "SELECT {} FROM {} {} AND id='{}'",
-                    field_str, self.table_name, wheres, id
-                )
-            }
-            None => {
-                format!(
-                    "SELECT * FROM {} {} AND id='{}'",
-                    self.table_name, wheres, id
-                )
-            }
-        };
-        self.sql = Some(sql);
+        self.action = Some(CtsAction::FindById(id.to_string()));
         self
     }
 
     /// 查询数据
     pub fn find(&mut self) -> &Self {
-        let fields = self
-            .fields
-            .clone()
-            .unwrap_or(vec![String::from('*')])
-            .join(",");
-        let order_by = self.order_by.clone().unwrap_or("".to_string());
-        let sql = match &self.wheres {
-            Some(wheres) => {
-                format!(
-                    "SELECT {} FROM {} {} {} ",
-                    fields, self.table_name, wheres, order_by
-                )
-            }
-            None => {
-                format!("SELECT {} FROM {} {}", fields, self.table_name, order_by)
-            }
-        };
-        self.sql = Some(sql);
+        self.action = Some(CtsAction::Find);
         self
     }
 
     /// 查询表结构
     pub fn find_table_field(&mut self) -> &Self {
-        let sql = r#"
-            select
-	a.attnum as "id",
-	a.attname as "name",
-	concat_ws('', t.typname, SUBSTRING(format_type(a.atttypid, a.atttypmod) from '\(.*\)')) as "type"
-from
-	pg_attribute a
-left join pg_description d on
-	d.objoid = a.attrelid
-	and d.objsubid = a.attnum
-left join pg_class c on
-	a.attrelid = c.oid
-left join pg_type t on
-	a.atttypid = t.oid
-where
-	a.attnum >= 0
-	and c.relname = '{}'
-order by
-	c.relname desc,
-	a.attnum asc
-        "#;
-        self.sql = Some(sql.replace("{}", &self.table_name));
+        self.action = Some(CtsAction::TableField);
         self
     }
 
@@ -207,21 +171,7 @@ order by
     /// @param id 数据编号
     /// @param force 是否彻底删除
     pub fn delete_by_id(&mut self, id: &str, force: bool) -> &Self {
-        self.sql = match force {
-            true => Some(format!(
-                "DELETE FROM {} WHERE id= '{}'",
-                self.table_name, id
-            )),
-            false => {
-                let date = Local::now().naive_local();
-                Some(format!(
-                    "UPDATE {} SET DELETED_AT={} WHERE id= '{}'",
-                    self.table_name,
-                    date.display(),
-                    id
-                ))
-            }
-        };
+        self.action = Some(CtsAction::Delete(id.to_string(), force));
         self
     }
 
@@ -229,9 +179,10 @@ order by
     /// @param id 数据编号
     /// @param data 数据
     pub fn update(&mut self, id: &str, data: Value) -> Result<&Self> {
-        let sql =
-            parse_value_to_update_sql(self.table_name.clone(), id.to_string(), data, |_| Ok(()))?;
-        self.sql = Some(sql);
+        if !data.is_object() {
+            bail!("data 数据必须是HashMap格式")
+        }
+        self.action = Some(CtsAction::Update(id.to_string(), data));
         Ok(self)
     }
 
@@ -242,72 +193,289 @@ order by
     where
         F: FnMut(&String),
     {
-        let (id, sql) = parse_value_to_insert_sql(
-            self.table_name.clone(),
-            data,
-            |_| Ok(()),
-            |_hearders, _columns| {
-                // hearders.push(FormCommonField::Status.to_string());
-                // columns.push(Box::new(0));
-            },
-        )?;
-        self.sql = Some(sql);
+        let mut data = match data {
+            Value::Object(data) => data,
+            _ => bail!("data 数据必须是HashMap格式"),
+        };
+        // 编号不存在生成uuid
+        let id_field = FormCommonField::Id.to_string();
+        let id = match data.get(&id_field) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => Uuid::new_v4().to_string(),
+        };
+        data.insert(id_field, Value::String(id.clone()));
+        self.action = Some(CtsAction::Add(Value::Object(data)));
         hande_id(&id);
         Ok(self)
     }
+
     /// 查询数据，返回单条数据
-    pub async fn one<'a, C>(&self, db: &'a C) -> Result<Option<Value>>
+    pub async fn one<C>(&self, db: &C) -> Result<Option<Value>>
     where
         C: ConnectionTrait,
     {
-        match &self.sql {
-            None => {
-                bail!("请先执行find_by_id")
-            }
-            Some(data) => {
-                let select = JsonValue::find_by_statement(Statement::from_sql_and_values(
-                    DatabaseBackend::Postgres,
-                    data,
-                    [],
-                ));
-                let result = select.one(db).await?;
-                Ok(result)
+        let statement = match &self.action {
+            Some(CtsAction::Find) | Some(CtsAction::FindById(_)) | Some(CtsAction::TableField) => {
+                self.statement(db).await?
             }
-        }
+            _ => bail!("请先执行find_by_id"),
+        };
+        let result = JsonValue::find_by_statement(statement).one(db).await?;
+        Ok(result)
     }
+
     /// 查询所有数据，根据查询条件进行过滤
-    pub async fn all<'a, C>(&self, db: &'a C) -> Result<Vec<Value>>
+    pub async fn all<C>(&self, db: &C) -> Result<Vec<Value>>
+    where
+        C: ConnectionTrait,
+    {
+        let statement = match &self.action {
+            Some(CtsAction::Find) | Some(CtsAction::FindById(_)) | Some(CtsAction::TableField) => {
+                self.statement(db).await?
+            }
+            _ => bail!("请先执行find方法"),
+        };
+        let result = JsonValue::find_by_statement(statement).all(db).await?;
+        Ok(result)
+    }
+
+    /// 执行添加、更新和删除操作，并记录变更前后的数据
+    pub async fn execute<C>(&self, db: &C) -> Result<ExecResult>
     where
         C: ConnectionTrait,
     {
-        match &self.sql {
-            None => {
-                bail!("请先执行find方法")
+        let (action, id, history_action) = match &self.action {
+            Some(action @ CtsAction::Add(data)) => {
+                let id = data.get(FormCommonField::Id.to_string());
+                let id = id.and_then(|id| id.as_str()).unwrap_or_default();
+                (action, id.to_string(), HistoryAction::Insert)
             }
-            Some(data) => {
-                let select = JsonValue::find_by_statement(Statement::from_sql_and_values(
-                    DatabaseBackend::Postgres,
-                    data,
-                    [],
-                ));
-                let result = select.all(db).await?;
-                Ok(result)
+            Some(action @ CtsAction::Update(id, _)) => (action, id.clone(), HistoryAction::Update),
+            Some(action @ CtsAction::Delete(id, _)) => (action, id.clone(), HistoryAction::Delete),
+            _ => bail!("请先执行delete_by_id或者update方法"),
+        };
+        let schema = TableSchema::load(db, &self.table_name).await?;
+        let before = match history_action {
+            HistoryAction::Insert => None,
+            _ => {
+                let lock = self.version.is_some();
+                let before = self.row_by_id(db, &schema, &id, lock).await?;
+                if let Some(before) = &before {
+                    let current = row_version(before);
+                    check_version(self.version.as_deref(), current.as_deref(), before)?;
+                }
+                before
             }
+        };
+        let statement = self.action_statement(&schema, action)?;
+        let result = db.execute(statement).await?;
+        let after = self.row_by_id(db, &schema, &id, false).await?;
+        // 数据不存在时不记录
+        if before.is_some() || after.is_some() {
+            let (before, after) = diff(before.as_ref(), after.as_ref());
+            let statement = history_sql(
+                &self.table_name,
+                &id,
+                history_action,
+                self.user_id.as_deref(),
+                before,
+                after,
+            )?;
+            db.execute(statement).await?;
         }
+        Ok(result)
     }
 
-    pub async fn execute<'a, C>(&self, db: &'a C) -> Result<ExecResult>
+    /// 根据编号查询完整数据，包括删除的数据
+    /// @param lock 是否锁定数据，在事务中检查版本时使用
+    async fn row_by_id<C>(
+        &self,
+        db: &C,
+        schema: &TableSchema,
+        id: &str,
+        lock: bool,
+    ) -> Result<Option<Value>>
     where
         C: ConnectionTrait,
     {
-        match &self.sql {
-            None => {
-                bail!("请先执行delete_by_id或者update方法")
+        let mut query = Query::select();
+        query
+            .column(Asterisk)
+            .from(Alias::new(&schema.table_name))
+            .and_where(id_expr(schema, id)?);
+        if lock {
+            query.lock(LockType::Update);
+        }
+        let statement = DatabaseBackend::Postgres.build(&query);
+        let result = JsonValue::find_by_statement(statement).one(db).await?;
+        Ok(result)
+    }
+
+    /// 根据操作生成语句，表结构查询不需要校验表名
+    async fn statement<C>(&self, db: &C) -> Result<Statement>
+    where
+        C: ConnectionTrait,
+    {
+        let action = match &self.action {
+            None => bail!("请先设置查询或者执行操作"),
+            Some(CtsAction::TableField) => return Ok(table_field_statement(&self.table_name)),
+            Some(action) => action,
+        };
+        let schema = TableSchema::load(db, &self.table_name).await?;
+        self.action_statement(&schema, action)
+    }
+
+    /// 根据操作和表结构生成语句
+    fn action_statement(&self, schema: &TableSchema, action: &CtsAction) -> Result<Statement> {
+        match action {
+            CtsAction::Find => self.select_statement(schema, None),
+            CtsAction::FindById(id) => self.select_statement(schema, Some(id)),
+            CtsAction::Delete(id, force) => self.delete_statement(schema, id, *force),
+            CtsAction::Update(id, data) => {
+                parse_value_to_update_sql(schema, id, data.clone(), |_| Ok(()))
+            }
+            CtsAction::Add(data) => {
+                let (_, statement) = parse_value_to_insert_sql(
+                    schema,
+                    data.clone(),
+                    |_| Ok(()),
+                    |_hearders, _columns| {
+                        // hearders.push(FormCommonField::Status.to_string());
+                        // columns.push(Box::new(0));
+                    },
+                )?;
+                Ok(statement)
+            }
+            CtsAction::TableField => Ok(table_field_statement(&self.table_name)),
+        }
+    }
+
+    /// 生成查询语句
+    /// @param schema 表结构
+    /// @param id 数据编号，None查询全部
+    fn select_statement(&self, schema: &TableSchema, id: Option<&str>) -> Result<Statement> {
+        let mut query = Query::select();
+        match &self.fields {
+            Some(fields) if !fields.is_empty() => {
+                schema.check_fields(fields)?;
+                query.columns(fields.iter().map(Alias::new));
             }
-            Some(data) => {
-                let result = db.execute_unprepared(data).await?;
-                Ok(result)
+            _ => {
+                query.column(Asterisk);
             }
         }
+        query.from(Alias::new(&schema.table_name));
+        query.cond_where(self.condition(schema)?);
+        if let Some(id) = id {
+            query.and_where(id_expr(schema, id)?);
+        }
+        for (field, order) in self.order_by.iter() {
+            schema.field(field)?;
+            query.order_by(Alias::new(field), order.clone());
+        }
+        if self.lock {
+            query.lock(LockType::Update);
+        }
+        Ok(DatabaseBackend::Postgres.build(&query))
+    }
+
+    /// 生成删除语句，不是强制删除时更新删除时间
+    fn delete_statement(&self, schema: &TableSchema, id: &str, force: bool) -> Result<Statement> {
+        let statement = match force {
+            true => {
+                let mut delete = Query::delete();
+                delete
+                    .from_table(Alias::new(&schema.table_name))
+                    .and_where(id_expr(schema, id)?);
+                DatabaseBackend::Postgres.build(&delete)
+            }
+            false => {
+                let deleted_at = FormCommonField::DeletedAt.to_string();
+                let date = Local::now().naive_local();
+                let mut update = Query::update();
+                update
+                    .table(Alias::new(&schema.table_name))
+                    .value(
+                        Alias::new(&deleted_at),
+                        schema.field(&deleted_at)?.cast(Some(date.into())),
+                    )
+                    .and_where(id_expr(schema, id)?);
+                DatabaseBackend::Postgres.build(&update)
+            }
+        };
+        Ok(statement)
+    }
+
+    /// 生成where条件
+    fn condition(&self, schema: &TableSchema) -> Result<Cond> {
+        let mut condition = Cond::all();
+        for filter in self.wheres.iter() {
+            condition = condition.add(filter.condition(schema)?);
+        }
+        Ok(condition)
+    }
+}
+
+/// 编号条件
+fn id_expr(schema: &TableSchema, id: &str) -> Result<SimpleExpr> {
+    let id_field = FormCommonField::Id.to_string();
+    let column = schema.field(&id_field)?;
+    Ok(Expr::col(Alias::new(&id_field)).eq(column.cast(Some(id.into()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::TableField;
+
+    fn schema() -> TableSchema {
+        let field = |name: &str, r#type: &str| TableField {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+        };
+        TableSchema {
+            table_name: "task_1".to_string(),
+            fields: vec![
+                field("id", "varchar"),
+                field("name", "varchar"),
+                field("status", "int4"),
+                field("deleted_at", "timestamp"),
+            ],
+        }
+    }
+
+    #[test]
+    fn select_with_bound_values() {
+        let mut select = CtsSelect::table("task_1");
+        select.filter_eq("name", Value::String("a' or '1'='1".to_string()));
+        select.defualt_filter();
+        select.order_by("status desc").unwrap();
+        let statement = select.select_statement(&schema(), Some("1")).unwrap();
+        assert_eq!(
+            statement.sql,
+            r#"SELECT * FROM "task_1" WHERE "name" = CAST($1 AS varchar) AND "deleted_at" IS NULL AND "id" = CAST($2 AS varchar) ORDER BY "status" DESC"#
+        );
+        assert_eq!(statement.values.unwrap().0.len(), 2);
+    }
+
+    #[test]
+    fn select_for_update() {
+        let mut select = CtsSelect::table("task_1");
+        select.defualt_filter();
+        select.lock();
+        let statement = select.select_statement(&schema(), Some("1")).unwrap();
+        assert_eq!(
+            statement.sql,
+            r#"SELECT * FROM "task_1" WHERE "deleted_at" IS NULL AND "id" = CAST($1 AS varchar) FOR UPDATE"#
+        );
+    }
+
+    #[test]
+    fn reject_unknown_field() {
+        let mut select = CtsSelect::table("task_1");
+        select.filter_eq("name; drop table task_1", Value::Null);
+        assert!(select.select_statement(&schema(), None).is_err());
+        assert!(select.order_by("status desc, id").is_err());
     }
 }
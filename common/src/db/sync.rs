@@ -0,0 +1,254 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use sea_orm::sea_query::{Alias, Asterisk, Expr, Func, Order, Query, SimpleExpr};
+use sea_orm::{DatabaseBackend, Statement};
+
+use crate::db::form::FormCommonField;
+use crate::db::schema::TableSchema;
+
+/// 同步游标格式
+pub const CURSOR_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.6f";
+
+/// 同步游标比数据库当前时间提前的秒数
+/// 变更时间在事务开始时生成，提交较晚的事务修改的数据变更时间可能早于本次游标，
+/// 游标提前后这些数据在下次同步时返回，执行时间超过该值的事务仍然可能遗漏
+pub const CURSOR_LAG_SECONDS: i64 = 60;
+
+/// 数据最后变更时间，取创建、更新和删除时间中最大的值
+fn change_time() -> SimpleExpr {
+    Func::cust(Alias::new("GREATEST"))
+        .args(
+            [
+                FormCommonField::CreatedAt,
+                FormCommonField::UpdatedAt,
+                FormCommonField::DeletedAt,
+            ]
+            .map(|field| Expr::col(Alias::new(field.to_string())).into()),
+        )
+        .into()
+}
+
+/// 本次同步游标sql函数，使用数据库当前时间减去提前的秒数，不使用应用服务器时间
+/// 变更时间使用本地时间，数据库时区需要和应用服务器一致
+pub fn cursor_sql() -> Statement {
+    Statement::from_string(
+        DatabaseBackend::Postgres,
+        format!(
+            "SELECT LOCALTIMESTAMP - INTERVAL '{} seconds' AS cursor",
+            CURSOR_LAG_SECONDS
+        ),
+    )
+}
+
+/// 同步位置，变更时间和编号，编号为空时该时间之前的数据已经全部同步
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncPosition {
+    pub time: NaiveDateTime,
+    pub id: Option<String>,
+}
+
+impl SyncPosition {
+    /// 解析同步位置，格式为时间或者时间|编号
+    fn parse(value: &str) -> Result<Self> {
+        let (time, id) = match value.split_once('|') {
+            Some((time, id)) => (time, Some(id.to_string())),
+            None => (value, None),
+        };
+        match NaiveDateTime::parse_from_str(time, CURSOR_FORMAT) {
+            Ok(time) => Ok(SyncPosition { time, id }),
+            Err(_) => bail!("同步游标：{}，格式不正确", value),
+        }
+    }
+}
+
+impl Display for SyncPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.time.format(CURSOR_FORMAT))?;
+        match &self.id {
+            Some(id) => write!(f, "|{}", id),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 同步游标，任务表和数据表分别记录同步位置，格式为任务位置;数据位置
+/// 只有时间的游标任务表和数据表使用同一个位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncCursor {
+    pub tasks: SyncPosition,
+    pub data: SyncPosition,
+}
+
+impl SyncCursor {
+    /// 解析同步游标
+    /// @param value 上次同步返回的游标
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.split_once(';') {
+            Some((tasks, data)) => Ok(SyncCursor {
+                tasks: SyncPosition::parse(tasks)?,
+                data: SyncPosition::parse(data)?,
+            }),
+            None => {
+                let position = SyncPosition::parse(value)?;
+                Ok(SyncCursor {
+                    tasks: position.clone(),
+                    data: position,
+                })
+            }
+        }
+    }
+
+    /// 任务表和数据表中较早的同步时间
+    pub fn since(&self) -> NaiveDateTime {
+        self.tasks.time.min(self.data.time)
+    }
+}
+
+impl Display for SyncCursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{};{}", self.tasks, self.data)
+    }
+}
+
+/// 增量同步查询sql函数，查询同步位置之后、变更时间不超过until的数据，按照变更时间和编号排序
+/// since为空时为首次同步，只查询没有删除的数据，否则包括删除的数据
+/// @param schema 表结构
+/// @param since 上次同步位置
+/// @param until 本次同步游标
+/// @param user_id 只查询该用户的数据，为空时查询全部数据
+/// @param limit 查询数量
+pub fn changed_sql(
+    schema: &TableSchema,
+    since: Option<&SyncPosition>,
+    until: NaiveDateTime,
+    user_id: Option<&str>,
+    limit: u64,
+) -> Result<Statement> {
+    for field in [
+        FormCommonField::Id,
+        FormCommonField::CreatedAt,
+        FormCommonField::UpdatedAt,
+        FormCommonField::DeletedAt,
+    ] {
+        schema.field(&field.to_string())?;
+    }
+    let id = || Expr::col(Alias::new(FormCommonField::Id.to_string()));
+    let cast = |date: NaiveDateTime| Expr::val(date).cast_as(Alias::new("timestamp"));
+    let mut select = Query::select();
+    select
+        .column(Asterisk)
+        .from(Alias::new(&schema.table_name))
+        .and_where(Expr::expr(change_time()).lte(cast(until)))
+        .order_by_expr(change_time(), Order::Asc)
+        .order_by_expr(id().into(), Order::Asc)
+        .limit(limit);
+    match since {
+        // 同一时间变更的数据按照编号继续查询
+        Some(SyncPosition {
+            time,
+            id: Some(last),
+        }) => {
+            select.and_where(
+                Expr::tuple([change_time(), id().into()])
+                    .gt(Expr::tuple([cast(*time), Expr::val(last.as_str()).into()])),
+            );
+        }
+        Some(SyncPosition { time, id: None }) => {
+            select.and_where(Expr::expr(change_time()).gt(cast(*time)));
+        }
+        None => {
+            select
+                .and_where(Expr::col(Alias::new(FormCommonField::DeletedAt.to_string())).is_null());
+        }
+    }
+    if let Some(user_id) = user_id {
+        let field = FormCommonField::UserId.to_string();
+        let value = schema.field(&field)?.bind(Some(user_id.into()))?;
+        select.and_where(Expr::col(Alias::new(field)).eq(value));
+    }
+    Ok(DatabaseBackend::Postgres.build(&select))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::TableField;
+
+    #[test]
+    fn compile_cursor() {
+        assert_eq!(
+            cursor_sql().sql,
+            "SELECT LOCALTIMESTAMP - INTERVAL '60 seconds' AS cursor"
+        );
+    }
+
+    #[test]
+    fn parse_cursor() {
+        let time = "2024-06-01 08:00:00.000000";
+        let since = NaiveDateTime::parse_from_str(time, CURSOR_FORMAT).unwrap();
+        // 只有时间的游标任务表和数据表使用同一个位置
+        let cursor = SyncCursor::parse(time).unwrap();
+        assert_eq!(
+            cursor.tasks,
+            SyncPosition {
+                time: since,
+                id: None
+            }
+        );
+        assert_eq!(cursor.data, cursor.tasks);
+        assert_eq!(cursor.to_string(), format!("{};{}", time, time));
+        let value = format!("{}|t1;{}", time, time);
+        let cursor = SyncCursor::parse(&value).unwrap();
+        assert_eq!(cursor.tasks.id.as_deref(), Some("t1"));
+        assert_eq!(cursor.data.id, None);
+        assert_eq!(cursor.to_string(), value);
+        assert!(SyncCursor::parse("2024-06-01").is_err());
+    }
+
+    #[test]
+    fn compile_changed() {
+        let field = |name: &str, r#type: &str| TableField {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+        };
+        let schema = TableSchema {
+            table_name: "data_1".to_string(),
+            fields: vec![
+                field("id", "varchar"),
+                field("user_id", "varchar"),
+                field("created_at", "timestamp"),
+                field("updated_at", "timestamp"),
+                field("deleted_at", "timestamp"),
+            ],
+        };
+        let since =
+            NaiveDateTime::parse_from_str("2024-06-01 08:00:00.000000", CURSOR_FORMAT).unwrap();
+        let until =
+            NaiveDateTime::parse_from_str("2024-06-01 08:05:00.000000", CURSOR_FORMAT).unwrap();
+        let position = SyncPosition {
+            time: since,
+            id: None,
+        };
+        let statement = changed_sql(&schema, Some(&position), until, None, 100).unwrap();
+        assert_eq!(
+            statement.sql,
+            r#"SELECT * FROM "data_1" WHERE GREATEST("created_at", "updated_at", "deleted_at") <= CAST($1 AS timestamp) AND GREATEST("created_at", "updated_at", "deleted_at") > CAST($2 AS timestamp) ORDER BY GREATEST("created_at", "updated_at", "deleted_at") ASC, "id" ASC LIMIT $3"#
+        );
+        // 变更时间等于游标的数据在本次同步返回，下次同步从游标之后开始，不重复也不遗漏
+        let values = statement.values.unwrap().0;
+        assert_eq!(values[0], until.into());
+        assert_eq!(values[1], since.into());
+        // 分页时同一时间变更的数据按照编号继续查询，采集员只查询自己的数据
+        let position = SyncPosition {
+            time: since,
+            id: Some("d1".to_string()),
+        };
+        let statement = changed_sql(&schema, Some(&position), until, Some("u1"), 100).unwrap();
+        assert_eq!(
+            statement.sql,
+            r#"SELECT * FROM "data_1" WHERE GREATEST("created_at", "updated_at", "deleted_at") <= CAST($1 AS timestamp) AND (GREATEST("created_at", "updated_at", "deleted_at"), "id") > (CAST($2 AS timestamp), $3) AND "user_id" = CAST($4 AS varchar) ORDER BY GREATEST("created_at", "updated_at", "deleted_at") ASC, "id" ASC LIMIT $5"#
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::form::FormCommonField;
+use crate::error::ConflictError;
+
+/// 数据版本格式，精确到微秒
+pub const VERSION_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.6f";
+
+/// 数据版本，使用更新时间，没有更新时使用创建时间
+/// @param updated_at 更新时间
+/// @param created_at 创建时间
+pub fn version(updated_at: Option<NaiveDateTime>, created_at: NaiveDateTime) -> String {
+    updated_at
+        .unwrap_or(created_at)
+        .format(VERSION_FORMAT)
+        .to_string()
+}
+
+/// 动态表数据的版本，规则和实体数据相同
+/// @param row 数据
+pub fn row_version(row: &Value) -> Option<String> {
+    [FormCommonField::UpdatedAt, FormCommonField::CreatedAt]
+        .iter()
+        .find_map(|field| row.get(field.to_string())?.as_str())
+        .and_then(parse_version)
+        .map(|version| version.format(VERSION_FORMAT).to_string())
+}
+
+/// 解析客户端提交的版本，兼容日期和时间之间使用空格分隔的格式
+fn parse_version(version: &str) -> Option<NaiveDateTime> {
+    let version = version.trim();
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(version, format).ok())
+}
+
+/// 两个版本是否相同，按照时间比较
+/// @param a 版本
+/// @param b 版本
+pub fn same_version(a: &str, b: &str) -> bool {
+    match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// 检查客户端提交的版本，不一致时返回冲突错误和服务器当前数据
+/// 客户端没有提交版本时不检查
+/// @param expected 客户端提交的版本
+/// @param current 服务器当前版本
+/// @param row 服务器当前数据
+pub fn check_version<T>(expected: Option<&str>, current: Option<&str>, row: &T) -> Result<()>
+where
+    T: Serialize,
+{
+    let expected = match expected {
+        None => return Ok(()),
+        Some(expected) => expected,
+    };
+    if parse_version(expected).is_none() {
+        bail!("数据版本：{}，格式不正确", expected)
+    }
+    match current {
+        Some(current) if same_version(expected, current) => Ok(()),
+        _ => Err(ConflictError {
+            current: serde_json::to_value(row)?,
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn check_row_version() {
+        let row = json!({"id": "1", "created_at": "2024-06-01T08:00:00", "updated_at": "2024-06-01T09:00:00.1"});
+        let current = row_version(&row);
+        assert_eq!(current.as_deref(), Some("2024-06-01T09:00:00.100000"));
+        assert!(check_version(None, current.as_deref(), &row).is_ok());
+        assert!(check_version(Some("2024-06-01 09:00:00.100"), current.as_deref(), &row).is_ok());
+        let error = check_version(Some("2024-06-01T08:00:00"), current.as_deref(), &row);
+        let error = error.unwrap_err();
+        let conflict = error.downcast_ref::<ConflictError>().unwrap();
+        assert_eq!(conflict.current, row);
+        assert!(check_version(Some("abc"), current.as_deref(), &row).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{Alias, Cond, Expr, Query};
+use sea_orm::{DatabaseBackend, Statement};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{Config, Workflow};
+use crate::db::filter::{FilterOp, FilterTree};
+use crate::db::form::{FormCommonField, TaskStatus};
+use crate::db::history::{history_returning, HistoryAction};
+use crate::db::schema::TableSchema;
+
+/// 流转配置，从配置文件读取
+static WORKFLOW: OnceLock<Workflow> = OnceLock::new();
+
+/// 任务流转动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskAction {
+    /// 分配给采集人员
+    Assign,
+    /// 取消分配
+    Unassign,
+    /// 提交采集数据，驳回后提交为重新提交
+    Submit,
+    /// 开始审核
+    Review,
+    /// 审核通过
+    Approve,
+    /// 驳回，必须填写意见
+    Reject,
+}
+
+impl Display for TaskAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TaskAction::Assign => "assign",
+            TaskAction::Unassign => "unassign",
+            TaskAction::Submit => "submit",
+            TaskAction::Review => "review",
+            TaskAction::Approve => "approve",
+            TaskAction::Reject => "reject",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl TaskAction {
+    /// 执行动作后的状态，不允许的流转返回错误
+    /// @param status 当前状态
+    pub fn next(&self, status: TaskStatus) -> Result<TaskStatus> {
+        use TaskStatus::*;
+        let result = match (self, status) {
+            (TaskAction::Assign, Unassigned | Assigned) => Assigned,
+            (TaskAction::Unassign, Unassigned | Assigned) => Unassigned,
+            // 审核前可以重复提交
+            (TaskAction::Submit, Unassigned | Assigned | Submitted) => Submitted,
+            (TaskAction::Submit, Rejected | Resubmitted) => Resubmitted,
+            (TaskAction::Review, Submitted | Resubmitted) => UnderReview,
+            (TaskAction::Approve, Submitted | Resubmitted | UnderReview) => Approved,
+            // 审核通过后仍然可以驳回
+            (TaskAction::Reject, Submitted | Resubmitted | UnderReview | Approved) => Rejected,
+            (action, status) => bail!("任务状态：{}，不能执行：{}", status.name(), action),
+        };
+        Ok(result)
+    }
+
+    /// 是否必须填写意见
+    pub fn requires_comment(&self) -> bool {
+        matches!(self, TaskAction::Reject)
+    }
+
+    /// 可以执行该动作的任务状态条件，状态为空的任务作为未分配
+    pub fn status_filter(&self) -> FilterTree {
+        let status = FormCommonField::Status.to_string();
+        let values = TaskStatus::ALL
+            .iter()
+            .filter(|from| self.next(**from).is_ok())
+            .map(|from| Value::from(from.value()))
+            .collect();
+        let mut or = vec![FilterTree::field(&status, FilterOp::In, Value::Array(values))];
+        if self.next(TaskStatus::Unassigned).is_ok() {
+            or.push(FilterTree::field(&status, FilterOp::IsNull, Value::Bool(true)));
+        }
+        FilterTree::Or { or }
+    }
+
+    /// 是否必须配置允许的角色，审核相关的动作没有配置时所有角色都不能执行
+    pub fn requires_role(&self) -> bool {
+        matches!(
+            self,
+            TaskAction::Review | TaskAction::Approve | TaskAction::Reject
+        )
+    }
+
+    /// 角色是否可以执行该动作，使用配置文件中的流转配置
+    /// @param role_id 角色编号
+    /// @param role_name 角色名称
+    pub fn allowed(&self, role_id: &str, role_name: &str) -> bool {
+        let workflow = WORKFLOW.get_or_init(|| Config::init_config().workflow);
+        self.allowed_by(workflow, role_id, role_name)
+    }
+
+    /// 角色是否可以执行该动作，没有配置的审核动作所有角色都不能执行，其他动作所有角色都可以执行
+    /// @param workflow 流转配置
+    /// @param role_id 角色编号
+    /// @param role_name 角色名称
+    pub fn allowed_by(&self, workflow: &Workflow, role_id: &str, role_name: &str) -> bool {
+        match workflow.roles.get(&self.to_string()) {
+            None => !self.requires_role(),
+            Some(roles) => roles
+                .iter()
+                .any(|role| role == role_id || role == role_name),
+        }
+    }
+}
+
+/// 批量流转任务状态sql函数，只更新状态为from的未删除任务，返回记录变更历史的数据
+/// 状态为空的任务作为未分配
+/// @param schema 任务表结构
+/// @param from 流转前状态
+/// @param to 流转后状态
+/// @param condition 查询条件
+pub fn status_update_sql(
+    schema: &TableSchema,
+    from: TaskStatus,
+    to: TaskStatus,
+    condition: Cond,
+) -> Result<Statement> {
+    let status = FormCommonField::Status.to_string();
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    let value = schema.field(&status)?.bind(Some(to.value().into()))?;
+    schema.field(&deleted_at)?;
+    let mut from_condition = Cond::any().add(Expr::col(Alias::new(&status)).eq(from.value()));
+    if from == TaskStatus::Unassigned {
+        from_condition = from_condition.add(Expr::col(Alias::new(&status)).is_null());
+    }
+    let mut update = Query::update();
+    update
+        .table(Alias::new(&schema.table_name))
+        .value(Alias::new(&status), value)
+        .and_where(Expr::col(Alias::new(&deleted_at)).is_null())
+        .cond_where(from_condition)
+        .cond_where(condition)
+        .returning(history_returning(&schema.table_name, HistoryAction::Update));
+    Ok(DatabaseBackend::Postgres.build(&update))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::db::schema::TableField;
+
+    #[test]
+    fn task_lifecycle() {
+        let mut status = TaskStatus::Unassigned;
+        for (action, expected) in [
+            (TaskAction::Assign, TaskStatus::Assigned),
+            (TaskAction::Submit, TaskStatus::Submitted),
+            (TaskAction::Review, TaskStatus::UnderReview),
+            (TaskAction::Reject, TaskStatus::Rejected),
+            (TaskAction::Submit, TaskStatus::Resubmitted),
+            (TaskAction::Approve, TaskStatus::Approved),
+        ] {
+            status = action.next(status).unwrap();
+            assert_eq!(status, expected);
+        }
+        // 审核通过后不能提交和取消分配
+        assert!(TaskAction::Submit.next(TaskStatus::Approved).is_err());
+        assert!(TaskAction::Unassign.next(TaskStatus::Approved).is_err());
+        assert!(TaskAction::Review.next(TaskStatus::Assigned).is_err());
+    }
+
+    #[test]
+    fn action_roles() {
+        let workflow = Workflow {
+            roles: HashMap::from([("approve".to_string(), vec!["审核员".to_string()])]),
+        };
+        assert!(TaskAction::Approve.allowed_by(&workflow, "2", "审核员"));
+        assert!(!TaskAction::Approve.allowed_by(&workflow, "3", "采集员"));
+        assert!(TaskAction::Submit.allowed_by(&workflow, "3", "采集员"));
+        // 没有配置的审核动作所有角色都不能执行
+        assert!(!TaskAction::Reject.allowed_by(&workflow, "2", "审核员"));
+        assert!(!TaskAction::Review.allowed_by(&workflow, "1", "超级管理员"));
+    }
+
+    #[test]
+    fn compile_status_update() {
+        let field = |name: &str, r#type: &str| TableField {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+        };
+        let schema = TableSchema {
+            table_name: "task_1".to_string(),
+            fields: vec![
+                field("id", "varchar"),
+                field("status", "int4"),
+                field("deleted_at", "timestamp"),
+            ],
+        };
+        let statement = status_update_sql(
+            &schema,
+            TaskStatus::Unassigned,
+            TaskStatus::Assigned,
+            Cond::all(),
+        )
+        .unwrap();
+        let mut select = Query::select();
+        select
+            .column(Alias::new("id"))
+            .from(Alias::new("task_1"))
+            .cond_where(TaskAction::Unassign.status_filter().condition(&schema).unwrap());
+        assert_eq!(
+            DatabaseBackend::Postgres.build(&select).sql,
+            r#"SELECT "id" FROM "task_1" WHERE "status" IN (CAST($1 AS int4), CAST($2 AS int4)) OR "status" IS NULL"#
+        );
+        assert_eq!(
+            statement.sql,
+            r#"UPDATE "task_1" SET "status" = CAST($1 AS int4) WHERE "deleted_at" IS NULL AND ("status" = $2 OR "status" IS NULL) RETURNING "id", to_jsonb("task_1") AS "after", (SELECT to_jsonb("old") FROM "task_1" AS "old" WHERE "old"."id" = "task_1"."id") AS "before""#
+        );
+    }
+}
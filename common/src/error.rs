@@ -1,4 +1,8 @@
+use std::collections::BTreeMap;
+
 use sea_orm::DbErr;
+use serde::Serialize;
+use serde_json::Value;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,4 +15,99 @@ pub enum CtsError {
     Request(String),
     #[error("系统错误：{0}")]
     Custom(String),
-}
\ No newline at end of file
+}
+/// 数据校验错误，key为字段名，value为错误信息
+#[derive(Debug, Error)]
+#[error("数据校验失败：{}", .errors.values().cloned().collect::<Vec<String>>().join("；"))]
+pub struct ValidationError {
+    pub errors: BTreeMap<String, String>,
+}
+
+impl ValidationError {
+    pub fn new() -> Self {
+        Self {
+            errors: BTreeMap::new(),
+        }
+    }
+
+    /// 添加字段错误，同一字段只保留第一条错误
+    /// @param field 字段名
+    /// @param message 错误信息
+    pub fn add(&mut self, field: &str, message: String) {
+        self.errors.entry(field.to_string()).or_insert(message);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Default for ValidationError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 数据版本冲突错误，数据已经被其他用户修改，current为服务器当前数据
+#[derive(Debug, Error)]
+#[error("数据已经被其他用户修改，请刷新后重试")]
+pub struct ConflictError {
+    pub current: Value,
+}
+
+/// 文件存储错误
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("文件：{0}，不存在")]
+    NotFound(String),
+}
+
+/// 导入数据的行错误
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    // 行号，从1开始
+    pub line: u64,
+    pub message: String,
+}
+
+impl RowError {
+    pub fn new(line: u64, message: String) -> Self {
+        Self { line, message }
+    }
+}
+
+/// 导入数据错误，按行记录错误信息，超过最大数量后不再记录
+#[derive(Debug, Error)]
+#[error("数据导入失败：{}", .errors.iter().map(|item| format!("第{}行，{}", item.line, item.message)).collect::<Vec<String>>().join("；"))]
+pub struct ImportError {
+    pub errors: Vec<RowError>,
+}
+
+impl ImportError {
+    /// 最多记录的错误数量
+    pub const MAX_ERRORS: usize = 100;
+
+    pub fn new() -> Self {
+        Self { errors: Vec::new() }
+    }
+
+    pub fn add(&mut self, error: RowError) {
+        if !self.is_full() {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.errors.len() >= Self::MAX_ERRORS
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Default for ImportError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
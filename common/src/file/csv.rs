@@ -1,32 +1,372 @@
-pub struct Csv {
-    pub header: Vec<String>,
-    pub data: Vec<Vec<String>>,
-}
-
-impl Csv {
-    pub fn read(path: &str) -> Result<Csv, std::io::Error> {
-        let mut result_headers = Vec::new();
-        let mut result_data = Vec::new();
-        // 读取数据
-        let mut records = csv::ReaderBuilder::new().delimiter(b',').from_path(path)?;
-        // 读取数据表头
-        let headers = records.headers().unwrap();
-        // 收集表头
-        for header in headers.into_iter() {
-            result_headers.push(header.to_string())
-        }
-        // 收集数据
-        for record in records.records() {
-            let mut row = Vec::new();
-            for cell in record.unwrap().into_iter() {
-                row.push(cell.to_string());
+use anyhow::{bail, Result};
+use csv_core::{ReadRecordResult, Reader, ReaderBuilder};
+use encoding_rs::{Decoder, Encoding, GB18030, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::error::RowError;
+
+/// 检测编码和分隔符使用的样本大小
+const SAMPLE_SIZE: usize = 64 * 1024;
+/// 支持检测的分隔符，数量相同时优先使用靠前的
+const DELIMITERS: [u8; 3] = [b',', b';', b'\t'];
+
+/// csv记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvRecord {
+    // 记录开始的行号，从1开始
+    pub line: u64,
+    pub fields: Vec<String>,
+}
+
+/// 流式csv解析，按数据块写入，返回已经解析完成的记录
+/// 跨数据块的记录保留在缓冲区中，等待后续数据
+pub struct CsvDecoder {
+    reader: Reader,
+    output: Vec<u8>,
+    output_len: usize,
+    ends: Vec<usize>,
+    ends_len: usize,
+    line: u64,
+}
+
+impl CsvDecoder {
+    /// 创建解析对象
+    /// @param delimiter 分隔符
+    pub fn new(delimiter: u8) -> Self {
+        Self {
+            reader: ReaderBuilder::new().delimiter(delimiter).build(),
+            output: vec![0; 4096],
+            output_len: 0,
+            ends: vec![0; 64],
+            ends_len: 0,
+            line: 1,
+        }
+    }
+
+    /// 写入数据块，返回解析完成的记录
+    /// @param chunk 数据块
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Result<CsvRecord, RowError>> {
+        let mut result = Vec::new();
+        if !chunk.is_empty() {
+            self.read(chunk, &mut result);
+        }
+        result
+    }
+
+    /// 数据结束，返回最后一条没有换行结尾的记录
+    pub fn finish(&mut self) -> Vec<Result<CsvRecord, RowError>> {
+        let mut result = Vec::new();
+        self.read(&[], &mut result);
+        result
+    }
+
+    /// 解析数据，输入为空代表数据结束
+    fn read(&mut self, mut input: &[u8], result: &mut Vec<Result<CsvRecord, RowError>>) {
+        let is_end = input.is_empty();
+        loop {
+            let (state, nin, nout, nend) = self.reader.read_record(
+                input,
+                &mut self.output[self.output_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[nin..];
+            self.output_len += nout;
+            self.ends_len += nend;
+            match state {
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return,
+                ReadRecordResult::OutputFull => {
+                    let len = self.output.len() * 2;
+                    self.output.resize(len, 0);
+                }
+                ReadRecordResult::OutputEndsFull => {
+                    let len = self.ends.len() * 2;
+                    self.ends.resize(len, 0);
+                }
+                ReadRecordResult::Record => {
+                    result.push(self.record());
+                    // 输入已经读完时不能继续调用，空输入会被当作数据结束
+                    if input.is_empty() && !is_end {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 取出缓冲区中的记录，字段必须是utf-8编码
+    fn record(&mut self) -> Result<CsvRecord, RowError> {
+        let line = self.line;
+        self.line = self.reader.line();
+        let mut fields = Vec::with_capacity(self.ends_len);
+        let mut start = 0;
+        let mut result = Ok(());
+        for end in self.ends[..self.ends_len].iter() {
+            match std::str::from_utf8(&self.output[start..*end]) {
+                Ok(field) => fields.push(field.to_string()),
+                Err(_) => {
+                    result = Err(RowError::new(
+                        line,
+                        format!("第{}列不是utf-8编码", fields.len() + 1),
+                    ));
+                    break;
+                }
             }
-            result_data.push(row);
+            start = *end;
         }
-        let csv = Csv {
-            header: result_headers,
-            data: result_data,
+        self.output_len = 0;
+        self.ends_len = 0;
+        result.map(|_| CsvRecord { line, fields })
+    }
+}
+
+/// csv读取参数，为空时自动检测
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvOptions {
+    pub encoding: Option<&'static Encoding>,
+    pub delimiter: Option<u8>,
+}
+
+impl CsvOptions {
+    /// 解析编码和分隔符参数
+    /// @param encoding 编码名称，例如 utf-8、gbk、gb18030、utf-16le
+    /// @param delimiter 分隔符，单个字符，或者 comma、semicolon、tab
+    pub fn parse(encoding: Option<&str>, delimiter: Option<&str>) -> Result<Self> {
+        let encoding = match encoding {
+            None => None,
+            Some(label) => match Encoding::for_label(label.trim().as_bytes()) {
+                Some(encoding) => Some(encoding),
+                None => bail!("不支持的文件编码：{}", label),
+            },
         };
-        Ok(csv)
+        let delimiter = match delimiter {
+            None => None,
+            Some(delimiter) => Some(match delimiter.to_lowercase().as_str() {
+                "comma" => b',',
+                "semicolon" => b';',
+                "tab" | "\\t" => b'\t',
+                item if item.len() == 1 && item.is_ascii() => item.as_bytes()[0],
+                _ => bail!("不支持的分隔符：{}", delimiter),
+            }),
+        };
+        Ok(Self {
+            encoding,
+            delimiter,
+        })
+    }
+}
+
+/// 流式读取csv数据，先缓存样本检测编码和分隔符，然后转换成utf-8解析
+pub struct CsvReader {
+    options: CsvOptions,
+    // 检测前缓存的数据
+    sample: Vec<u8>,
+    // 编码转换和csv解析对象，检测完成后创建
+    state: Option<(Decoder, CsvDecoder)>,
+}
+
+impl CsvReader {
+    pub fn new(options: CsvOptions) -> Self {
+        Self {
+            options,
+            sample: Vec::new(),
+            state: None,
+        }
+    }
+
+    /// 写入数据块，返回解析完成的记录
+    /// @param chunk 数据块
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Result<CsvRecord, RowError>> {
+        if self.state.is_none() {
+            self.sample.extend_from_slice(chunk);
+            if self.sample.len() < SAMPLE_SIZE {
+                return Vec::new();
+            }
+            return self.start(false);
+        }
+        self.read(chunk, false)
+    }
+
+    /// 数据结束，返回剩余的记录
+    pub fn finish(&mut self) -> Vec<Result<CsvRecord, RowError>> {
+        if self.state.is_none() {
+            return self.start(true);
+        }
+        self.read(&[], true)
+    }
+
+    /// 使用样本检测编码和分隔符，然后解析样本
+    /// @param is_end 数据是否已经结束
+    fn start(&mut self, is_end: bool) -> Vec<Result<CsvRecord, RowError>> {
+        let sample = std::mem::take(&mut self.sample);
+        let encoding = self
+            .options
+            .encoding
+            .unwrap_or_else(|| detect_encoding(&sample, is_end));
+        let mut decoder = encoding.new_decoder_with_bom_removal();
+        let text = transcode(&mut decoder, &sample, is_end);
+        let delimiter = self
+            .options
+            .delimiter
+            .unwrap_or_else(|| detect_delimiter(&text));
+        let mut csv = CsvDecoder::new(delimiter);
+        let mut result = csv.push(text.as_bytes());
+        if is_end {
+            result.extend(csv.finish());
+        }
+        self.state = Some((decoder, csv));
+        result
+    }
+
+    /// 转换编码后解析
+    fn read(&mut self, input: &[u8], is_end: bool) -> Vec<Result<CsvRecord, RowError>> {
+        let (decoder, csv) = match &mut self.state {
+            None => return Vec::new(),
+            Some(state) => state,
+        };
+        let text = transcode(decoder, input, is_end);
+        let mut result = csv.push(text.as_bytes());
+        if is_end {
+            result.extend(csv.finish());
+        }
+        result
+    }
+}
+
+/// 转换成utf-8，无法转换的字符使用替换字符，数据块末尾截断的字符保留到下次转换
+fn transcode(decoder: &mut Decoder, input: &[u8], is_end: bool) -> String {
+    let len = decoder
+        .max_utf8_buffer_length(input.len())
+        .unwrap_or(input.len() * 3 + 16);
+    let mut output = String::with_capacity(len);
+    let _ = decoder.decode_to_string(input, &mut output, is_end);
+    output
+}
+
+/// 检测编码，优先使用BOM，没有BOM时判断utf-16和utf-8，都不是时使用GB18030，兼容GBK
+/// @param sample 样本数据
+/// @param is_end 样本是否包含全部数据
+pub fn detect_encoding(sample: &[u8], is_end: bool) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return encoding;
+    }
+    // 没有BOM的utf-16，英文字符的高位字节为0
+    let half = sample.len() / 2;
+    if half > 0 {
+        let even = sample.iter().step_by(2).filter(|item| **item == 0).count();
+        let odd = sample
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .filter(|item| **item == 0)
+            .count();
+        if odd * 5 > half * 2 && even == 0 {
+            return UTF_16LE;
+        }
+        if even * 5 > half * 2 && odd == 0 {
+            return UTF_16BE;
+        }
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => UTF_8,
+        // 样本末尾截断的字符不算错误
+        Err(error) if error.error_len().is_none() && !is_end => UTF_8,
+        Err(_) => GB18030,
+    }
+}
+
+/// 根据第一行检测分隔符，引号中的字符不计算，没有找到时使用逗号
+/// @param text 样本文本
+pub fn detect_delimiter(text: &str) -> u8 {
+    let mut counts = [0usize; DELIMITERS.len()];
+    let mut quoted = false;
+    for byte in text.bytes() {
+        match byte {
+            b'"' => quoted = !quoted,
+            b'\n' if !quoted => break,
+            _ if !quoted => {
+                if let Some(index) = DELIMITERS.iter().position(|item| *item == byte) {
+                    counts[index] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut result = DELIMITERS[0];
+    let mut max = 0;
+    for (delimiter, count) in DELIMITERS.iter().zip(counts.iter()) {
+        if *count > max {
+            result = *delimiter;
+            max = *count;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_chunks() {
+        let data = "code,name\r\n1,\"a\nb\"\n2,中文\n3";
+        let mut decoder = CsvDecoder::new(b',');
+        let mut records = Vec::new();
+        // 按字节写入，模拟任意位置拆分的数据块
+        for byte in data.as_bytes().chunks(1) {
+            records.extend(decoder.push(byte));
+        }
+        records.extend(decoder.finish());
+        let records: Vec<CsvRecord> = records.into_iter().map(|item| item.unwrap()).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].fields, vec!["code", "name"]);
+        assert_eq!(records[1].fields, vec!["1", "a\nb"]);
+        assert_eq!(records[2].fields, vec!["2", "中文"]);
+        assert_eq!(records[2].line, 4);
+        assert_eq!(records[3].line, 5);
+
+        let mut decoder = CsvDecoder::new(b',');
+        let records = decoder.push(b"a,b\n\xff,1\n");
+        assert!(records[0].is_ok());
+        assert_eq!(records[1].as_ref().unwrap_err().line, 2);
+    }
+
+    fn read_all(data: &[u8], options: CsvOptions) -> Vec<Vec<String>> {
+        let mut reader = CsvReader::new(options);
+        let mut records = Vec::new();
+        for chunk in data.chunks(3) {
+            records.extend(reader.push(chunk));
+        }
+        records.extend(reader.finish());
+        records
+            .into_iter()
+            .map(|item| item.unwrap().fields)
+            .collect()
+    }
+
+    #[test]
+    fn detect_encoding_and_delimiter() {
+        let text = "名称;编码\n耕地;01\n";
+        // gbk
+        let (gbk, _, _) = encoding_rs::GBK.encode(text);
+        let records = read_all(&gbk, CsvOptions::default());
+        assert_eq!(records[0], vec!["名称", "编码"]);
+        assert_eq!(records[1], vec!["耕地", "01"]);
+        // utf-8 bom，制表符
+        let mut data = vec![0xEF, 0xBB, 0xBF];
+        data.extend_from_slice("名称\t编码\n耕地\t01".as_bytes());
+        let records = read_all(&data, CsvOptions::default());
+        assert_eq!(records[0], vec!["名称", "编码"]);
+        assert_eq!(records[1], vec!["耕地", "01"]);
+        // utf-16le bom
+        let mut data = vec![0xFF, 0xFE];
+        for unit in "code,name\n1,耕地\n".encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+        let records = read_all(&data, CsvOptions::default());
+        assert_eq!(records[1], vec!["1", "耕地"]);
+        // 指定编码和分隔符
+        let options = CsvOptions::parse(Some("gbk"), Some("semicolon")).unwrap();
+        assert_eq!(read_all(&gbk, options)[1], vec!["耕地", "01"]);
+        assert!(CsvOptions::parse(Some("abc"), None).is_err());
+        assert_eq!(detect_delimiter("\"a;b\",c\n"), b',');
     }
 }
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+
+use crate::storage::uri_encode;
+
+/// 识别文件类型需要的数据长度
+pub const SNIFF_SIZE: usize = 512;
+
+/// 请求的数据范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    // 完整文件，没有Range请求头或者请求头不支持
+    Full,
+    // 部分数据，开始和结束位置，包含结束位置
+    Partial(u64, u64),
+    // 范围超出文件大小
+    Unsatisfiable,
+}
+
+/// 文件头标识，偏移位置、标识和文件类型，靠前的优先匹配
+const SIGNATURES: [(usize, &[u8], &str); 23] = [
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (8, b"WAVE", "audio/wav"),
+    (8, b"AVI ", "video/x-msvideo"),
+    (0, b"BM", "image/bmp"),
+    (0, b"II*\0", "image/tiff"),
+    (0, b"MM\0*", "image/tiff"),
+    (4, b"ftypqt", "video/quicktime"),
+    (4, b"ftypheic", "image/heic"),
+    (4, b"ftypmif1", "image/heic"),
+    (4, b"ftypM4A", "audio/mp4"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"\xff\xfb", "audio/mpeg"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+];
+
+/// 根据文件内容识别文件类型，只识别常见的图片、音视频和压缩文件
+/// 无法识别的文本按照纯文本处理，不会识别为html，防止上传的文件在浏览器中执行脚本
+/// @param data 文件开始的数据
+pub fn sniff_mime(data: &[u8]) -> &'static str {
+    let signature = SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| data.get(*offset..offset + magic.len()) == Some(*magic));
+    match signature {
+        Some((_, _, mime)) => mime,
+        None if is_text(data) => "text/plain; charset=utf-8",
+        None => "application/octet-stream",
+    }
+}
+
+/// 是否为utf-8文本，样本末尾可能截断多字节字符
+fn is_text(data: &[u8]) -> bool {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => {
+            // 末尾字符不完整
+            std::str::from_utf8(&data[..err.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return false,
+    };
+    !text.is_empty()
+        && !text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+}
+
+/// 解析Range请求头，只支持单个范围，多个范围时返回完整文件
+/// @param range Range请求头
+/// @param size 文件大小
+pub fn parse_range(range: &str, size: u64) -> ByteRange {
+    let range = match range.trim().strip_prefix("bytes=") {
+        Some(range) if !range.contains(',') => range.trim(),
+        _ => return ByteRange::Full,
+    };
+    let (start, end) = match range.split_once('-') {
+        Some(value) => value,
+        None => return ByteRange::Full,
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return ByteRange::Full,
+        // 最后n个字节
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (size.saturating_sub(suffix), size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, size.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(size.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+    if size == 0 || start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+/// Content-Disposition响应头，文件名使用RFC 5987编码，同时提供ascii文件名兼容旧浏览器
+/// @param inline 是否在浏览器中直接打开
+/// @param filename 文件名
+pub fn content_disposition(inline: bool, filename: &str) -> String {
+    let disposition = if inline { "inline" } else { "attachment" };
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition,
+        fallback,
+        uri_encode(filename, true)
+    )
+}
+
+/// 格式化为http日期
+/// @param time 时间
+pub fn http_date(time: &DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// 解析http日期
+/// @param value 日期
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// If-None-Match请求头是否包含ETag，比较时忽略弱校验标记
+/// @param if_none_match If-None-Match请求头
+/// @param etag 文件ETag
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(|item| item.trim())
+        .any(|item| item == "*" || item.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn sniff() {
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n\0\0"), "image/png");
+        assert_eq!(sniff_mime(b"\xff\xd8\xff\xe0"), "image/jpeg");
+        assert_eq!(sniff_mime(b"\0\0\0\x18ftypmp42"), "video/mp4");
+        assert_eq!(sniff_mime(b"%PDF-1.7"), "application/pdf");
+        assert_eq!(
+            sniff_mime("<html>脚本</html>".as_bytes()),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            sniff_mime(&"中文".as_bytes()[..5]),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(sniff_mime(b"\0\x01\x02"), "application/octet-stream");
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Partial(0, 99));
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            ByteRange::Partial(900, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial(900, 999)
+        );
+        assert_eq!(parse_range("bytes=-2000", 1000), ByteRange::Partial(0, 999));
+        assert_eq!(
+            parse_range("bytes=500-2000", 1000),
+            ByteRange::Partial(500, 999)
+        );
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=5-1", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn header() {
+        assert_eq!(
+            content_disposition(false, "照片 1.jpg"),
+            "attachment; filename=\"__ 1.jpg\"; filename*=UTF-8''%E7%85%A7%E7%89%87%201.jpg"
+        );
+        let time = Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap();
+        assert_eq!(http_date(&time), "Sat, 01 Jun 2024 08:00:00 GMT");
+        assert_eq!(parse_http_date(&http_date(&time)), Some(time));
+        assert!(etag_matches("W/\"a\", \"b\"", "\"b\""));
+        assert!(!etag_matches("\"a\"", "\"b\""));
+    }
+}
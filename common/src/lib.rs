@@ -6,5 +6,6 @@ pub mod db;
 pub mod error;
 pub mod file;
 pub mod md5;
+pub mod storage;
 
 pub const FORCE: &str = "force";
@@ -0,0 +1,164 @@
+pub mod local;
+pub mod s3;
+
+use std::io;
+use std::path::{Component, Path};
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use crypto::digest::Digest;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use futures::Stream;
+
+use crate::config::{Config, StorageType};
+use crate::storage::local::LocalStorage;
+use crate::storage::s3::S3Storage;
+
+/// 文件数据流
+pub type ByteStream<'a> = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + 'a>>;
+
+/// 文件信息
+#[derive(Debug, Clone)]
+pub struct FileMeta {
+    // 文件大小，单位字节
+    pub size: u64,
+    // 最后修改时间
+    pub modified: Option<DateTime<Utc>>,
+    // 文件标识，文件内容变化时改变，包含引号
+    pub etag: Option<String>,
+}
+
+/// 文件存储，从配置文件读取
+static STORAGE: OnceLock<Box<dyn Storage>> = OnceLock::new();
+
+/// 文件存储，key为文件在存储中的相对路径，使用/分隔
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// 保存文件，文件已经存在时覆盖，返回文件大小
+    /// @param key 文件路径
+    /// @param stream 文件数据流
+    async fn put_stream(&self, key: &str, stream: ByteStream<'_>) -> Result<u64>;
+
+    /// 读取文件，文件不存在时返回StorageError::NotFound
+    /// @param key 文件路径
+    async fn get_stream(&self, key: &str) -> Result<ByteStream<'static>>;
+
+    /// 读取文件的一部分，文件不存在时返回StorageError::NotFound
+    /// @param key 文件路径
+    /// @param start 开始位置
+    /// @param end 结束位置，包含结束位置
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<ByteStream<'static>>;
+
+    /// 查询文件信息，文件不存在时返回StorageError::NotFound
+    /// @param key 文件路径
+    async fn metadata(&self, key: &str) -> Result<FileMeta>;
+
+    /// 删除文件，文件不存在时不报错
+    /// @param key 文件路径
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// 文件是否存在
+    /// @param key 文件路径
+    async fn exists(&self, key: &str) -> Result<bool>;
+
+    /// 生成有效期内不需要登录就可以下载文件的地址
+    /// @param key 文件路径
+    /// @param expires 有效期
+    async fn presigned_url(&self, key: &str, expires: Duration) -> Result<String>;
+
+    /// 校验预签名地址，对象存储的预签名地址由存储服务校验，默认不通过
+    /// @param key 文件路径
+    /// @param expires 过期时间戳（秒）
+    /// @param signature 签名
+    fn verify_presigned(&self, _key: &str, _expires: i64, _signature: &str) -> bool {
+        false
+    }
+}
+
+/// 获取配置文件中的文件存储，配置不正确时返回错误
+/// 服务启动时先调用一次，配置不正确时不启动服务
+pub fn get_storage() -> Result<&'static dyn Storage> {
+    if let Some(storage) = STORAGE.get() {
+        return Ok(storage.as_ref());
+    }
+    let config = Config::init_config().storage;
+    let storage: Box<dyn Storage> = match config.r#type {
+        StorageType::Local => Box::new(LocalStorage::new(
+            &config.root,
+            &config.secret,
+            &config.base_url,
+        )),
+        StorageType::S3 => Box::new(S3Storage::new(&config.s3)?),
+    };
+    Ok(STORAGE.get_or_init(|| storage).as_ref())
+}
+
+/// 检查文件路径，只允许普通的相对路径，防止访问存储目录以外的文件
+/// @param key 文件路径
+pub fn check_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty()
+        && !key.contains('\\')
+        && Path::new(key)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !valid {
+        bail!("文件路径：{}，不正确", key)
+    }
+    Ok(())
+}
+
+/// 按照RFC 3986编码路径，保留非保留字符
+/// @param value 路径
+/// @param encode_slash 是否编码/
+pub(crate) fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                result.push(byte as char)
+            }
+            b'/' if !encode_slash => result.push('/'),
+            _ => result.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    result
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn sha256_hex(data: &str) -> String {
+    let mut sha256 = Sha256::new();
+    sha256.input_str(data);
+    sha256.result_str()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut hmac = Hmac::new(Sha256::new(), key);
+    hmac.input(data.as_bytes());
+    hmac.result().code().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_storage_key() {
+        assert!(check_key("upload/a.png").is_ok());
+        assert!(check_key("../etc/passwd").is_err());
+        assert!(check_key("upload/../../etc/passwd").is_err());
+        assert!(check_key("/etc/passwd").is_err());
+        assert!(check_key("upload\\..\\a.png").is_err());
+        assert!(check_key("").is_err());
+        assert_eq!(uri_encode("upload/a b+.png", false), "upload/a%20b%2B.png");
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::{ErrorKind, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Utc};
+use crypto::util::fixed_time_eq;
+use futures::StreamExt;
+use tokio::fs::{self, File};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+use tokio_util::io::ReaderStream;
+
+use crate::error::StorageError;
+use crate::storage::{check_key, hex, hmac_sha256, uri_encode, ByteStream, FileMeta, Storage};
+
+/// 本地文件存储，文件保存在根目录下
+pub struct LocalStorage {
+    root: PathBuf,
+    // 预签名密钥
+    secret: String,
+    // 预签名地址前缀
+    base_url: String,
+}
+
+impl LocalStorage {
+    /// 创建本地文件存储
+    /// @param root 根目录
+    /// @param secret 预签名密钥
+    /// @param base_url 预签名地址前缀，例如http://localhost:8080
+    pub fn new(root: &str, secret: &str, base_url: &str) -> Self {
+        Self {
+            root: PathBuf::from(root),
+            secret: secret.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// 文件在本地的路径
+    /// @param key 文件路径
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    /// 打开文件，文件不存在或者不是文件时返回StorageError::NotFound
+    /// @param key 文件路径
+    async fn open(&self, key: &str) -> Result<File> {
+        let path = self.path(key)?;
+        let file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(StorageError::NotFound(key.to_string()).into())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !file.metadata().await?.is_file() {
+            return Err(StorageError::NotFound(key.to_string()).into());
+        }
+        Ok(file)
+    }
+
+    /// 预签名签名，签名内容为文件路径和过期时间
+    /// @param key 文件路径
+    /// @param expires 过期时间戳（秒）
+    fn signature(&self, key: &str, expires: i64) -> String {
+        hex(&hmac_sha256(
+            self.secret.as_bytes(),
+            &format!("{}\n{}", key, expires),
+        ))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put_stream(&self, key: &str, mut stream: ByteStream<'_>) -> Result<u64> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut file = BufWriter::new(File::create(&path).await?);
+        let mut size = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(size)
+    }
+
+    async fn get_stream(&self, key: &str) -> Result<ByteStream<'static>> {
+        let file = self.open(key).await?;
+        Ok(Box::pin(ReaderStream::new(file)))
+    }
+
+    async fn get_range(&self, key: &str, start: u64, end: u64) -> Result<ByteStream<'static>> {
+        let mut file = self.open(key).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        let length = end.saturating_sub(start) + 1;
+        Ok(Box::pin(ReaderStream::new(file.take(length))))
+    }
+
+    async fn metadata(&self, key: &str) -> Result<FileMeta> {
+        let file = self.open(key).await?;
+        let metadata = file.metadata().await?;
+        let modified: Option<DateTime<Utc>> = metadata.modified().ok().map(DateTime::from);
+        // 使用文件大小和修改时间作为文件标识
+        let etag = format!(
+            "\"{:x}-{:x}\"",
+            metadata.len(),
+            modified
+                .map(|time| time.timestamp_micros())
+                .unwrap_or_default()
+        );
+        Ok(FileMeta {
+            size: metadata.len(),
+            modified,
+            etag: Some(etag),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path(key)?;
+        match fs::remove_file(&path).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let path = self.path(key)?;
+        match fs::metadata(&path).await {
+            Ok(metadata) => Ok(metadata.is_file()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn presigned_url(&self, key: &str, expires: Duration) -> Result<String> {
+        check_key(key)?;
+        let expires = Local::now().timestamp() + expires.as_secs() as i64;
+        Ok(format!(
+            "{}/presigned/{}?expires={}&signature={}",
+            self.base_url,
+            uri_encode(key, false),
+            expires,
+            self.signature(key, expires)
+        ))
+    }
+
+    fn verify_presigned(&self, key: &str, expires: i64, signature: &str) -> bool {
+        if self.secret.is_empty() || expires < Local::now().timestamp() {
+            return false;
+        }
+        fixed_time_eq(
+            self.signature(key, expires).as_bytes(),
+            signature.as_bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Bytes;
+    use futures::stream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn local_storage() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(root.path().to_str().unwrap(), "secret", "");
+        let data = vec![Ok(Bytes::from("hello ")), Ok(Bytes::from("world"))];
+        let size = storage
+            .put_stream("upload/a.txt", Box::pin(stream::iter(data)))
+            .await
+            .unwrap();
+        assert_eq!(size, 11);
+        assert!(storage.exists("upload/a.txt").await.unwrap());
+        let mut stream = storage.get_stream("upload/a.txt").await.unwrap();
+        let mut content = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(content, b"hello world");
+        assert!(storage.get_stream("../a.txt").await.is_err());
+        assert!(storage.get_stream("upload").await.is_err());
+        let mut stream = storage.get_range("upload/a.txt", 6, 10).await.unwrap();
+        let mut content = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            content.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(content, b"world");
+        let metadata = storage.metadata("upload/a.txt").await.unwrap();
+        assert_eq!(metadata.size, 11);
+        assert!(metadata.etag.is_some());
+
+        let url = storage
+            .presigned_url("upload/a.txt", Duration::from_secs(60))
+            .await
+            .unwrap();
+        let query = url.split_once('?').unwrap().1;
+        let (expires, signature) = query.split_once('&').unwrap();
+        let expires: i64 = expires.trim_start_matches("expires=").parse().unwrap();
+        let signature = signature.trim_start_matches("signature=");
+        assert!(storage.verify_presigned("upload/a.txt", expires, signature));
+        assert!(!storage.verify_presigned("upload/b.txt", expires, signature));
+        assert!(!storage.verify_presigned("upload/a.txt", expires - 120, signature));
+
+        storage.delete("upload/a.txt").await.unwrap();
+        storage.delete("upload/a.txt").await.unwrap();
+        assert!(!storage.exists("upload/a.txt").await.unwrap());
+        let error = storage.get_stream("upload/a.txt").await.err().unwrap();
+        assert!(error.downcast_ref::<StorageError>().is_some());
+    }
+}
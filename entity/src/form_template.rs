@@ -13,6 +13,7 @@ pub struct Model {
     pub version: String,
     pub description: Option<String>,
     pub remark: Option<String>,
+    pub status: i32,
     pub created_at: DateTime,
     pub updated_at: Option<DateTime>,
     pub deleted_at: Option<DateTime>,
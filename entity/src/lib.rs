@@ -10,4 +10,9 @@ pub mod sys_user;
 pub mod sys_role_menu;
 pub mod sys_role_api;
 pub mod form_template;
+pub mod form_template_version;
 pub mod project;
+pub mod record_history;
+pub mod task_transition;
+pub mod sys_file;
+pub mod sys_file_link;
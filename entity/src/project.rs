@@ -10,6 +10,7 @@ pub struct Model {
     pub name: String,
     pub code: String,
     pub form_template_id: String,
+    pub form_template_version: Option<String>,
     pub data_table_name: String,
     pub total: i32,
     pub r#type: i32,
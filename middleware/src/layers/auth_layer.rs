@@ -1,12 +1,15 @@
-use axum::extract::Request;
+use axum::extract::{OriginalUri, Request};
 use axum::http;
 use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::Response;
+use tracing::{error, info};
 
 use common::auth::jwt::{Claims, decode_token};
 use models::dto::sys::response::sys_user::ResponseUser;
 
+use crate::permission::has_permission;
+
 pub async fn auth(mut req: Request, next: Next) -> Result<Response, StatusCode> {
     let auth_header = req.headers()
         .get(http::header::AUTHORIZATION)
@@ -18,6 +21,14 @@ pub async fn auth(mut req: Request, next: Next) -> Result<Response, StatusCode>
     };
 
     let user = authorize_current_user::<>(auth_header).await?;
+    // 嵌套路由会裁剪uri，优先使用原始uri
+    let path = match req.extensions().get::<OriginalUri>() {
+        Some(uri) => uri.path().to_string(),
+        None => req.uri().path().to_string(),
+    };
+    let method = req.method().to_string();
+    // 校验角色api权限
+    authorize_current_api(&method, &path, &user.user).await?;
     req.extensions_mut().insert(user.user);
     Ok(next.run(req).await)
 }
@@ -31,4 +42,27 @@ where
     } else {
         Err(StatusCode::UNAUTHORIZED)
     }
-}
\ No newline at end of file
+}
+
+/// 根据用户角色判断是否有权限访问当前api
+async fn authorize_current_api(
+    method: &str,
+    path: &str,
+    user: &ResponseUser,
+) -> Result<(), StatusCode> {
+    let role_id = match &user.role {
+        Some(role) => role.id.as_str(),
+        None => return Err(StatusCode::FORBIDDEN),
+    };
+    match has_permission(role_id, method, path).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            info!("角色：{}，无权访问：{} {}", role_id, method, path);
+            Err(StatusCode::FORBIDDEN)
+        }
+        Err(err) => {
+            error!("查询角色权限失败：{}", err);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
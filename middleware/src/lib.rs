@@ -1,2 +1,2 @@
 pub mod layers;
-
+pub mod permission;
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Result;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use tokio::sync::RwLock;
+use tracing::info;
+
+use common::db::get_db;
+use entity::sys_api::{Column as SysApiColumn, Entity as SysApi};
+use entity::sys_role::{Column as SysRoleColumn, Entity as SysRole};
+use entity::sys_role_api::{Column as SysRoleApiColumn, Entity as SysRoleApi};
+
+/// 超级管理员角色编号，拥有全部api权限
+pub const ADMIN_ROLE_ID: &str = "1";
+
+/// 登录后即可访问的api，不需要授权
+const AUTHENTICATED_APIS: [(&str, &str); 3] = [
+    ("GET", "/sys/user/getUserInfo"),
+    ("GET", "/sys/authorize/search/menu"),
+    ("GET", "/sys/authorize/search/api"),
+];
+
+/// 角色api缓存，key为角色编号
+static ROLE_API_CACHE: OnceLock<RwLock<HashMap<String, Arc<Vec<ApiRule>>>>> = OnceLock::new();
+
+fn cache() -> &'static RwLock<HashMap<String, Arc<Vec<ApiRule>>>> {
+    ROLE_API_CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// api授权规则
+/// 由sys_api的api_method和api_path组成，api_path支持路由模板，
+/// 例如 /cts/form/update/:table_id/:id 或 /sys/image/*path
+#[derive(Debug, Clone)]
+pub struct ApiRule {
+    method: String,
+    segments: Vec<String>,
+}
+
+impl ApiRule {
+    pub fn new(method: &str, path: &str) -> Self {
+        Self {
+            method: method.trim().to_uppercase(),
+            segments: split_path(path),
+        }
+    }
+
+    /// 判断请求方式和路径是否匹配该规则
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        if self.method != "*" && self.method != "ALL" && !self.method.eq_ignore_ascii_case(method) {
+            return false;
+        }
+        let segments = split_path(path);
+        for (index, rule) in self.segments.iter().enumerate() {
+            // 通配符匹配剩余全部路径，至少一段
+            if rule.starts_with('*') {
+                return segments.len() > index;
+            }
+            match segments.get(index) {
+                None => return false,
+                Some(segment) => {
+                    // 路径参数匹配任意一段
+                    if !rule.starts_with(':') && rule != segment {
+                        return false;
+                    }
+                }
+            }
+        }
+        self.segments.len() == segments.len()
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.to_string())
+        .collect()
+}
+
+/// 判断角色是否有权限访问api
+/// @param role_id 角色编号
+/// @param method 请求方式
+/// @param path 请求路径
+pub async fn has_permission(role_id: &str, method: &str, path: &str) -> Result<bool> {
+    // 超级管理员拥有全部权限
+    if role_id == ADMIN_ROLE_ID {
+        return Ok(true);
+    }
+    // 登录即可访问的api
+    let is_authenticated_api = AUTHENTICATED_APIS
+        .iter()
+        .any(|(api_method, api_path)| ApiRule::new(api_method, api_path).matches(method, path));
+    if is_authenticated_api {
+        return Ok(true);
+    }
+    let rules = role_apis(role_id).await?;
+    Ok(rules.iter().any(|rule| rule.matches(method, path)))
+}
+
+/// 查询角色授权的api规则，优先读取缓存
+async fn role_apis(role_id: &str) -> Result<Arc<Vec<ApiRule>>> {
+    if let Some(rules) = cache().read().await.get(role_id) {
+        return Ok(rules.clone());
+    }
+    let db = get_db().await;
+    // 删除的角色没有api权限
+    let role = SysRole::find_by_id(role_id)
+        .filter(SysRoleColumn::DeletedAt.is_null())
+        .one(&db)
+        .await?;
+    if role.is_none() {
+        return Ok(Arc::new(Vec::new()));
+    }
+    let rules: Vec<ApiRule> = SysApi::find()
+        .inner_join(SysRoleApi)
+        .filter(SysRoleApiColumn::RoleId.eq(role_id))
+        .filter(SysApiColumn::DeletedAt.is_null())
+        .select_only()
+        .columns([SysApiColumn::ApiMethod, SysApiColumn::ApiPath])
+        .into_tuple::<(String, String)>()
+        .all(&db)
+        .await?
+        .iter()
+        .map(|(method, path)| ApiRule::new(method, path))
+        .collect();
+    let rules = Arc::new(rules);
+    cache()
+        .write()
+        .await
+        .insert(role_id.to_string(), rules.clone());
+    Ok(rules)
+}
+
+/// 清除角色api缓存，角色授权、状态变更或者角色删除后调用
+/// @param role_id 角色编号
+pub async fn invalidate_role(role_id: &str) {
+    info!("清除角色api缓存：{}", role_id);
+    cache().write().await.remove(role_id);
+}
+
+/// 清除全部角色api缓存，api信息变更后调用
+pub async fn invalidate_all() {
+    info!("清除全部角色api缓存");
+    cache().write().await.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_template_path() {
+        let rule = ApiRule::new("post", "/cts/form/update/:table_id/:id");
+        assert!(rule.matches("POST", "/cts/form/update/abc/1"));
+        assert!(!rule.matches("GET", "/cts/form/update/abc/1"));
+        assert!(!rule.matches("POST", "/cts/form/update/abc"));
+        assert!(!rule.matches("POST", "/cts/form/update/abc/1/2"));
+    }
+
+    #[test]
+    fn match_wildcard_path() {
+        let rule = ApiRule::new("GET", "/sys/image/*path");
+        assert!(rule.matches("GET", "/sys/image/upload/a.png"));
+        assert!(!rule.matches("GET", "/sys/image"));
+        let rule = ApiRule::new("*", "/sys/user/search/");
+        assert!(rule.matches("DELETE", "/sys/user/search"));
+    }
+}
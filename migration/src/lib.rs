@@ -1,6 +1,10 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20240607_130840_init_database;
+mod m20261018_000001_form_template_version;
+mod m20261018_000002_task_transition;
+mod m20261018_000003_record_history;
+mod m20261018_000004_sys_file;
 mod manager;
 
 trait TableOperation {
@@ -16,6 +20,10 @@ impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
         vec![
             Box::new(m20240607_130840_init_database::Migration),
+            Box::new(m20261018_000001_form_template_version::Migration),
+            Box::new(m20261018_000002_task_transition::Migration),
+            Box::new(m20261018_000003_record_history::Migration),
+            Box::new(m20261018_000004_sys_file::Migration),
         ]
     }
 }
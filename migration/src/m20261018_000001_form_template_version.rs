@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+use crate::manager::cts::form_template::FormTemplate;
+use crate::manager::cts::form_template_version::FormTemplateVersion;
+use crate::manager::cts::project::Project;
+use crate::TableOperation;
+
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 表单添加状态字段
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FormTemplate::Table)
+                    .add_column_if_not_exists(
+                        ColumnDef::new(FormTemplate::Status).integer().not_null().default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // 项目添加表单版本字段
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .add_column_if_not_exists(ColumnDef::new(Project::FormTemplateVersion).string())
+                    .to_owned(),
+            )
+            .await?;
+        // 创建表单版本表
+        FormTemplateVersion::Table.create_table(manager).await?;
+        FormTemplateVersion::Table.create_index(manager).await?;
+        // 已有表单发布为当前版本，项目使用当前版本
+        FormTemplateVersion::Table.insert_data(manager).await?;
+        let connection = manager.get_connection();
+        connection
+            .execute_unprepared(
+                r#"UPDATE form_template SET status = 1
+                WHERE id IN (SELECT form_template_id FROM form_template_version)"#,
+            )
+            .await?;
+        connection
+            .execute_unprepared(
+                r#"UPDATE project SET form_template_version = form_template.version
+                FROM form_template
+                WHERE project.form_template_id = form_template.id AND form_template.status = 1"#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        FormTemplateVersion::Table.drop_table(manager).await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Project::Table)
+                    .drop_column(Project::FormTemplateVersion)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FormTemplate::Table)
+                    .drop_column(FormTemplate::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+}
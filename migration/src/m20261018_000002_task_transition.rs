@@ -0,0 +1,20 @@
+use sea_orm_migration::prelude::*;
+use crate::manager::cts::task_transition::TaskTransition;
+use crate::TableOperation;
+
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建任务流转记录表
+        TaskTransition::Table.create_table(manager).await?;
+        TaskTransition::Table.create_index(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        TaskTransition::Table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,20 @@
+use sea_orm_migration::prelude::*;
+use crate::manager::cts::record_history::RecordHistory;
+use crate::TableOperation;
+
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建数据变更历史表
+        RecordHistory::Table.create_table(manager).await?;
+        RecordHistory::Table.create_index(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        RecordHistory::Table.drop_table(manager).await
+    }
+}
@@ -0,0 +1,23 @@
+use sea_orm_migration::prelude::*;
+use crate::manager::sys::sys_file::SysFile;
+use crate::manager::sys::sys_file_link::SysFileLink;
+use crate::TableOperation;
+
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建上传文件表和文件引用表
+        SysFile::Table.create_table(manager).await?;
+        SysFileLink::Table.create_table(manager).await?;
+        SysFileLink::Table.create_index(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        SysFileLink::Table.drop_table(manager).await?;
+        SysFile::Table.drop_table(manager).await
+    }
+}
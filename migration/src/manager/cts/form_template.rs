@@ -18,6 +18,8 @@ pub enum FormTemplate {
     Description,
     // 备注
     Remark,
+    // 状态，0：草稿，1：已发布
+    Status,
     // 创建时间
     CreatedAt,
     // 更新时间
@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+use crate::manager::cts::form_template::FormTemplate;
+use crate::TableOperation;
+
+/// 表单模板发布历史，发布后不能修改
+#[derive(DeriveIden)]
+pub enum FormTemplateVersion {
+    Table,
+    // 编号
+    Id,
+    // 表单编号
+    FormTemplateId,
+    // 版本
+    Version,
+    // 表单内容
+    Content,
+    // 备注
+    Remark,
+    // 创建时间
+    CreatedAt,
+}
+
+impl TableOperation for FormTemplateVersion {
+    async fn create_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FormTemplateVersion::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FormTemplateVersion::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FormTemplateVersion::FormTemplateId).string().not_null())
+                    .col(ColumnDef::new(FormTemplateVersion::Version).string().not_null())
+                    .col(ColumnDef::new(FormTemplateVersion::Content).string().not_null())
+                    .col(ColumnDef::new(FormTemplateVersion::Remark).string())
+                    .col(ColumnDef::new(FormTemplateVersion::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn create_index(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        // 创建唯一键，同一个表单的版本不能重复
+        manager.create_index(Index::create()
+            .table(FormTemplateVersion::Table)
+            .if_not_exists()
+            .name("unique_form-template-version_version")
+            .col(FormTemplateVersion::FormTemplateId)
+            .col(FormTemplateVersion::Version)
+            .unique()
+            .to_owned()
+        ).await?;
+        // 创建 表单外键
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("Fk_form-template-version_form_template_id")
+                .from(FormTemplateVersion::Table, FormTemplateVersion::FormTemplateId)
+                .to(FormTemplate::Table, FormTemplate::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await
+    }
+
+    async fn drop_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FormTemplateVersion::Table).if_exists().to_owned()).await
+    }
+
+    async fn insert_data(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        // 已有表单内容发布为当前版本
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"INSERT INTO form_template_version (id, form_template_id, version, content, created_at)
+                SELECT md5(random()::text || id), id, version, content, now()
+                FROM form_template
+                WHERE content IS NOT NULL AND deleted_at IS NULL"#,
+            )
+            .await?;
+        Ok(())
+    }
+}
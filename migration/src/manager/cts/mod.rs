@@ -1,2 +1,5 @@
 pub mod form_template;
-pub mod project;
\ No newline at end of file
+pub mod form_template_version;
+pub mod project;
+pub mod record_history;
+pub mod task_transition;
\ No newline at end of file
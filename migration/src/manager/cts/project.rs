@@ -12,6 +12,8 @@ pub enum Project {
     Code,
     // 表单编号
     FormTemplateId,
+    // 表单版本
+    FormTemplateVersion,
     // 数据变化
     DataTableName,
     // 数据条数
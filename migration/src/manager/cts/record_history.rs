@@ -0,0 +1,71 @@
+use sea_orm_migration::prelude::*;
+use crate::TableOperation;
+
+/// 数据变更历史，记录动态表中每条数据的添加、更新和删除
+#[derive(DeriveIden)]
+pub enum RecordHistory {
+    Table,
+    // 编号
+    Id,
+    // 数据表名
+    TableName,
+    // 数据编号
+    RecordId,
+    // 变更动作
+    Action,
+    // 操作用户编号
+    UserId,
+    // 变更前的字段值
+    Before,
+    // 变更后的字段值
+    After,
+    // 创建时间
+    CreatedAt,
+}
+
+impl TableOperation for RecordHistory {
+    async fn create_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecordHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecordHistory::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RecordHistory::TableName).string().not_null())
+                    .col(ColumnDef::new(RecordHistory::RecordId).string().not_null())
+                    .col(ColumnDef::new(RecordHistory::Action).string().not_null())
+                    .col(ColumnDef::new(RecordHistory::UserId).string())
+                    .col(ColumnDef::new(RecordHistory::Before).json_binary())
+                    .col(ColumnDef::new(RecordHistory::After).json_binary())
+                    .col(ColumnDef::new(RecordHistory::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn create_index(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        // 按照数据查询变更历史
+        manager.create_index(Index::create()
+            .table(RecordHistory::Table)
+            .if_not_exists()
+            .name("idx_record-history_record_id")
+            .col(RecordHistory::TableName)
+            .col(RecordHistory::RecordId)
+            .to_owned()
+        ).await
+    }
+
+    async fn drop_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecordHistory::Table).if_exists().to_owned()).await
+    }
+
+    async fn insert_data(&self, _manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+use crate::TableOperation;
+
+/// 任务状态流转记录，任务表为动态表，使用表编号和任务编号关联
+#[derive(DeriveIden)]
+pub enum TaskTransition {
+    Table,
+    // 编号
+    Id,
+    // 任务表编号
+    TableId,
+    // 任务编号
+    TaskId,
+    // 流转动作
+    Action,
+    // 流转前状态
+    FromStatus,
+    // 流转后状态
+    ToStatus,
+    // 审核意见
+    Comment,
+    // 操作用户编号
+    UserId,
+    // 创建时间
+    CreatedAt,
+}
+
+impl TableOperation for TaskTransition {
+    async fn create_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TaskTransition::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(TaskTransition::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(TaskTransition::TableId).string().not_null())
+                    .col(ColumnDef::new(TaskTransition::TaskId).string().not_null())
+                    .col(ColumnDef::new(TaskTransition::Action).string().not_null())
+                    .col(ColumnDef::new(TaskTransition::FromStatus).integer().not_null())
+                    .col(ColumnDef::new(TaskTransition::ToStatus).integer().not_null())
+                    .col(ColumnDef::new(TaskTransition::Comment).string())
+                    .col(ColumnDef::new(TaskTransition::UserId).string().not_null())
+                    .col(ColumnDef::new(TaskTransition::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn create_index(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        // 按照任务查询流转记录
+        manager.create_index(Index::create()
+            .table(TaskTransition::Table)
+            .if_not_exists()
+            .name("idx_task-transition_task_id")
+            .col(TaskTransition::TableId)
+            .col(TaskTransition::TaskId)
+            .to_owned()
+        ).await
+    }
+
+    async fn drop_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaskTransition::Table).if_exists().to_owned()).await
+    }
+
+    async fn insert_data(&self, _manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
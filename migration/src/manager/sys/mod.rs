@@ -5,4 +5,6 @@ pub mod sys_menu;
 pub mod sys_api;
 pub mod sys_role_menu;
 pub mod sys_role_api;
+pub mod sys_file;
+pub mod sys_file_link;
 
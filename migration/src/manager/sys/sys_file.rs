@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+use crate::TableOperation;
+
+/// 上传文件，记录文件的原始名称、存储位置和上传用户
+#[derive(DeriveIden)]
+pub enum SysFile {
+    Table,
+    // 编号
+    Id,
+    // 原始文件名
+    OriginalName,
+    // 存储路径
+    Path,
+    // 文件大小，单位字节
+    Size,
+    // 文件类型
+    Mime,
+    // 文件内容sha256
+    Sha256,
+    // 上传用户编号
+    UserId,
+    // 租户编号
+    TenantId,
+    // 创建时间
+    CreatedAt,
+}
+
+impl TableOperation for SysFile {
+    async fn create_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysFile::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SysFile::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysFile::OriginalName).string().not_null())
+                    .col(ColumnDef::new(SysFile::Path).string().not_null())
+                    .col(ColumnDef::new(SysFile::Size).big_integer().not_null())
+                    .col(ColumnDef::new(SysFile::Mime).string().not_null())
+                    .col(ColumnDef::new(SysFile::Sha256).string().not_null())
+                    .col(ColumnDef::new(SysFile::UserId).string().not_null())
+                    .col(ColumnDef::new(SysFile::TenantId).string())
+                    .col(ColumnDef::new(SysFile::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn create_index(&self, _manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        Ok(())
+    }
+
+    async fn drop_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysFile::Table).if_exists().to_owned()).await
+    }
+
+    async fn insert_data(&self, _manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+use crate::manager::sys::sys_file::SysFile;
+use crate::TableOperation;
+
+/// 文件引用，记录动态表数据中引用文件的题目
+#[derive(DeriveIden)]
+pub enum SysFileLink {
+    Table,
+    // 编号
+    Id,
+    // 文件编号
+    FileId,
+    // 数据表名
+    TableName,
+    // 数据编号
+    RecordId,
+    // 题目名称
+    QuestionName,
+    // 在题目中的顺序
+    Sort,
+    // 创建时间
+    CreatedAt,
+}
+
+impl TableOperation for SysFileLink {
+    async fn create_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SysFileLink::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SysFileLink::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SysFileLink::FileId).string().not_null())
+                    .col(ColumnDef::new(SysFileLink::TableName).string().not_null())
+                    .col(ColumnDef::new(SysFileLink::RecordId).string().not_null())
+                    .col(ColumnDef::new(SysFileLink::QuestionName).string().not_null())
+                    .col(ColumnDef::new(SysFileLink::Sort).integer().not_null())
+                    .col(ColumnDef::new(SysFileLink::CreatedAt).timestamp().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn create_index(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        // 创建 文件外键
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .name("Fk_file-link_file_id")
+                .from(SysFileLink::Table, SysFileLink::FileId)
+                .to(SysFile::Table, SysFile::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        // 按照数据查询引用的文件
+        manager.create_index(Index::create()
+            .table(SysFileLink::Table)
+            .if_not_exists()
+            .name("idx_file-link_record_id")
+            .col(SysFileLink::TableName)
+            .col(SysFileLink::RecordId)
+            .to_owned()
+        ).await
+    }
+
+    async fn drop_table(&self, manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SysFileLink::Table).if_exists().to_owned()).await
+    }
+
+    async fn insert_data(&self, _manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
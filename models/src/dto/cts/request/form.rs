@@ -1,4 +1,6 @@
 use crate::dto::Page;
+use common::db::aggregate::AggregateMetric;
+use common::db::filter::FilterTree;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -7,7 +9,8 @@ use serde_json::Value;
 pub struct SearchFormData {
     pub page: Option<Page>,
     pub fields: Option<Vec<String>>,
-    pub wheres: Option<Vec<String>>,
+    // 查询条件树
+    pub wheres: Option<FilterTree>,
     // 排序
     pub orders: Option<Vec<String>>,
 }
@@ -32,4 +35,19 @@ pub struct UpdateFormData {
     pub name: String,
     // 表单数据
     pub data: Value,
+    // 读取数据时的版本，不为空时检查数据是否已经被修改
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateFormData {
+    // 统计指标，count可以统计任意题目，sum、avg、min和max只能统计数值题目
+    pub metrics: Option<Vec<AggregateMetric>>,
+    // 频数统计的单选题
+    pub frequencies: Option<Vec<String>>,
+    // 分组题目，最多两个
+    pub group_by: Option<Vec<String>>,
+    // 查询条件树
+    pub wheres: Option<FilterTree>,
 }
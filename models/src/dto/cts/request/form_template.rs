@@ -36,4 +36,18 @@ pub struct SearchFormTemplateDto {
     pub page: Option<Page>,
     // 排序
     pub orders: Option<Vec<Order>>,
-}
\ No newline at end of file
+}
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishFormTemplateDto {
+    pub remark: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffFormTemplateDto {
+    // 源版本
+    pub from: String,
+    // 目标版本
+    pub to: String,
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::dto::{Order, Page};
 use sea_orm::FromQueryResult;
 use serde::{Deserialize, Serialize};
@@ -21,12 +23,83 @@ pub struct AddProjectDto {
     pub r#type: i32,
     pub status: i32,
     pub form_template_id: String,
+    // 表单版本，为空使用最新发布的版本
+    pub form_template_version: Option<String>,
     pub description: Option<String>,
     pub remark: Option<String>,
     pub task_code: String,
+    // 经纬度字段，GeoJSON和Shapefile导入时作为几何生成的经纬度字段名
     pub task_lon: String,
     pub task_lat: String,
     pub file: String,
+    // 任务文件编码，为空时自动检测
+    pub encoding: Option<String>,
+    // 任务文件分隔符，为空时自动检测
+    pub delimiter: Option<String>,
+    // xlsx工作表名称，为空时使用第一个工作表
+    pub sheet: Option<String>,
+    // xlsx表头行号，从1开始，为空时自动查找
+    pub header_row: Option<i32>,
+}
+
+/// 已有项目导入任务的方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    // 追加，任务编号已经存在的跳过
+    Append,
+    // 任务编号已经存在的更新，不存在的插入
+    Upsert,
+    // 删除原有任务后插入，项目有采集的数据时不能替换
+    Replace,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportTaskDto {
+    pub mode: ImportMode,
+    pub task_code: String,
+    pub task_lon: String,
+    pub task_lat: String,
+    // 任务表中没有的字段是否添加到任务表
+    pub add_columns: bool,
+    // 任务文件编码，为空时自动检测
+    pub encoding: Option<String>,
+    // 任务文件分隔符，为空时自动检测
+    pub delimiter: Option<String>,
+    // xlsx工作表名称，为空时使用第一个工作表
+    pub sheet: Option<String>,
+    // xlsx表头行号，从1开始，为空时自动查找
+    pub header_row: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProjectDto {
+    // 导出格式，csv、xlsx、geojson、kml、kmz或者gpkg，为空时使用csv
+    pub format: Option<String>,
+    // 选择题是否导出选项名称，为空时导出选项编码
+    pub item_name: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStatsDto {
+    // 分组统计的任务字段，为空时不分组
+    pub group_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateProjectDto {
+    // 迁移到的表单版本
+    pub version: String,
+    // 只返回迁移计划，不执行
+    pub dry_run: Option<bool>,
+    // 是否删除表单中已经不存在的字段
+    pub drop_columns: Option<bool>,
+    // 重命名的字段，key为原字段名，value为题目name
+    pub renames: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,12 +124,17 @@ impl Default for AddProjectDto {
             r#type: 0,
             status: 0,
             form_template_id: "".to_string(),
+            form_template_version: None,
             description: None,
             remark: None,
             task_code: "".to_string(),
             task_lon: "".to_string(),
             task_lat: "".to_string(),
             file: "".to_string(),
+            encoding: None,
+            delimiter: None,
+            sheet: None,
+            header_row: None,
         }
     }
 }
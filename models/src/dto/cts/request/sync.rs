@@ -0,0 +1,29 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPullDto {
+    // 上次同步返回的游标，为空时全量同步
+    pub cursor: Option<String>,
+    // 任务和数据每次最多返回的数量，为空时使用默认值
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushDto {
+    // 离线提交的数据
+    pub items: Vec<SyncPushItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushItem {
+    // 提交数据对应的任务编号
+    pub task_id: String,
+    // 离线修改时服务器数据的版本，新提交的数据为空
+    pub base_version: Option<String>,
+    // 表单数据
+    pub data: Value,
+}
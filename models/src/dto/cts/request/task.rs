@@ -1,13 +1,47 @@
 use crate::dto::Page;
+use common::db::filter::FilterTree;
+use common::db::workflow::TaskAction;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchTask {
     pub fields: Option<Vec<String>>,
-    pub wheres: Option<Vec<String>>,
+    // 查询条件树
+    pub wheres: Option<FilterTree>,
     // 分页信息
     pub page: Option<Page>,
     // 排序
     pub orders: Option<Vec<String>>,
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignTaskDto {
+    // 分配的用户编号，为空时取消分配
+    pub user_id: Option<String>,
+    // 任务编号列表
+    pub ids: Option<Vec<String>>,
+    // 查询条件树，和任务编号同时存在时都需要满足
+    pub wheres: Option<FilterTree>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitTaskDto {
+    // 平均分配任务的用户编号
+    pub user_ids: Vec<String>,
+    // 查询条件树，为空时分配全部任务
+    pub wheres: Option<FilterTree>,
+    // 是否只分配没有分配的任务
+    pub unassigned: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionTaskDto {
+    // 流转动作
+    pub action: TaskAction,
+    // 审核意见，驳回时必须填写
+    pub comment: Option<String>,
+}
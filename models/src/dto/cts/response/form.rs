@@ -0,0 +1,59 @@
+use common::db::aggregate::AggregateOp;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// 表单数据统计结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateResult {
+    // 分组统计结果，没有分组时只有一组
+    pub groups: Vec<AggregateGroup>,
+    // 单选题频数统计结果
+    pub frequencies: Vec<FrequencyTable>,
+}
+
+/// 分组统计结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateGroup {
+    // 分组题目和分组值
+    pub keys: Map<String, Value>,
+    // 数据数量
+    pub count: u64,
+    // 统计指标的值
+    pub metrics: Vec<MetricValue>,
+}
+
+/// 统计指标的值
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricValue {
+    pub field: String,
+    pub op: AggregateOp,
+    pub value: Value,
+}
+
+/// 单选题频数表
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FrequencyTable {
+    // 题目名称
+    pub field: String,
+    // 题目标题
+    pub title: String,
+    pub rows: Vec<FrequencyRow>,
+}
+
+/// 频数表的一行
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FrequencyRow {
+    // 分组题目和分组值
+    pub keys: Map<String, Value>,
+    // 选项编码
+    pub code: Value,
+    // 选项名称，表单中没有的编码为空
+    pub name: Option<String>,
+    // 数据数量
+    pub count: u64,
+}
@@ -2,6 +2,8 @@ use sea_orm::FromQueryResult;
 use sea_orm::prelude::DateTime;
 use serde::{Deserialize, Serialize};
 use entity::form_template::Model;
+use entity::form_template_version::Model as VersionModel;
+use common::db::version::version;
 
 #[derive(Debug, Serialize, Deserialize, Clone, FromQueryResult)]
 #[serde(rename_all = "camelCase")]
@@ -13,8 +15,12 @@ pub struct ResponseFormTemplate {
     pub content: Option<String>,
     pub description: Option<String>,
     pub remark: Option<String>,
+    pub status: i32,
     pub created_at: DateTime,
     pub updated_at: Option<DateTime>,
+    // 数据版本，更新时放在If-Match请求头中，和服务器版本不一致时更新失败
+    #[serde(default)]
+    pub row_version: String,
 }
 
 
@@ -26,10 +32,36 @@ impl From<Model> for ResponseFormTemplate {
             title: value.title,
             version: "".to_string(),
             remark: value.remark,
+            status: value.status,
             description: value.description,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            row_version: version(value.updated_at, value.created_at),
             content: value.content,
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseFormTemplateVersion {
+    pub id: String,
+    pub form_template_id: String,
+    pub version: String,
+    pub content: Option<String>,
+    pub remark: Option<String>,
+    pub created_at: DateTime,
+}
+
+impl From<VersionModel> for ResponseFormTemplateVersion {
+    fn from(value: VersionModel) -> Self {
+        Self {
+            id: value.id,
+            form_template_id: value.form_template_id,
+            version: value.version,
+            content: Some(value.content),
+            remark: value.remark,
+            created_at: value.created_at,
+        }
+    }
+}
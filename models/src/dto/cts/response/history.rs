@@ -0,0 +1,32 @@
+use sea_orm::prelude::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use entity::record_history::Model;
+
+/// 数据变更历史，变更前后只包含变化的字段
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseRecordHistory {
+    pub id: String,
+    pub record_id: String,
+    pub action: String,
+    pub user_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime,
+}
+
+impl From<Model> for ResponseRecordHistory {
+    fn from(value: Model) -> Self {
+        Self {
+            id: value.id,
+            record_id: value.record_id,
+            action: value.action,
+            user_id: value.user_id,
+            before: value.before,
+            after: value.after,
+            created_at: value.created_at,
+        }
+    }
+}
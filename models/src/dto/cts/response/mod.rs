@@ -1,2 +1,6 @@
+pub mod form;
 pub mod form_template;
-pub mod project;
\ No newline at end of file
+pub mod history;
+pub mod project;
+pub mod sync;
+pub mod task;
\ No newline at end of file
@@ -2,6 +2,8 @@ use crate::dto::cts::response::form_template::ResponseFormTemplate;
 use entity::project::Model;
 use sea_orm::prelude::DateTime;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use common::db::version::version;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -14,11 +16,15 @@ pub struct ResponseProject {
     pub total: i32,
     pub form_template: Option<ResponseFormTemplate>,
     pub form_template_name: String,
+    pub form_template_version: Option<String>,
     pub data_table_name: String,
     pub description: Option<String>,
     pub remark: Option<String>,
     pub created_at: DateTime,
     pub updated_at: Option<DateTime>,
+    // 数据版本，更新时放在If-Match请求头中，和服务器版本不一致时更新失败
+    #[serde(default)]
+    pub row_version: String,
 }
 
 impl From<Model> for ResponseProject {
@@ -34,9 +40,59 @@ impl From<Model> for ResponseProject {
             description: value.description,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            row_version: version(value.updated_at, value.created_at),
             form_template: None,
             form_template_name: value.form_template_id,
+            form_template_version: value.form_template_version,
             data_table_name: value.data_table_name,
         }
     }
 }
+
+/// 任务导入结果
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportTaskResult {
+    // 插入的数量
+    pub inserted: usize,
+    // 更新的数量
+    pub updated: usize,
+    // 跳过的数量，任务编号已经存在或者文件中重复
+    pub skipped: usize,
+    // 导入后项目的任务总数
+    pub total: usize,
+}
+
+/// 项目进度统计
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    // 任务总数
+    pub total: u64,
+    // 已完成的任务数量，状态不是初始状态
+    pub completed: u64,
+    // 完成百分比
+    pub completion: f64,
+    // 按照任务状态统计
+    pub by_status: Vec<StatsCount>,
+    // 按照提交用户统计
+    pub by_user: Vec<StatsCount>,
+    // 按照提交日期统计，日期格式为yyyy-MM-dd
+    pub by_day: Vec<StatsCount>,
+    // 按照任务字段分组统计
+    pub by_group: Option<Vec<StatsCount>>,
+}
+
+/// 分组统计数量
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsCount {
+    // 分组值
+    pub key: Value,
+    // 数量
+    pub count: u64,
+    // 已完成的数量
+    pub completed: Option<u64>,
+    // 完成百分比
+    pub completion: Option<f64>,
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 增量同步结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPullResult {
+    // 下次同步使用的游标
+    pub cursor: String,
+    // 是否还有数据，为true时使用游标继续同步
+    pub more: bool,
+    // 表单内容，没有变化时为空
+    pub form: Option<SyncForm>,
+    // 新增和修改的任务
+    pub tasks: Vec<Value>,
+    // 新增和修改的数据
+    pub data: Vec<Value>,
+    // 删除的任务编号
+    pub deleted_tasks: Vec<String>,
+    // 删除的数据编号
+    pub deleted_data: Vec<String>,
+}
+
+/// 项目使用的表单
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncForm {
+    pub version: String,
+    pub content: String,
+}
+
+/// 离线提交的处理结果
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPushStatus {
+    // 已经保存
+    Applied,
+    // 服务器数据和提交的数据相同，重复提交
+    Unchanged,
+    // 服务器数据在离线期间被修改
+    Conflict,
+    // 保存失败
+    Error,
+}
+
+/// 单条离线提交的处理结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushResult {
+    pub task_id: String,
+    pub status: SyncPushStatus,
+    // 服务器数据当前版本
+    pub version: Option<String>,
+    // 冲突时服务器的当前数据
+    pub current: Option<Value>,
+    // 失败原因
+    pub message: Option<String>,
+}
@@ -0,0 +1,41 @@
+use sea_orm::prelude::DateTime;
+use serde::{Deserialize, Serialize};
+
+use entity::task_transition::Model as TransitionModel;
+
+/// 用户分配的任务数量
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignCount {
+    pub user_id: String,
+    pub count: usize,
+}
+
+/// 任务状态流转记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseTaskTransition {
+    pub id: String,
+    pub task_id: String,
+    pub action: String,
+    pub from_status: i32,
+    pub to_status: i32,
+    pub comment: Option<String>,
+    pub user_id: String,
+    pub created_at: DateTime,
+}
+
+impl From<TransitionModel> for ResponseTaskTransition {
+    fn from(value: TransitionModel) -> Self {
+        Self {
+            id: value.id,
+            task_id: value.task_id,
+            action: value.action,
+            from_status: value.from_status,
+            to_status: value.to_status,
+            comment: value.comment,
+            user_id: value.user_id,
+            created_at: value.created_at,
+        }
+    }
+}
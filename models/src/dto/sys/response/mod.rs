@@ -1,7 +1,7 @@
 pub mod base;
 pub mod sys_api;
+pub mod sys_file;
 pub mod sys_menu;
 pub mod sys_role;
 pub mod sys_tenant;
 pub mod sys_user;
-
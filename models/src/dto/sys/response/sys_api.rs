@@ -3,6 +3,7 @@ use common::date_time_format_option;
 use entity::sys_api::Model;
 use sea_orm::prelude::DateTime;
 use serde::{Deserialize, Serialize};
+use common::db::version::version;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +19,9 @@ pub struct ResponseApi {
     pub created_at: DateTime,
     #[serde(with = "date_time_format_option")]
     pub updated_at: Option<DateTime>,
+    // 数据版本，更新时放在If-Match请求头中，和服务器版本不一致时更新失败
+    #[serde(default)]
+    pub row_version: String,
 }
 
 impl From<Model> for ResponseApi {
@@ -32,6 +36,7 @@ impl From<Model> for ResponseApi {
             description: value.description,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            row_version: version(value.updated_at, value.created_at),
         }
     }
 }
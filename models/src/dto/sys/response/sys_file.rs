@@ -0,0 +1,45 @@
+use common::date_time_format;
+use entity::sys_file::Model;
+use sea_orm::prelude::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// 上传文件信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseFile {
+    pub id: String,
+    pub original_name: String,
+    pub path: String,
+    pub size: i64,
+    pub mime: String,
+    pub sha256: String,
+    pub user_id: String,
+    pub tenant_id: Option<String>,
+    #[serde(with = "date_time_format")]
+    pub created_at: DateTime,
+}
+
+impl From<Model> for ResponseFile {
+    fn from(value: Model) -> Self {
+        Self {
+            id: value.id,
+            original_name: value.original_name,
+            path: value.path,
+            size: value.size,
+            mime: value.mime,
+            sha256: value.sha256,
+            user_id: value.user_id,
+            tenant_id: value.tenant_id,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// 数据引用的文件，按照题目区分
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseAttachment {
+    // 题目名称
+    pub question_name: String,
+    pub file: ResponseFile,
+}
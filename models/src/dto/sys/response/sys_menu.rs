@@ -3,6 +3,7 @@ use common::date_time_format_option;
 use entity::sys_menu::Model;
 use sea_orm::prelude::DateTime;
 use serde::{Deserialize, Serialize};
+use common::db::version::version;
 
 const SYSTEM_PARENT_MENU_ID: &str = "";
 
@@ -67,6 +68,9 @@ pub struct ResponseMenu {
     pub created_at: DateTime,
     #[serde(with = "date_time_format_option")]
     pub updated_at: Option<DateTime>,
+    // 数据版本，更新时放在If-Match请求头中，和服务器版本不一致时更新失败
+    #[serde(default)]
+    pub row_version: String,
     pub children: Option<Vec<ResponseMenu>>,
 }
 
@@ -104,6 +108,7 @@ impl From<Model> for ResponseMenu {
             remark: value.remark,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            row_version: version(value.updated_at, value.created_at),
             children: None,
             meta: Meta::new(
                 value.title,
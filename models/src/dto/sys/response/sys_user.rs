@@ -5,6 +5,7 @@ use sea_orm::prelude::DateTime;
 use serde::{Deserialize, Serialize};
 use common::date_time_format;
 use common::date_time_format_option;
+use common::db::version::version;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +23,9 @@ pub struct ResponseUser {
     pub created_at: DateTime,
     #[serde(with = "date_time_format_option")]
     pub updated_at: Option<DateTime>,
+    // 数据版本，更新时放在If-Match请求头中，和服务器版本不一致时更新失败
+    #[serde(default)]
+    pub row_version: String,
     pub tenant: Option<ResponseTenant>,
     pub role: Option<ResponseRole>,
 }
@@ -40,6 +44,7 @@ impl From<Model> for ResponseUser {
             avatar: value.avatar,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            row_version: version(value.updated_at, value.created_at),
             role: None,
             tenant: None,
         }
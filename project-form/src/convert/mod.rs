@@ -28,3 +28,14 @@ impl TypeConvert<i32> for &Bytes {
     }
 }
 
+impl TypeConvert<bool> for &Bytes {
+    fn convert(self) -> Result<bool> {
+        let result = String::from_utf8(self.to_vec()).unwrap_or("".to_string());
+        match result.trim() {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            "" => Err(Error::msg("值不能为空")),
+            value => Err(Error::msg(format!("{}不是有效的布尔值", value))),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+
+use crate::export::{value_to_string, ExportRow};
+
+/// utf-8 bom，excel打开时识别编码
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// csv导出
+pub struct CsvExporter {
+    // 已经生成未输出的数据
+    pub buffer: Vec<u8>,
+}
+
+impl CsvExporter {
+    /// 创建csv导出并写入表头
+    /// @param titles 列标题
+    pub fn new(titles: Vec<String>) -> Self {
+        let mut result = Self {
+            buffer: UTF8_BOM.to_vec(),
+        };
+        result.write_line(titles.iter().map(|item| item.as_str()));
+        result
+    }
+
+    /// 写入一行数据
+    /// @param row 行数据
+    pub fn write(&mut self, row: &ExportRow) -> Result<()> {
+        let values: Vec<String> = row.values.iter().map(value_to_string).collect();
+        self.write_line(values.iter().map(|item| item.as_str()));
+        Ok(())
+    }
+
+    fn write_line<'a>(&mut self, fields: impl Iterator<Item = &'a str>) {
+        for (index, field) in fields.enumerate() {
+            if index > 0 {
+                self.buffer.push(b',');
+            }
+            // 包含分隔符、引号和换行的字段加引号，引号转义
+            if field.contains([',', '"', '\r', '\n']) {
+                self.buffer.push(b'"');
+                self.buffer
+                    .extend_from_slice(field.replace('"', "\"\"").as_bytes());
+                self.buffer.push(b'"');
+            } else {
+                self.buffer.extend_from_slice(field.as_bytes());
+            }
+        }
+        self.buffer.extend_from_slice(b"\r\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn write_csv() {
+        let mut exporter = CsvExporter::new(vec!["编号".to_string(), "备注".to_string()]);
+        let row = ExportRow {
+            values: vec![json!(1), json!("a,\"b\"")],
+            ..Default::default()
+        };
+        exporter.write(&row).unwrap();
+        let text = String::from_utf8(exporter.buffer[3..].to_vec()).unwrap();
+        assert_eq!(text, "编号,备注\r\n1,\"a,\"\"b\"\"\"\r\n");
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use serde_json::{json, Map};
+
+use crate::export::ExportRow;
+
+/// GeoJSON导出，要素逐个写入FeatureCollection
+pub struct GeoJsonExporter {
+    // 列标题，作为属性名称
+    titles: Vec<String>,
+    // 已经写入的要素数量
+    count: usize,
+    // 已经生成未输出的数据
+    pub buffer: Vec<u8>,
+}
+
+impl GeoJsonExporter {
+    /// 创建GeoJSON导出并写入要素集合开头
+    /// @param titles 列标题
+    pub fn new(titles: Vec<String>) -> Self {
+        Self {
+            titles,
+            count: 0,
+            buffer: br#"{"type":"FeatureCollection","features":["#.to_vec(),
+        }
+    }
+
+    /// 写入一个要素，有非点几何时使用该几何，否则使用经纬度
+    /// @param row 行数据
+    pub fn write(&mut self, row: &ExportRow) -> Result<()> {
+        let mut properties = Map::new();
+        for (title, value) in self.titles.iter().zip(row.values.iter()) {
+            properties.insert(title.clone(), value.clone());
+        }
+        let feature = json!({
+            "type": "Feature",
+            "geometry": row.geometry(),
+            "properties": properties,
+        });
+        if self.count > 0 {
+            self.buffer.push(b',');
+        }
+        serde_json::to_writer(&mut self.buffer, &feature)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// 结束要素集合，返回剩余的数据
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buffer.extend_from_slice(b"]}");
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn write_geojson() {
+        let mut exporter = GeoJsonExporter::new(vec!["名称".to_string()]);
+        let row = ExportRow {
+            values: vec![json!("a")],
+            point: Some((117.0, 30.0)),
+            ..Default::default()
+        };
+        exporter.write(&row).unwrap();
+        exporter.write(&row).unwrap();
+        let value: Value = serde_json::from_slice(&exporter.finish()).unwrap();
+        assert_eq!(value["features"].as_array().unwrap().len(), 2);
+        assert_eq!(value["features"][0]["properties"]["名称"], "a");
+        assert_eq!(value["features"][0]["geometry"]["coordinates"][0], 117.0);
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashSet;
+use std::fs::File;
+
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{Alias, Expr, Query, SqliteQueryBuilder};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, TransactionTrait};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+use crate::export::{value_to_string, ExportColumn, ExportRow};
+use crate::import::geo::geometry_bounds;
+
+/// GeoPackage的application_id，ASCII码“GPKG”
+const APPLICATION_ID: i32 = 0x47504B47;
+
+/// GeoPackage版本1.3
+const USER_VERSION: i32 = 10300;
+
+/// 要素表名称
+const FEATURE_TABLE: &str = "tasks";
+
+/// 几何字段名称
+const GEOMETRY_COLUMN: &str = "geom";
+
+/// WGS84坐标系
+const SRS_ID: i32 = 4326;
+
+/// 每次插入的最大参数数量，sqlite默认最多32766个参数
+const MAX_PARAMS: usize = 30000;
+
+/// 每次插入的最大行数
+const MAX_BATCH_ROWS: usize = 500;
+
+const WGS84_WKT: &str = r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]]"#;
+
+/// GeoPackage导出，使用sqlite写入临时文件，要素表包含任务点和采集结果
+pub struct GpkgExporter {
+    // 临时文件，sqlite写入该文件
+    file: NamedTempFile,
+    db: DatabaseConnection,
+    // 要素表字段名称
+    columns: Vec<String>,
+    // 是否为数值字段
+    numerics: Vec<bool>,
+    // 待插入的数据
+    batch: Vec<Vec<sea_orm::Value>>,
+    batch_size: usize,
+    // 全部要素的外包矩形 [minx, miny, maxx, maxy]
+    bounds: Option<[f64; 4]>,
+}
+
+impl GpkgExporter {
+    /// 创建GeoPackage文件，写入元数据表并创建要素表
+    /// @param name 名称，要素表描述
+    /// @param columns 导出的列
+    pub async fn new(name: &str, columns: &[ExportColumn]) -> Result<Self> {
+        let file = tempfile::Builder::new().suffix(".gpkg").tempfile()?;
+        let path = match file.path().to_str() {
+            Some(path) => path.to_string(),
+            None => bail!("临时文件路径错误"),
+        };
+        let db = Database::connect(format!("sqlite://{}?mode=rwc", path)).await?;
+        // 列标题作为字段名称，重复的名称加序号
+        let mut names: HashSet<String> =
+            HashSet::from(["fid".to_string(), GEOMETRY_COLUMN.to_string()]);
+        let mut field_names = Vec::with_capacity(columns.len());
+        for column in columns.iter() {
+            let mut field_name = column.title.clone();
+            let mut index = 1;
+            while !names.insert(field_name.to_lowercase()) {
+                field_name = format!("{}_{}", column.title, index);
+                index += 1;
+            }
+            field_names.push(field_name);
+        }
+        let fields: Vec<String> = field_names
+            .iter()
+            .zip(columns.iter())
+            .map(|(field_name, column)| {
+                let field_type = if column.numeric { "REAL" } else { "TEXT" };
+                format!("{} {}", quote(field_name), field_type)
+            })
+            .collect();
+        let mut create_table = format!(
+            "CREATE TABLE {} (fid INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL, {} GEOMETRY",
+            quote(FEATURE_TABLE),
+            quote(GEOMETRY_COLUMN)
+        );
+        for field in fields.iter() {
+            create_table.push_str(", ");
+            create_table.push_str(field);
+        }
+        create_table.push(')');
+        let sqls = [
+            format!("PRAGMA application_id = {}", APPLICATION_ID),
+            format!("PRAGMA user_version = {}", USER_VERSION),
+            "CREATE TABLE gpkg_spatial_ref_sys (srs_name TEXT NOT NULL, srs_id INTEGER NOT NULL PRIMARY KEY, organization TEXT NOT NULL, organization_coordsys_id INTEGER NOT NULL, definition TEXT NOT NULL, description TEXT)".to_string(),
+            format!(
+                "INSERT INTO gpkg_spatial_ref_sys VALUES ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', 'undefined cartesian coordinate reference system'), ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', 'undefined geographic coordinate reference system'), ('WGS 84 geodetic', {}, 'EPSG', {}, '{}', 'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid')",
+                SRS_ID, SRS_ID, WGS84_WKT
+            ),
+            "CREATE TABLE gpkg_contents (table_name TEXT NOT NULL PRIMARY KEY, data_type TEXT NOT NULL, identifier TEXT UNIQUE, description TEXT DEFAULT '', last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')), min_x DOUBLE, min_y DOUBLE, max_x DOUBLE, max_y DOUBLE, srs_id INTEGER, CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id))".to_string(),
+            "CREATE TABLE gpkg_geometry_columns (table_name TEXT NOT NULL, column_name TEXT NOT NULL, geometry_type_name TEXT NOT NULL, srs_id INTEGER NOT NULL, z TINYINT NOT NULL, m TINYINT NOT NULL, CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name), CONSTRAINT uk_gc_table_name UNIQUE (table_name), CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name), CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id))".to_string(),
+            create_table,
+            format!(
+                "INSERT INTO gpkg_contents (table_name, data_type, identifier, description, srs_id) VALUES ('{}', 'features', '{}', '{}', {})",
+                FEATURE_TABLE,
+                FEATURE_TABLE,
+                name.replace('\'', "''"),
+                SRS_ID
+            ),
+            format!(
+                "INSERT INTO gpkg_geometry_columns VALUES ('{}', '{}', 'GEOMETRY', {}, 0, 0)",
+                FEATURE_TABLE, GEOMETRY_COLUMN, SRS_ID
+            ),
+        ];
+        for sql in sqls.iter() {
+            db.execute_unprepared(sql).await?;
+        }
+        let batch_size = (MAX_PARAMS / (columns.len() + 1)).clamp(1, MAX_BATCH_ROWS);
+        Ok(Self {
+            file,
+            db,
+            columns: field_names,
+            numerics: columns.iter().map(|column| column.numeric).collect(),
+            batch: Vec::with_capacity(batch_size),
+            batch_size,
+            bounds: None,
+        })
+    }
+
+    /// 写入一个要素，数据达到批次大小时插入要素表
+    /// @param row 行数据
+    pub async fn write(&mut self, row: &ExportRow) -> Result<()> {
+        let mut values = Vec::with_capacity(self.columns.len() + 1);
+        let geometry = match row.geometry() {
+            Some(geometry) => {
+                let bounds = geometry_bounds(&geometry)?;
+                if let Some(bounds) = bounds {
+                    self.bounds = Some(match self.bounds {
+                        Some(total) => [
+                            total[0].min(bounds[0]),
+                            total[1].min(bounds[1]),
+                            total[2].max(bounds[2]),
+                            total[3].max(bounds[3]),
+                        ],
+                        None => bounds,
+                    });
+                }
+                Some(geometry_blob(&geometry, bounds)?)
+            }
+            None => None,
+        };
+        values.push(sea_orm::Value::Bytes(geometry.map(Box::new)));
+        for (value, numeric) in row.values.iter().zip(self.numerics.iter()) {
+            values.push(to_sqlite_value(value, *numeric));
+        }
+        self.batch.push(values);
+        if self.batch.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// 插入剩余数据，更新要素表范围，返回GeoPackage文件
+    pub async fn finish(mut self) -> Result<File> {
+        self.flush().await?;
+        if let Some(bounds) = self.bounds {
+            let sql = Query::update()
+                .table(Alias::new("gpkg_contents"))
+                .value(Alias::new("min_x"), bounds[0])
+                .value(Alias::new("min_y"), bounds[1])
+                .value(Alias::new("max_x"), bounds[2])
+                .value(Alias::new("max_y"), bounds[3])
+                .and_where(Expr::col(Alias::new("table_name")).eq(FEATURE_TABLE))
+                .to_string(SqliteQueryBuilder);
+            self.db.execute_unprepared(&sql).await?;
+        }
+        self.db.close().await?;
+        // 重新打开的文件在临时文件删除后仍然可以读取
+        let file = self.file.reopen()?;
+        Ok(file)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let mut insert = Query::insert();
+        insert.into_table(Alias::new(FEATURE_TABLE)).columns(
+            std::iter::once(GEOMETRY_COLUMN)
+                .chain(self.columns.iter().map(|item| item.as_str()))
+                .map(Alias::new),
+        );
+        for values in self.batch.drain(..) {
+            insert.values(values.into_iter().map(|item| item.into()))?;
+        }
+        let (sql, values) = insert.build(SqliteQueryBuilder);
+        let tx = self.db.begin().await?;
+        tx.execute(sea_orm::Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// 字段名称加双引号
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// json值转换成sqlite值，数值字段的非数值内容为空
+fn to_sqlite_value(value: &Value, numeric: bool) -> sea_orm::Value {
+    match value {
+        Value::Null => sea_orm::Value::String(None),
+        Value::Number(number) if numeric => sea_orm::Value::Double(number.as_f64()),
+        Value::String(text) if numeric => sea_orm::Value::Double(text.trim().parse().ok()),
+        _ if numeric => sea_orm::Value::Double(None),
+        value => sea_orm::Value::String(Some(Box::new(value_to_string(value)))),
+    }
+}
+
+/// 生成GeoPackage几何，GP头加小端WKB，非点几何写入外包矩形
+/// @param geometry GeoJSON几何
+/// @param bounds 外包矩形 [minx, miny, maxx, maxy]
+fn geometry_blob(geometry: &Value, bounds: Option<[f64; 4]>) -> Result<Vec<u8>> {
+    let is_point = geometry.get("type").and_then(|item| item.as_str()) == Some("Point");
+    let envelope = match bounds {
+        Some(bounds) if !is_point => Some(bounds),
+        _ => None,
+    };
+    let mut result = b"GP".to_vec();
+    // 版本0
+    result.push(0);
+    // 标志位：小端字节序，有外包矩形时类型为1(xy)，没有坐标时为空几何
+    let mut flags = 0x01;
+    if envelope.is_some() {
+        flags |= 0x01 << 1;
+    }
+    if bounds.is_none() {
+        flags |= 0x01 << 4;
+    }
+    result.push(flags);
+    result.extend_from_slice(&SRS_ID.to_le_bytes());
+    if let Some(bounds) = envelope {
+        for value in [bounds[0], bounds[2], bounds[1], bounds[3]] {
+            result.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    write_wkb(geometry, &mut result)?;
+    Ok(result)
+}
+
+/// GeoJSON几何转换成小端WKB
+fn write_wkb(geometry: &Value, wkb: &mut Vec<u8>) -> Result<()> {
+    let geometry_type = geometry.get("type").and_then(|item| item.as_str());
+    let coordinates = geometry.get("coordinates").unwrap_or(&Value::Null);
+    let code: u32 = match geometry_type {
+        Some("Point") => 1,
+        Some("LineString") => 2,
+        Some("Polygon") => 3,
+        Some("MultiPoint") => 4,
+        Some("MultiLineString") => 5,
+        Some("MultiPolygon") => 6,
+        Some("GeometryCollection") => 7,
+        _ => bail!("不支持的几何类型：{:?}", geometry_type),
+    };
+    wkb.push(0x01);
+    wkb.extend_from_slice(&code.to_le_bytes());
+    match code {
+        1 => write_position(coordinates, wkb)?,
+        2 => write_positions(coordinates, wkb)?,
+        3 => {
+            let rings = parts(coordinates)?;
+            wkb.extend_from_slice(&(rings.len() as u32).to_le_bytes());
+            for ring in rings.iter() {
+                write_positions(ring, wkb)?;
+            }
+        }
+        4..=6 => {
+            let items = parts(coordinates)?;
+            wkb.extend_from_slice(&(items.len() as u32).to_le_bytes());
+            let part_type = ["Point", "LineString", "Polygon"][code as usize - 4];
+            for item in items.iter() {
+                let part = serde_json::json!({"type": part_type, "coordinates": item});
+                write_wkb(&part, wkb)?;
+            }
+        }
+        _ => {
+            let geometries = parts(geometry.get("geometries").unwrap_or(&Value::Null))?;
+            wkb.extend_from_slice(&(geometries.len() as u32).to_le_bytes());
+            for item in geometries.iter() {
+                write_wkb(item, wkb)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_positions(coordinates: &Value, wkb: &mut Vec<u8>) -> Result<()> {
+    let positions = parts(coordinates)?;
+    wkb.extend_from_slice(&(positions.len() as u32).to_le_bytes());
+    for position in positions.iter() {
+        write_position(position, wkb)?;
+    }
+    Ok(())
+}
+
+fn write_position(position: &Value, wkb: &mut Vec<u8>) -> Result<()> {
+    match parts(position)?.as_slice() {
+        [Value::Number(x), Value::Number(y), ..] => {
+            for value in [x.as_f64(), y.as_f64()] {
+                wkb.extend_from_slice(&value.unwrap_or(f64::NAN).to_le_bytes());
+            }
+            Ok(())
+        }
+        _ => bail!("坐标格式错误"),
+    }
+}
+
+fn parts(value: &Value) -> Result<&Vec<Value>> {
+    match value {
+        Value::Array(items) => Ok(items),
+        _ => bail!("坐标格式错误"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn point_blob() {
+        let geometry = json!({"type": "Point", "coordinates": [1.0, 2.0]});
+        let blob = geometry_blob(&geometry, geometry_bounds(&geometry).unwrap()).unwrap();
+        assert_eq!(&blob[..8], &[b'G', b'P', 0, 0x01, 0xE6, 0x10, 0, 0]);
+        assert_eq!(&blob[8..13], &[0x01, 1, 0, 0, 0]);
+        assert_eq!(&blob[13..21], &1.0f64.to_le_bytes());
+        assert_eq!(&blob[21..29], &2.0f64.to_le_bytes());
+        assert_eq!(blob.len(), 29);
+    }
+
+    #[test]
+    fn polygon_blob() {
+        let geometry =
+            json!({"type": "Polygon", "coordinates": [[[0, 0], [2, 0], [2, 1], [0, 0]]]});
+        let blob = geometry_blob(&geometry, geometry_bounds(&geometry).unwrap()).unwrap();
+        // 标志位包含外包矩形
+        assert_eq!(blob[3], 0x03);
+        assert_eq!(&blob[16..24], &2.0f64.to_le_bytes());
+        // 头8字节，外包矩形32字节，WKB：字节序1、类型4、环数量4、点数量4、4个点
+        assert_eq!(blob.len(), 8 + 32 + 1 + 4 + 4 + 4 + 4 * 16);
+    }
+
+    #[tokio::test]
+    async fn write_gpkg() {
+        let columns = vec![
+            ExportColumn {
+                title: "名称".to_string(),
+                items: None,
+                numeric: false,
+            },
+            ExportColumn {
+                title: "名称".to_string(),
+                items: None,
+                numeric: true,
+            },
+        ];
+        let mut exporter = GpkgExporter::new("项目", &columns).await.unwrap();
+        let row = ExportRow {
+            values: vec![json!("a"), json!("1.5")],
+            point: Some((117.0, 30.0)),
+            ..Default::default()
+        };
+        exporter.write(&row).await.unwrap();
+        assert_eq!(exporter.columns, vec!["名称", "名称_1"]);
+        let file = exporter.finish().await.unwrap();
+        assert!(file.metadata().unwrap().len() > 0);
+    }
+}
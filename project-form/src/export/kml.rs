@@ -0,0 +1,265 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::export::xlsx::escape;
+use crate::export::{value_to_string, ExportEnd, ExportRow};
+
+/// 任务状态样式，状态值、名称和图标颜色(aabbggrr)
+const STATUS_STYLES: [(i64, &str, &str); 7] = [
+    (0, "未分配", "ff0000ff"),
+    (1, "已提交", "ffff0000"),
+    (2, "已分配", "ff0080ff"),
+    (3, "审核中", "ff00ffff"),
+    (4, "审核通过", "ff00ff00"),
+    (5, "驳回", "ffff00ff"),
+    (6, "重新提交", "ffffff00"),
+];
+
+/// 其他状态的样式颜色
+const OTHER_STYLE_COLOR: &str = "ffffffff";
+
+/// kml导出，kml边写边输出，kmz将kml写入临时zip文件
+pub struct KmlExporter {
+    // 列标题，作为扩展数据名称
+    titles: Vec<String>,
+    // kmz写入的临时文件
+    writer: Option<ZipWriter<File>>,
+    // 已经生成未输出的数据
+    pub buffer: Vec<u8>,
+}
+
+impl KmlExporter {
+    /// 创建kml导出并写入文档开头和状态样式
+    /// @param name 文档名称
+    /// @param titles 列标题
+    /// @param kmz 是否压缩成kmz
+    pub fn new(name: &str, titles: Vec<String>, kmz: bool) -> Result<Self> {
+        let mut buffer = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        buffer.push_str(r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>"#);
+        buffer.push_str(&format!("<name>{}</name>", escape(name)));
+        let styles = STATUS_STYLES
+            .iter()
+            .map(|(status, _, color)| (status.to_string(), *color))
+            .chain([("other".to_string(), OTHER_STYLE_COLOR)]);
+        for (id, color) in styles {
+            buffer.push_str(&format!(
+                r#"<Style id="status_{}"><IconStyle><color>{}</color></IconStyle><LineStyle><color>{}</color><width>2</width></LineStyle><PolyStyle><color>{}</color><fill>0</fill></PolyStyle></Style>"#,
+                id, color, color, color
+            ));
+        }
+        let writer = match kmz {
+            true => {
+                // 匿名临时文件，关闭后自动删除
+                let mut writer = ZipWriter::new(tempfile::tempfile()?);
+                let options =
+                    FileOptions::default().compression_method(CompressionMethod::Deflated);
+                writer.start_file("doc.kml", options)?;
+                Some(writer)
+            }
+            false => None,
+        };
+        let mut result = Self {
+            titles,
+            writer,
+            buffer: buffer.into_bytes(),
+        };
+        result.flush()?;
+        Ok(result)
+    }
+
+    /// 写入一个地标，名称为任务编号，样式按任务状态区分
+    /// @param row 行数据
+    pub fn write(&mut self, row: &ExportRow) -> Result<()> {
+        let geometry = match row.geometry() {
+            Some(geometry) => geometry,
+            // 没有几何的任务不能显示，不写入
+            None => return Ok(()),
+        };
+        let mut placemark = String::from("<Placemark>");
+        if let Some(code) = &row.code {
+            placemark.push_str(&format!("<name>{}</name>", escape(code)));
+        }
+        let style = match row.status {
+            Some(status) if STATUS_STYLES.iter().any(|item| item.0 == status) => {
+                status.to_string()
+            }
+            _ => "other".to_string(),
+        };
+        placemark.push_str(&format!("<styleUrl>#status_{}</styleUrl>", style));
+        placemark.push_str("<ExtendedData>");
+        for (title, value) in self.titles.iter().zip(row.values.iter()) {
+            placemark.push_str(&format!(
+                r#"<Data name="{}"><value>{}</value></Data>"#,
+                escape(title),
+                escape(&value_to_string(value))
+            ));
+        }
+        placemark.push_str("</ExtendedData>");
+        write_geometry(&geometry, &mut placemark)?;
+        placemark.push_str("</Placemark>");
+        self.buffer.extend_from_slice(placemark.as_bytes());
+        self.flush()
+    }
+
+    /// 结束文档，kml返回剩余的数据，kmz返回从头读取的临时文件
+    pub fn finish(mut self) -> Result<ExportEnd> {
+        self.buffer.extend_from_slice(b"</Document></kml>");
+        self.flush()?;
+        let result = match self.writer {
+            Some(mut writer) => {
+                let mut file = writer.finish()?;
+                file.seek(SeekFrom::Start(0))?;
+                ExportEnd::File(file)
+            }
+            None => ExportEnd::Bytes(self.buffer),
+        };
+        Ok(result)
+    }
+
+    /// kmz将已经生成的数据写入压缩文件
+    fn flush(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+/// GeoJSON几何转换成kml几何，多部件几何和几何集合使用MultiGeometry
+/// @param geometry GeoJSON几何
+/// @param kml 写入的kml
+fn write_geometry(geometry: &Value, kml: &mut String) -> Result<()> {
+    let geometry_type = geometry.get("type").and_then(|item| item.as_str());
+    let coordinates = geometry.get("coordinates").unwrap_or(&Value::Null);
+    match geometry_type {
+        Some("Point") => write_point(coordinates, kml)?,
+        Some("LineString") => write_line(coordinates, kml)?,
+        Some("Polygon") => write_polygon(coordinates, kml)?,
+        Some("MultiPoint") | Some("MultiLineString") | Some("MultiPolygon") => {
+            kml.push_str("<MultiGeometry>");
+            for part in parts(coordinates)? {
+                match geometry_type {
+                    Some("MultiPoint") => write_point(part, kml)?,
+                    Some("MultiLineString") => write_line(part, kml)?,
+                    _ => write_polygon(part, kml)?,
+                }
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+        Some("GeometryCollection") => {
+            kml.push_str("<MultiGeometry>");
+            let geometries = geometry.get("geometries").unwrap_or(&Value::Null);
+            for item in parts(geometries)? {
+                write_geometry(item, kml)?;
+            }
+            kml.push_str("</MultiGeometry>");
+        }
+        _ => bail!("不支持的几何类型：{:?}", geometry_type),
+    }
+    Ok(())
+}
+
+fn write_point(coordinates: &Value, kml: &mut String) -> Result<()> {
+    kml.push_str("<Point><coordinates>");
+    write_position(coordinates, kml)?;
+    kml.push_str("</coordinates></Point>");
+    Ok(())
+}
+
+fn write_line(coordinates: &Value, kml: &mut String) -> Result<()> {
+    kml.push_str("<LineString><coordinates>");
+    write_positions(coordinates, kml)?;
+    kml.push_str("</coordinates></LineString>");
+    Ok(())
+}
+
+fn write_polygon(coordinates: &Value, kml: &mut String) -> Result<()> {
+    kml.push_str("<Polygon>");
+    for (index, ring) in parts(coordinates)?.iter().enumerate() {
+        // 第一个环为外环，其余为内环
+        let boundary = match index {
+            0 => "outerBoundaryIs",
+            _ => "innerBoundaryIs",
+        };
+        kml.push_str(&format!("<{}><LinearRing><coordinates>", boundary));
+        write_positions(ring, kml)?;
+        kml.push_str(&format!("</coordinates></LinearRing></{}>", boundary));
+    }
+    kml.push_str("</Polygon>");
+    Ok(())
+}
+
+fn write_positions(coordinates: &Value, kml: &mut String) -> Result<()> {
+    for (index, position) in parts(coordinates)?.iter().enumerate() {
+        if index > 0 {
+            kml.push(' ');
+        }
+        write_position(position, kml)?;
+    }
+    Ok(())
+}
+
+fn write_position(position: &Value, kml: &mut String) -> Result<()> {
+    match parts(position)?.as_slice() {
+        [Value::Number(x), Value::Number(y), ..] => {
+            kml.push_str(&format!("{},{}", x, y));
+            Ok(())
+        }
+        _ => bail!("坐标格式错误"),
+    }
+}
+
+fn parts(value: &Value) -> Result<&Vec<Value>> {
+    match value {
+        Value::Array(items) => Ok(items),
+        _ => bail!("坐标格式错误"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn write_kml() {
+        let mut exporter = KmlExporter::new("项目", vec!["备注".to_string()], false).unwrap();
+        let row = ExportRow {
+            values: vec![json!("a<b")],
+            point: Some((117.5, 30.0)),
+            code: Some("T1".to_string()),
+            status: Some(1),
+            ..Default::default()
+        };
+        exporter.write(&row).unwrap();
+        let text = match exporter.finish().unwrap() {
+            ExportEnd::Bytes(data) => String::from_utf8(data).unwrap(),
+            ExportEnd::File(_) => panic!("kml不写入临时文件"),
+        };
+        assert!(text.contains("<name>T1</name><styleUrl>#status_1</styleUrl>"));
+        assert!(text.contains(r#"<Data name="备注"><value>a&lt;b</value></Data>"#));
+        assert!(text.contains("<Point><coordinates>117.5,30.0</coordinates></Point>"));
+        assert!(text.ends_with("</Document></kml>"));
+    }
+
+    #[test]
+    fn write_polygon_geometry() {
+        let geometry = json!({
+            "type": "MultiPolygon",
+            "coordinates": [[[[0, 0], [1, 0], [1, 1], [0, 0]]]]
+        });
+        let mut kml = String::new();
+        write_geometry(&geometry, &mut kml).unwrap();
+        assert_eq!(
+            kml,
+            "<MultiGeometry><Polygon><outerBoundaryIs><LinearRing><coordinates>0,0 1,0 1,1 0,0</coordinates></LinearRing></outerBoundaryIs></Polygon></MultiGeometry>"
+        );
+    }
+}
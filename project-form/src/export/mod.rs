@@ -0,0 +1,273 @@
+use std::fs::File;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+use crate::export::csv::CsvExporter;
+use crate::export::geojson::GeoJsonExporter;
+use crate::export::gpkg::GpkgExporter;
+use crate::export::kml::KmlExporter;
+use crate::export::xlsx::XlsxExporter;
+use crate::form::question::Item;
+
+pub mod csv;
+pub mod geojson;
+pub mod gpkg;
+pub mod kml;
+pub mod xlsx;
+
+/// 导出文件格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    GeoJson,
+    Kml,
+    Kmz,
+    GeoPackage,
+}
+
+impl ExportFormat {
+    /// 解析导出格式，为空时使用csv
+    /// @param format 格式名称
+    pub fn parse(format: Option<&str>) -> Result<Self> {
+        let result = match format.map(|item| item.to_lowercase()).as_deref() {
+            None | Some("csv") => ExportFormat::Csv,
+            Some("xlsx") => ExportFormat::Xlsx,
+            Some("geojson") => ExportFormat::GeoJson,
+            Some("kml") => ExportFormat::Kml,
+            Some("kmz") => ExportFormat::Kmz,
+            Some("gpkg") => ExportFormat::GeoPackage,
+            Some(format) => bail!(
+                "导出格式：{}，必须是csv、xlsx、geojson、kml、kmz或者gpkg",
+                format
+            ),
+        };
+        Ok(result)
+    }
+
+    /// 文件扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::GeoJson => "geojson",
+            ExportFormat::Kml => "kml",
+            ExportFormat::Kmz => "kmz",
+            ExportFormat::GeoPackage => "gpkg",
+        }
+    }
+
+    /// 响应的内容类型
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+            ExportFormat::GeoJson => "application/geo+json",
+            ExportFormat::Kml => "application/vnd.google-earth.kml+xml",
+            ExportFormat::Kmz => "application/vnd.google-earth.kmz",
+            ExportFormat::GeoPackage => "application/geopackage+sqlite3",
+        }
+    }
+
+    /// 是否导出全部任务，kml、kmz和gpkg导出任务点，没有采集的任务也导出
+    pub fn all_tasks(&self) -> bool {
+        matches!(
+            self,
+            ExportFormat::Kml | ExportFormat::Kmz | ExportFormat::GeoPackage
+        )
+    }
+}
+
+/// 导出的列，选择题可以将选项编码转换成选项名称
+#[derive(Debug, Clone)]
+pub struct ExportColumn {
+    // 列标题
+    pub title: String,
+    // 选择题的选项
+    pub items: Option<Vec<Item>>,
+    // 是否为数值字段
+    pub numeric: bool,
+}
+
+impl ExportColumn {
+    /// 转换导出的值，多选的编码数组转换成逗号分隔的选项名称
+    /// @param value 字段值
+    /// @param item_name 是否转换成选项名称
+    pub fn convert(&self, value: Value, item_name: bool) -> Value {
+        let items = match &self.items {
+            Some(items) if item_name => items,
+            _ => return value,
+        };
+        let name = |code: &str| {
+            items
+                .iter()
+                .find(|item| item.code == code)
+                .map(|item| item.name.clone())
+                .unwrap_or_else(|| code.to_string())
+        };
+        match value {
+            Value::String(code) => Value::String(name(&code)),
+            Value::Number(code) => Value::String(name(&code.to_string())),
+            Value::Array(codes) => {
+                let names: Vec<String> = codes
+                    .iter()
+                    .map(|code| match code {
+                        Value::String(code) => name(code),
+                        code => name(&code.to_string()),
+                    })
+                    .collect();
+                Value::String(names.join(","))
+            }
+            value => value,
+        }
+    }
+}
+
+/// 导出的一行数据
+#[derive(Debug, Clone, Default)]
+pub struct ExportRow {
+    // 和列对应的值
+    pub values: Vec<Value>,
+    // 经纬度
+    pub point: Option<(f64, f64)>,
+    // 非点几何，GeoJSON格式
+    pub geometry: Option<Value>,
+    // 任务编号
+    pub code: Option<String>,
+    // 任务状态
+    pub status: Option<i64>,
+}
+
+impl ExportRow {
+    /// 导出的几何，有非点几何时使用该几何，否则使用经纬度生成点
+    pub fn geometry(&self) -> Option<Value> {
+        match (&self.geometry, self.point) {
+            (Some(geometry), _) => Some(geometry.clone()),
+            (None, Some((lon, lat))) => {
+                Some(serde_json::json!({"type": "Point", "coordinates": [lon, lat]}))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// 导出结束后剩余的数据，xlsx写入临时文件
+pub enum ExportEnd {
+    Bytes(Vec<u8>),
+    File(File),
+}
+
+/// 导出文件生成，csv、GeoJSON和kml边写边输出，xlsx、kmz和gpkg需要写入临时文件后输出
+pub enum Exporter {
+    Csv(CsvExporter),
+    Xlsx(Box<XlsxExporter>),
+    GeoJson(GeoJsonExporter),
+    Kml(Box<KmlExporter>),
+    GeoPackage(Box<GpkgExporter>),
+}
+
+impl Exporter {
+    /// 创建导出对象并写入表头
+    /// @param format 导出格式
+    /// @param name 名称，kml文档名称
+    /// @param columns 导出的列
+    pub async fn new(format: ExportFormat, name: &str, columns: &[ExportColumn]) -> Result<Self> {
+        let titles: Vec<String> = columns.iter().map(|column| column.title.clone()).collect();
+        let result = match format {
+            ExportFormat::Csv => Exporter::Csv(CsvExporter::new(titles)),
+            ExportFormat::Xlsx => Exporter::Xlsx(Box::new(XlsxExporter::new(titles)?)),
+            ExportFormat::GeoJson => Exporter::GeoJson(GeoJsonExporter::new(titles)),
+            ExportFormat::Kml => Exporter::Kml(Box::new(KmlExporter::new(name, titles, false)?)),
+            ExportFormat::Kmz => Exporter::Kml(Box::new(KmlExporter::new(name, titles, true)?)),
+            ExportFormat::GeoPackage => {
+                Exporter::GeoPackage(Box::new(GpkgExporter::new(name, columns).await?))
+            }
+        };
+        Ok(result)
+    }
+
+    /// 写入一行数据
+    /// @param row 行数据
+    pub async fn write(&mut self, row: &ExportRow) -> Result<()> {
+        match self {
+            Exporter::Csv(exporter) => exporter.write(row),
+            Exporter::Xlsx(exporter) => exporter.write(row),
+            Exporter::GeoJson(exporter) => exporter.write(row),
+            Exporter::Kml(exporter) => exporter.write(row),
+            Exporter::GeoPackage(exporter) => exporter.write(row).await,
+        }
+    }
+
+    /// 已经生成未输出的数据大小
+    pub fn buffered(&self) -> usize {
+        match self {
+            Exporter::Csv(exporter) => exporter.buffer.len(),
+            Exporter::GeoJson(exporter) => exporter.buffer.len(),
+            Exporter::Kml(exporter) => exporter.buffer.len(),
+            Exporter::Xlsx(_) | Exporter::GeoPackage(_) => 0,
+        }
+    }
+
+    /// 取出已经生成的数据，写入临时文件的格式没有数据
+    pub fn take(&mut self) -> Vec<u8> {
+        match self {
+            Exporter::Csv(exporter) => std::mem::take(&mut exporter.buffer),
+            Exporter::GeoJson(exporter) => std::mem::take(&mut exporter.buffer),
+            Exporter::Kml(exporter) => std::mem::take(&mut exporter.buffer),
+            Exporter::Xlsx(_) | Exporter::GeoPackage(_) => Vec::new(),
+        }
+    }
+
+    /// 结束写入，返回剩余的数据
+    pub async fn finish(self) -> Result<ExportEnd> {
+        let result = match self {
+            Exporter::Csv(exporter) => ExportEnd::Bytes(exporter.buffer),
+            Exporter::Xlsx(exporter) => ExportEnd::File(exporter.finish()?),
+            Exporter::GeoJson(exporter) => ExportEnd::Bytes(exporter.finish()),
+            Exporter::Kml(exporter) => exporter.finish()?,
+            Exporter::GeoPackage(exporter) => ExportEnd::File(exporter.finish().await?),
+        };
+        Ok(result)
+    }
+}
+
+/// 值转换成文本，空值为空字符串，对象和数组使用json文本
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_item_name() {
+        let column = ExportColumn {
+            title: "地类".to_string(),
+            numeric: false,
+            items: Some(vec![
+                Item {
+                    name: "耕地".to_string(),
+                    code: "01".to_string(),
+                },
+                Item {
+                    name: "园地".to_string(),
+                    code: "02".to_string(),
+                },
+            ]),
+        };
+        assert_eq!(column.convert("01".into(), true), Value::from("耕地"));
+        assert_eq!(column.convert("01".into(), false), Value::from("01"));
+        assert_eq!(
+            column.convert(serde_json::json!(["01", "02", "03"]), true),
+            Value::from("耕地,园地,03")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::export::{value_to_string, ExportRow};
+
+/// 工作表最大行数
+const MAX_ROWS: usize = 1_048_576;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+const SHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#;
+
+const SHEET_END: &str = "</sheetData></worksheet>";
+
+/// xlsx导出，工作表逐行写入临时文件，文本使用内联字符串，不需要共享字符串表
+pub struct XlsxExporter {
+    writer: ZipWriter<File>,
+    // 已经写入的行数，包括表头
+    rows: usize,
+}
+
+impl XlsxExporter {
+    /// 创建xlsx导出并写入表头
+    /// @param titles 列标题
+    pub fn new(titles: Vec<String>) -> Result<Self> {
+        // 匿名临时文件，关闭后自动删除
+        let mut writer = ZipWriter::new(tempfile::tempfile()?);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, content) in [
+            ("[Content_Types].xml", CONTENT_TYPES),
+            ("_rels/.rels", ROOT_RELS),
+            ("xl/workbook.xml", WORKBOOK),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS),
+        ] {
+            writer.start_file(name, options)?;
+            writer.write_all(content.as_bytes())?;
+        }
+        writer.start_file("xl/worksheets/sheet1.xml", options)?;
+        writer.write_all(SHEET_START.as_bytes())?;
+        let mut result = Self { writer, rows: 0 };
+        let titles: Vec<Value> = titles.into_iter().map(Value::String).collect();
+        result.write_row(&titles)?;
+        Ok(result)
+    }
+
+    /// 写入一行数据
+    /// @param row 行数据
+    pub fn write(&mut self, row: &ExportRow) -> Result<()> {
+        self.write_row(&row.values)
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<()> {
+        if self.rows >= MAX_ROWS {
+            bail!("xlsx最多导出{}行数据", MAX_ROWS - 1)
+        }
+        self.rows += 1;
+        let mut line = format!(r#"<row r="{}">"#, self.rows);
+        for value in values.iter() {
+            match value {
+                Value::Null => line.push_str("<c/>"),
+                Value::Number(number) => {
+                    line.push_str(&format!("<c><v>{}</v></c>", number));
+                }
+                Value::Bool(value) => {
+                    line.push_str(&format!(r#"<c t="b"><v>{}</v></c>"#, *value as u8));
+                }
+                value => {
+                    line.push_str(r#"<c t="inlineStr"><is><t xml:space="preserve">"#);
+                    line.push_str(&escape(&value_to_string(value)));
+                    line.push_str("</t></is></c>");
+                }
+            }
+        }
+        line.push_str("</row>");
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// 结束工作表，返回从头读取的临时文件
+    pub fn finish(mut self) -> Result<File> {
+        self.writer.write_all(SHEET_END.as_bytes())?;
+        let mut file = self.writer.finish()?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    }
+}
+
+/// xml转义，去掉xml不允许的控制字符
+pub(crate) fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\t' | '\n' | '\r' => result.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{open_workbook_from_rs, Data, Reader, Xlsx};
+    use serde_json::json;
+    use std::io::{Cursor, Read};
+
+    use super::*;
+
+    #[test]
+    fn write_xlsx() {
+        let mut exporter = XlsxExporter::new(vec!["名称".to_string(), "面积".to_string()]).unwrap();
+        let row = ExportRow {
+            values: vec![json!("a<b"), json!(12.5)],
+            ..Default::default()
+        };
+        exporter.write(&row).unwrap();
+        let mut data = Vec::new();
+        exporter.finish().unwrap().read_to_end(&mut data).unwrap();
+        let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(data)).unwrap();
+        let range = workbook.worksheet_range("Sheet1").unwrap();
+        let rows: Vec<&[Data]> = range.rows().collect();
+        assert_eq!(rows[0][0], Data::String("名称".to_string()));
+        assert_eq!(rows[1][0], Data::String("a<b".to_string()));
+        assert_eq!(rows[1][1], Data::Float(12.5));
+    }
+}
@@ -0,0 +1,562 @@
+//! 表单校验表达式，例如：-180<name26 && name26 <180
+//! 只支持字面量、题目引用、运算符和内置函数，不能访问其他资源
+
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+/// 表达式最大长度
+const MAX_LENGTH: usize = 1024;
+/// 表达式最大嵌套深度
+const MAX_DEPTH: usize = 32;
+
+/// 表达式的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<ExprValue>),
+}
+
+impl ExprValue {
+    /// json值转换成表达式的值
+    pub fn from_json(value: &Value) -> Self {
+        match value {
+            Value::Null => ExprValue::Null,
+            Value::Bool(item) => ExprValue::Bool(*item),
+            Value::Number(item) => ExprValue::Number(item.as_f64().unwrap_or(f64::NAN)),
+            Value::String(item) => ExprValue::String(item.clone()),
+            Value::Array(items) => ExprValue::List(items.iter().map(ExprValue::from_json).collect()),
+            Value::Object(_) => ExprValue::String(value.to_string()),
+        }
+    }
+
+    /// 判断是否为空，空字符串和空数组视为空
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ExprValue::Null => true,
+            ExprValue::String(item) => item.trim().is_empty(),
+            ExprValue::List(items) => items.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// 转换成数字，表单提交的数字字符串也可以转换
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            ExprValue::Number(item) => Some(*item),
+            ExprValue::String(item) => item.trim().parse::<f64>().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Result<bool> {
+        match self {
+            ExprValue::Bool(item) => Ok(*item),
+            _ => bail!("值：{:?}，不是布尔值", self),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str> {
+        match self {
+            ExprValue::String(item) => Ok(item),
+            _ => bail!("值：{:?}，不是字符串", self),
+        }
+    }
+}
+
+/// 内置函数
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Len,
+    Trim,
+    Upper,
+    Lower,
+    Contains,
+    StartsWith,
+    EndsWith,
+    IsEmpty,
+    NotEmpty,
+    Abs,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        let function = match name {
+            "len" => (Function::Len, 1),
+            "trim" => (Function::Trim, 1),
+            "upper" => (Function::Upper, 1),
+            "lower" => (Function::Lower, 1),
+            "contains" => (Function::Contains, 2),
+            "starts_with" => (Function::StartsWith, 2),
+            "ends_with" => (Function::EndsWith, 2),
+            "is_empty" => (Function::IsEmpty, 1),
+            "not_empty" => (Function::NotEmpty, 1),
+            "abs" => (Function::Abs, 1),
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    fn call(&self, args: &[ExprValue]) -> Result<ExprValue> {
+        let result = match self {
+            Function::Len => match &args[0] {
+                ExprValue::Null => ExprValue::Number(0.0),
+                ExprValue::List(items) => ExprValue::Number(items.len() as f64),
+                value => ExprValue::Number(value.as_str()?.chars().count() as f64),
+            },
+            Function::Trim => ExprValue::String(args[0].as_str()?.trim().to_string()),
+            Function::Upper => ExprValue::String(args[0].as_str()?.to_uppercase()),
+            Function::Lower => ExprValue::String(args[0].as_str()?.to_lowercase()),
+            Function::Contains => match &args[0] {
+                ExprValue::List(items) => ExprValue::Bool(items.iter().any(|item| equals(item, &args[1]))),
+                value => ExprValue::Bool(value.as_str()?.contains(args[1].as_str()?)),
+            },
+            Function::StartsWith => ExprValue::Bool(args[0].as_str()?.starts_with(args[1].as_str()?)),
+            Function::EndsWith => ExprValue::Bool(args[0].as_str()?.ends_with(args[1].as_str()?)),
+            Function::IsEmpty => ExprValue::Bool(args[0].is_empty()),
+            Function::NotEmpty => ExprValue::Bool(!args[0].is_empty()),
+            Function::Abs => match args[0].as_number() {
+                Some(number) => ExprValue::Number(number.abs()),
+                None => bail!("值：{:?}，不是数字", args[0]),
+            },
+        };
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn from_token(token: &str) -> Option<(Self, u8)> {
+        let op = match token {
+            "||" => (BinaryOp::Or, 1),
+            "&&" => (BinaryOp::And, 2),
+            "==" => (BinaryOp::Eq, 3),
+            "!=" => (BinaryOp::Ne, 3),
+            "<" => (BinaryOp::Lt, 4),
+            "<=" => (BinaryOp::Le, 4),
+            ">" => (BinaryOp::Gt, 4),
+            ">=" => (BinaryOp::Ge, 4),
+            "+" => (BinaryOp::Add, 5),
+            "-" => (BinaryOp::Sub, 5),
+            "*" => (BinaryOp::Mul, 6),
+            "/" => (BinaryOp::Div, 6),
+            "%" => (BinaryOp::Rem, 6),
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+/// 语法树节点
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(ExprValue),
+    Var(String),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// 词法分析
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    const OPERATORS: [&str; 16] = [
+        "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")",
+    ];
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(index + 1).is_some_and(|c| c.is_ascii_digit())) {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+            let text: String = chars[start..index].iter().collect();
+            match text.parse::<f64>() {
+                Ok(number) => tokens.push(Token::Number(number)),
+                Err(_) => bail!("数字：{}，格式不正确", text),
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            tokens.push(Token::Ident(chars[start..index].iter().collect()));
+        } else if c == '\'' || c == '"' {
+            let mut text = String::new();
+            index += 1;
+            loop {
+                match chars.get(index) {
+                    None => bail!("字符串没有结束引号"),
+                    Some(item) if *item == c => break,
+                    Some('\\') => {
+                        match chars.get(index + 1) {
+                            None => bail!("字符串没有结束引号"),
+                            Some(item) => text.push(*item),
+                        }
+                        index += 2;
+                    }
+                    Some(item) => {
+                        text.push(*item);
+                        index += 1;
+                    }
+                }
+            }
+            index += 1;
+            tokens.push(Token::Str(text));
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            index += 1;
+        } else {
+            let rest: String = chars[index..chars.len().min(index + 2)].iter().collect();
+            let op = OPERATORS.iter().find(|op| rest.starts_with(**op));
+            match op {
+                Some(&"(") => tokens.push(Token::LParen),
+                Some(&")") => tokens.push(Token::RParen),
+                Some(op) => tokens.push(Token::Op(op)),
+                None => bail!("不支持的字符：{}", c),
+            }
+            index += op.map(|op| op.len()).unwrap_or(1);
+        }
+    }
+    Ok(tokens)
+}
+
+/// 语法分析，按照运算符优先级解析
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Node> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            bail!("表达式嵌套层级不能超过{}", MAX_DEPTH)
+        }
+        let mut left = self.unary()?;
+        while let Some(Token::Op(token)) = self.peek() {
+            let (op, precedence) = match BinaryOp::from_token(token) {
+                Some(op) => op,
+                None => bail!("运算符：{}，位置不正确", token),
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.position += 1;
+            let right = self.expression(precedence + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        self.depth -= 1;
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        match self.next() {
+            Some(Token::Op("!")) => Ok(Node::Not(Box::new(self.nested_unary()?))),
+            Some(Token::Op("-")) => Ok(Node::Neg(Box::new(self.nested_unary()?))),
+            Some(Token::Number(number)) => Ok(Node::Literal(ExprValue::Number(number))),
+            Some(Token::Str(text)) => Ok(Node::Literal(ExprValue::String(text))),
+            Some(Token::LParen) => {
+                let node = self.expression(1)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(node),
+                    _ => bail!("缺少右括号"),
+                }
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Node::Literal(ExprValue::Bool(true))),
+                "false" => Ok(Node::Literal(ExprValue::Bool(false))),
+                "null" => Ok(Node::Literal(ExprValue::Null)),
+                _ if self.peek() == Some(&Token::LParen) => self.call(&name),
+                _ => Ok(Node::Var(name)),
+            },
+            Some(token) => bail!("表达式：{:?}，位置不正确", token),
+            None => bail!("表达式不完整"),
+        }
+    }
+
+    fn nested_unary(&mut self) -> Result<Node> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            bail!("表达式嵌套层级不能超过{}", MAX_DEPTH)
+        }
+        let node = self.unary();
+        self.depth -= 1;
+        node
+    }
+
+    fn call(&mut self, name: &str) -> Result<Node> {
+        let (function, arity) = match Function::from_name(name) {
+            Some(function) => function,
+            None => bail!("函数：{}，不存在", name),
+        };
+        // 跳过左括号
+        self.position += 1;
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.position += 1;
+        } else {
+            loop {
+                args.push(self.expression(1)?);
+                match self.next() {
+                    Some(Token::Comma) => continue,
+                    Some(Token::RParen) => break,
+                    _ => bail!("函数：{}，缺少右括号", name),
+                }
+            }
+        }
+        if args.len() != arity {
+            bail!("函数：{}，需要{}个参数", name, arity)
+        }
+        Ok(Node::Call(function, args))
+    }
+}
+
+/// 解析后的表达式
+#[derive(Debug, Clone)]
+pub struct Expression {
+    node: Node,
+}
+
+impl Expression {
+    /// 解析表达式
+    /// @param source 表达式字符串
+    pub fn parse(source: &str) -> Result<Self> {
+        if source.len() > MAX_LENGTH {
+            bail!("表达式长度不能超过{}", MAX_LENGTH)
+        }
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            depth: 0,
+        };
+        let node = parser.expression(1)?;
+        if let Some(token) = parser.peek() {
+            bail!("表达式：{:?}，位置不正确", token)
+        }
+        Ok(Self { node })
+    }
+
+    /// 表达式引用的题目名称，按照出现顺序去重
+    pub fn variables(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        collect_variables(&self.node, &mut result);
+        result
+    }
+
+    /// 是否使用了判空函数，使用判空函数的表达式在题目为空时也需要计算
+    pub fn has_empty_check(&self) -> bool {
+        has_empty_check(&self.node)
+    }
+
+    /// 计算表达式
+    /// @param lookup 根据题目名称获取值
+    pub fn eval<F>(&self, lookup: &F) -> Result<ExprValue>
+    where
+        F: Fn(&str) -> ExprValue,
+    {
+        eval(&self.node, lookup)
+    }
+
+    /// 根据表单数据计算表达式，结果必须是布尔值
+    /// @param data 表单数据
+    pub fn eval_json(&self, data: &Map<String, Value>) -> Result<bool> {
+        let lookup = |name: &str| match data.get(name) {
+            None => ExprValue::Null,
+            Some(value) => ExprValue::from_json(value),
+        };
+        self.eval(&lookup)?.as_bool()
+    }
+}
+
+fn collect_variables(node: &Node, result: &mut Vec<String>) {
+    match node {
+        Node::Literal(_) => {}
+        Node::Var(name) => {
+            if !result.contains(name) {
+                result.push(name.clone());
+            }
+        }
+        Node::Not(item) | Node::Neg(item) => collect_variables(item, result),
+        Node::Binary(_, left, right) => {
+            collect_variables(left, result);
+            collect_variables(right, result);
+        }
+        Node::Call(_, args) => args.iter().for_each(|arg| collect_variables(arg, result)),
+    }
+}
+
+fn has_empty_check(node: &Node) -> bool {
+    match node {
+        Node::Literal(_) | Node::Var(_) => false,
+        Node::Not(item) | Node::Neg(item) => has_empty_check(item),
+        Node::Binary(_, left, right) => has_empty_check(left) || has_empty_check(right),
+        Node::Call(function, args) => {
+            matches!(function, Function::IsEmpty | Function::NotEmpty | Function::Len)
+                || args.iter().any(has_empty_check)
+        }
+    }
+}
+
+fn eval<F>(node: &Node, lookup: &F) -> Result<ExprValue>
+where
+    F: Fn(&str) -> ExprValue,
+{
+    let result = match node {
+        Node::Literal(value) => value.clone(),
+        Node::Var(name) => lookup(name),
+        Node::Not(item) => ExprValue::Bool(!eval(item, lookup)?.as_bool()?),
+        Node::Neg(item) => match eval(item, lookup)?.as_number() {
+            Some(number) => ExprValue::Number(-number),
+            None => bail!("取负数的值必须是数字"),
+        },
+        Node::Binary(BinaryOp::And, left, right) => {
+            ExprValue::Bool(eval(left, lookup)?.as_bool()? && eval(right, lookup)?.as_bool()?)
+        }
+        Node::Binary(BinaryOp::Or, left, right) => {
+            ExprValue::Bool(eval(left, lookup)?.as_bool()? || eval(right, lookup)?.as_bool()?)
+        }
+        Node::Binary(op, left, right) => binary(*op, &eval(left, lookup)?, &eval(right, lookup)?)?,
+        Node::Call(function, args) => {
+            let mut values = Vec::new();
+            for arg in args.iter() {
+                values.push(eval(arg, lookup)?);
+            }
+            function.call(&values)?
+        }
+    };
+    Ok(result)
+}
+
+/// 判断两个值是否相等，可以转换成数字的按照数字比较
+fn equals(left: &ExprValue, right: &ExprValue) -> bool {
+    match (left.as_number(), right.as_number()) {
+        (Some(left), Some(right)) => left == right,
+        _ => left == right,
+    }
+}
+
+fn binary(op: BinaryOp, left: &ExprValue, right: &ExprValue) -> Result<ExprValue> {
+    let result = match op {
+        BinaryOp::Eq => ExprValue::Bool(equals(left, right)),
+        BinaryOp::Ne => ExprValue::Bool(!equals(left, right)),
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            let ordering = match (left.as_number(), right.as_number(), left, right) {
+                (Some(left), Some(right), _, _) => left.partial_cmp(&right),
+                (_, _, ExprValue::String(left), ExprValue::String(right)) => Some(left.cmp(right)),
+                _ => bail!("值：{:?}和{:?}，不能比较大小", left, right),
+            };
+            let ordering = match ordering {
+                Some(ordering) => ordering,
+                None => return Ok(ExprValue::Bool(false)),
+            };
+            ExprValue::Bool(match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            })
+        }
+        _ => match (left.as_number(), right.as_number()) {
+            (Some(left), Some(right)) => ExprValue::Number(match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Sub => left - right,
+                BinaryOp::Mul => left * right,
+                BinaryOp::Div | BinaryOp::Rem if right == 0.0 => bail!("除数不能为0"),
+                BinaryOp::Div => left / right,
+                _ => left % right,
+            }),
+            // 字符串相加进行拼接
+            _ => match (op, left, right) {
+                (BinaryOp::Add, ExprValue::String(left), ExprValue::String(right)) => {
+                    ExprValue::String(format!("{left}{right}"))
+                }
+                _ => bail!("值：{:?}和{:?}，不能进行算术运算", left, right),
+            },
+        },
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval_json(expression: &str, data: Value) -> Result<bool> {
+        let data = data.as_object().cloned().unwrap_or_default();
+        Expression::parse(expression)?.eval_json(&data)
+    }
+
+    #[test]
+    fn eval_expression() {
+        let expression = "-180<name26 && name26 <180";
+        assert!(eval_json(expression, json!({"name26": 106.2})).unwrap());
+        assert!(eval_json(expression, json!({"name26": "106.2"})).unwrap());
+        assert!(!eval_json(expression, json!({"name26": 190})).unwrap());
+        assert!(eval_json("(a + b) * 2 == 10 || !true", json!({"a": 2, "b": "3"})).unwrap());
+        assert!(eval_json("len(trim(a)) >= 2 && starts_with(trim(a), '银')", json!({"a": " 银川 "})).unwrap());
+        assert!(eval_json("contains(a, '01') && not_empty(a)", json!({"a": ["01", "02"]})).unwrap());
+        assert!(eval_json("is_empty(b)", json!({})).unwrap());
+        assert_eq!(
+            Expression::parse("a > 1 && b < a + c").unwrap().variables(),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn reject_expression() {
+        assert!(Expression::parse("a >").is_err());
+        assert!(Expression::parse("a ; drop").is_err());
+        assert!(Expression::parse("exec('rm')").is_err());
+        assert!(Expression::parse("len(a, b)").is_err());
+        assert!(Expression::parse(&"(".repeat(100)).is_err());
+        assert!(eval_json("a / 0 > 1", json!({"a": 1})).is_err());
+        assert!(eval_json("a + 1", json!({"a": 1})).is_err());
+    }
+}
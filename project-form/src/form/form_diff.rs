@@ -0,0 +1,191 @@
+use serde::Serialize;
+
+use crate::form::form_json::FormTemplate;
+use crate::form::question::{Item, Question};
+
+/// 两个表单版本的题目差异
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormDiff {
+    // 新增的题目
+    pub added: Vec<QuestionInfo>,
+    // 删除的题目
+    pub removed: Vec<QuestionInfo>,
+    // 类型变化的题目
+    pub retyped: Vec<QuestionRetyped>,
+    // 选项变化的题目
+    pub items_changed: Vec<QuestionItemsChanged>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionInfo {
+    pub name: String,
+    pub title: String,
+    pub r#type: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionRetyped {
+    pub name: String,
+    pub title: String,
+    pub from_type: String,
+    pub to_type: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuestionItemsChanged {
+    pub name: String,
+    pub title: String,
+    // 新增的选项
+    pub added: Vec<Item>,
+    // 删除的选项
+    pub removed: Vec<Item>,
+    // 编码不变，名称变化的选项
+    pub renamed: Vec<ItemRenamed>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemRenamed {
+    pub code: String,
+    pub from_name: String,
+    pub to_name: String,
+}
+
+impl FormDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retyped.is_empty()
+            && self.items_changed.is_empty()
+    }
+}
+
+impl From<&Question> for QuestionInfo {
+    fn from(value: &Question) -> Self {
+        Self {
+            name: value.name.clone(),
+            title: value.title.clone(),
+            r#type: value.r#type.clone(),
+        }
+    }
+}
+
+impl FormTemplate {
+    /// 数据题目列表，过滤掉分组
+    pub fn data_questions(&self) -> Vec<&Question> {
+        self.form
+            .questions
+            .iter()
+            .filter(|item| item.r#type != "SectionType")
+            .collect()
+    }
+
+    /// 比较两个表单的题目差异，题目根据name对应
+    /// @param target 目标表单
+    pub fn diff(&self, target: &FormTemplate) -> FormDiff {
+        let source_questions = self.data_questions();
+        let target_questions = target.data_questions();
+        let mut result = FormDiff::default();
+        for source in source_questions.iter() {
+            if !target_questions.iter().any(|item| item.name == source.name) {
+                result.removed.push((*source).into());
+            }
+        }
+        for target in target_questions.iter() {
+            let source = match source_questions.iter().find(|item| item.name == target.name) {
+                None => {
+                    result.added.push((*target).into());
+                    continue;
+                }
+                Some(source) => source,
+            };
+            if source.r#type != target.r#type {
+                result.retyped.push(QuestionRetyped {
+                    name: target.name.clone(),
+                    title: target.title.clone(),
+                    from_type: source.r#type.clone(),
+                    to_type: target.r#type.clone(),
+                });
+            }
+            if let Some(changed) = diff_items(source, target) {
+                result.items_changed.push(changed);
+            }
+        }
+        result
+    }
+}
+
+/// 比较题目选项，选项根据code对应
+fn diff_items(source: &Question, target: &Question) -> Option<QuestionItemsChanged> {
+    let empty = Vec::new();
+    let source_items = source.items.as_ref().unwrap_or(&empty);
+    let target_items = target.items.as_ref().unwrap_or(&empty);
+    let removed: Vec<Item> = source_items
+        .iter()
+        .filter(|item| !target_items.iter().any(|target| target.code == item.code))
+        .cloned()
+        .collect();
+    let mut added = Vec::new();
+    let mut renamed = Vec::new();
+    for item in target_items.iter() {
+        match source_items.iter().find(|source| source.code == item.code) {
+            None => added.push(item.clone()),
+            Some(source) if source.name != item.name => renamed.push(ItemRenamed {
+                code: item.code.clone(),
+                from_name: source.name.clone(),
+                to_name: item.name.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    if added.is_empty() && removed.is_empty() && renamed.is_empty() {
+        return None;
+    }
+    Some(QuestionItemsChanged {
+        name: target.name.clone(),
+        title: target.title.clone(),
+        added,
+        removed,
+        renamed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::form::form_util::parse;
+
+    #[test]
+    fn diff_form() {
+        let source = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"1","questions":[
+            {"type":"TextType","name":"name1","title":"名称"},
+            {"type":"TextType","name":"name2","title":"面积"},
+            {"type":"SingleChoiceType","name":"name3","title":"类型","items":[{"name":"耕地","code":"01"},{"name":"林地","code":"02"}]}
+            ]}}"#,
+        )
+        .unwrap();
+        let target = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"2","questions":[
+            {"type":"NumberType","name":"name2","title":"面积"},
+            {"type":"SingleChoiceType","name":"name3","title":"类型","items":[{"name":"水田","code":"01"},{"name":"草地","code":"03"}]},
+            {"type":"SectionType","name":"name4","title":"分组"},
+            {"type":"TextType","name":"name5","title":"备注"}
+            ]}}"#,
+        )
+        .unwrap();
+        let diff = source.diff(&target);
+        assert_eq!(diff.removed[0].name, "name1");
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "name5");
+        assert_eq!(diff.retyped[0].to_type, "NumberType");
+        let items = &diff.items_changed[0];
+        assert_eq!(items.added[0].code, "03");
+        assert_eq!(items.removed[0].code, "02");
+        assert_eq!(items.renamed[0].to_name, "水田");
+        assert!(source.diff(&source).is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use common::db::form::FormCommonField;
+use common::db::schema::TableSchema;
+use common::db::{add_column_sql, drop_column_sql, rename_column_sql};
+
+use crate::form::form_json::FormTemplate;
+
+/// 数据表迁移到表单版本的计划
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigratePlan {
+    // 新增的字段
+    pub added: Vec<String>,
+    // 重命名的字段
+    pub renamed: Vec<ColumnRenamed>,
+    // 删除的字段
+    pub dropped: Vec<String>,
+    // 表单中已经不存在，保留在数据表中的字段
+    pub kept: Vec<String>,
+    // 字段类型和题目类型不一致，不做修改
+    pub mismatched: Vec<ColumnMismatched>,
+    // 执行的ddl语句
+    pub statements: Vec<String>,
+    // 是否已经执行
+    pub executed: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnRenamed {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMismatched {
+    pub name: String,
+    pub column_type: String,
+    pub question_type: String,
+}
+
+impl FormTemplate {
+    /// 比较数据表字段和表单题目，生成迁移计划，题目和字段根据name对应
+    /// @param schema 数据表结构
+    /// @param renames 重命名的字段，key为原字段名，value为题目name
+    /// @param drop_columns 是否删除表单中已经不存在的字段
+    pub fn migrate_plan(
+        &self,
+        schema: &TableSchema,
+        renames: &BTreeMap<String, String>,
+        drop_columns: bool,
+    ) -> Result<MigratePlan> {
+        let questions = self.data_questions();
+        let table_name = schema.table_name.as_str();
+        let mut result = MigratePlan::default();
+        // 检查重命名
+        for (from, to) in renames.iter() {
+            if FormCommonField::contains(from) || !schema.contains(from) {
+                bail!("字段：{}，不存在或者不能重命名", from)
+            }
+            if questions.iter().any(|item| item.name == *from) {
+                bail!("字段：{}，仍是表单题目，不能重命名", from)
+            }
+            if !questions.iter().any(|item| item.name == *to) {
+                bail!("题目：{}，在表单中不存在", to)
+            }
+            if schema.contains(to) || renames.iter().filter(|(_, item)| *item == to).count() > 1 {
+                bail!("字段：{}，已经存在，不能重命名", to)
+            }
+            result.renamed.push(ColumnRenamed {
+                from: from.clone(),
+                to: to.clone(),
+            });
+            result
+                .statements
+                .push(rename_column_sql(table_name, from, to));
+        }
+        for question in questions.iter() {
+            let column_type = question.column_type();
+            // 重命名的字段使用原字段比较类型
+            let name = renames
+                .iter()
+                .find(|(_, to)| **to == question.name)
+                .map(|(from, _)| from.as_str())
+                .unwrap_or(question.name.as_str());
+            match schema.fields.iter().find(|field| field.name == name) {
+                None => {
+                    result.added.push(question.name.clone());
+                    result
+                        .statements
+                        .push(add_column_sql(table_name, &question.name, column_type));
+                }
+                Some(field) if field.base_type() != column_type.type_name() => {
+                    result.mismatched.push(ColumnMismatched {
+                        name: question.name.clone(),
+                        column_type: field.r#type.clone(),
+                        question_type: question.r#type.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        // 表单中已经不存在的字段，没有指定删除时保留数据
+        for field in schema.fields.iter() {
+            let is_used = FormCommonField::contains(&field.name)
+                || renames.contains_key(&field.name)
+                || questions.iter().any(|item| item.name == field.name);
+            if is_used {
+                continue;
+            }
+            if drop_columns {
+                result.dropped.push(field.name.clone());
+                result
+                    .statements
+                    .push(drop_column_sql(table_name, &field.name));
+            } else {
+                result.kept.push(field.name.clone());
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use common::db::schema::{TableField, TableSchema};
+
+    use crate::form::form_util::parse;
+
+    fn schema() -> TableSchema {
+        let fields = [
+            ("id", "varchar"),
+            ("code", "varchar"),
+            ("name1", "varchar"),
+            ("name2", "varchar"),
+            ("name3", "varchar"),
+        ];
+        TableSchema {
+            table_name: "data_1".to_string(),
+            fields: fields
+                .iter()
+                .map(|(name, r#type)| TableField {
+                    name: name.to_string(),
+                    r#type: r#type.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn migrate_plan() {
+        let form = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"2","questions":[
+            {"type":"TextType","name":"name1","title":"名称"},
+            {"type":"NumberType","name":"name2","title":"面积"},
+            {"type":"TextType","name":"name4","title":"类型"},
+            {"type":"MultipleChoiceType","name":"name5","title":"用途"}
+            ]}}"#,
+        )
+        .unwrap();
+        let plan = form
+            .migrate_plan(&schema(), &BTreeMap::new(), false)
+            .unwrap();
+        assert_eq!(plan.added, vec!["name4", "name5"]);
+        assert_eq!(plan.kept, vec!["name3"]);
+        assert!(plan.dropped.is_empty());
+        assert_eq!(plan.mismatched[0].name, "name2");
+        assert_eq!(
+            plan.statements[1],
+            r#"ALTER TABLE "data_1" ADD COLUMN "name5" jsonb"#
+        );
+
+        let plan = form
+            .migrate_plan(&schema(), &BTreeMap::new(), true)
+            .unwrap();
+        assert_eq!(plan.dropped, vec!["name3"]);
+        assert!(plan.kept.is_empty());
+
+        let renames = BTreeMap::from([("name3".to_string(), "name4".to_string())]);
+        let plan = form.migrate_plan(&schema(), &renames, true).unwrap();
+        assert_eq!(plan.renamed[0].to, "name4");
+        assert_eq!(plan.added, vec!["name5"]);
+        assert!(plan.dropped.is_empty());
+        assert_eq!(
+            plan.statements[0],
+            r#"ALTER TABLE "data_1" RENAME COLUMN "name3" TO "name4""#
+        );
+
+        let renames = BTreeMap::from([("name1".to_string(), "name4".to_string())]);
+        assert!(form.migrate_plan(&schema(), &renames, false).is_err());
+    }
+}
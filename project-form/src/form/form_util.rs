@@ -32,15 +32,19 @@ pub fn handler_form_header(headers: &[String]) -> Vec<String> {
     result
 }
 
-/// 从表头列表中找出对应位置列表
-pub fn filter_code_lon_lat(headers: &[String], fields: &[String]) -> Vec<usize> {
+/// 从表头列表中找出code、lon、lat对应的位置列表，位置按照字段顺序返回
+/// 数据第一个位置会插入编号，返回的位置加1
+/// @param headers 原始表头列表
+/// @param fields code、lon、lat对应的表头
+pub fn filter_code_lon_lat(headers: &[String], fields: &[String]) -> anyhow::Result<Vec<usize>> {
     let mut result = Vec::new();
-    for (index, header) in headers.iter().enumerate() {
-        if fields.contains(header) {
-            result.push(index);
+    for field in fields.iter() {
+        match headers.iter().position(|header| header == field) {
+            Some(index) => result.push(index + 1),
+            None => anyhow::bail!("表头中没有字段：{}", field),
         }
     }
-    result
+    Ok(result)
 }
 
 /// 处理表单数据
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+use common::db::form::FormCommonField;
+use common::error::ValidationError;
+
+use crate::form::expression::Expression;
+use crate::form::form_json::FormTemplate;
+use crate::form::question::Question;
+
+/// 可以随表单数据提交的公共字段，其他公共字段由服务端维护
+/// 状态由任务流转维护，提交用户由任务分配维护，都不能随数据提交
+const SUBMIT_COMMON_FIELDS: [FormCommonField; 4] = [
+    FormCommonField::Id,
+    FormCommonField::Code,
+    FormCommonField::Lon,
+    FormCommonField::Lat,
+];
+
+impl FormTemplate {
+    /// 根据表单模板校验提交的数据，错误信息以题目name为key
+    /// @param data 提交的数据
+    /// @param current 更新时的原始数据，更新时只校验提交的字段，表达式使用合并后的数据计算
+    pub fn validate(&self, data: &Value, current: Option<&Value>) -> Result<(), ValidationError> {
+        let is_update = current.is_some();
+        let mut error = ValidationError::new();
+        let data = match data {
+            Value::Object(data) => data,
+            _ => {
+                error.add("data", "data 数据必须是HashMap格式".to_string());
+                return Err(error);
+            }
+        };
+        // 题目列表，过滤掉分组
+        let questions: HashMap<&str, &Question> = self
+            .data_questions()
+            .into_iter()
+            .map(|item| (item.name.as_str(), item))
+            .collect();
+        // 检查未知字段
+        for key in data.keys() {
+            let is_common = SUBMIT_COMMON_FIELDS
+                .iter()
+                .any(|field| field.to_string() == *key);
+            if !is_common && !questions.contains_key(key.as_str()) {
+                error.add(key, format!("字段：{}，不存在", key));
+            }
+        }
+        for question in questions.values() {
+            question.validate(data, is_update, &mut error);
+        }
+        // 题目校验通过后再计算表达式
+        if error.is_empty() {
+            match current {
+                Some(Value::Object(current)) => {
+                    let mut merged = current.clone();
+                    merged.extend(data.clone());
+                    self.validate_expressions(&merged, &mut error);
+                }
+                _ => self.validate_expressions(data, &mut error),
+            }
+        }
+        if error.is_empty() {
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// 提交数据中文件题目引用的文件编号，只返回提交了的题目
+    /// @param data 提交的数据
+    pub fn file_refs(&self, data: &Value) -> Vec<(String, Vec<String>)> {
+        self.data_questions()
+            .into_iter()
+            .filter(|question| question.is_file())
+            .filter_map(|question| {
+                let ids = question.file_ids(data.get(&question.name)?)?;
+                Some((question.name.clone(), ids))
+            })
+            .collect()
+    }
+
+    /// 只保留可以随表单数据提交的字段，用于重新提交已经保存的整行数据，例如恢复历史版本
+    /// 编号由调用方指定，不保留
+    /// @param data 已经保存的数据
+    pub fn submit_data(&self, data: &Map<String, Value>) -> Map<String, Value> {
+        let questions = self.data_questions();
+        let id = FormCommonField::Id.to_string();
+        data.iter()
+            .filter(|(key, _)| {
+                let is_common = SUBMIT_COMMON_FIELDS
+                    .iter()
+                    .any(|field| field.to_string() == **key);
+                **key != id && (is_common || questions.iter().any(|question| question.name == **key))
+            })
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// 计算表达式校验规则，失败时返回规则的提示信息
+    /// 引用的题目为空时跳过，使用了判空函数的规则除外
+    /// @param data 表单数据
+    /// @param error 错误信息
+    fn validate_expressions(&self, data: &Map<String, Value>, error: &mut ValidationError) {
+        let validations = match &self.form.validations {
+            None => return,
+            Some(validations) => &validations.expression_validations,
+        };
+        for validation in validations.iter() {
+            let expression = match Expression::parse(&validation.expression) {
+                Ok(expression) => expression,
+                Err(err) => {
+                    error.add("form", format!("表达式：{}，{}", validation.expression, err));
+                    continue;
+                }
+            };
+            let variables = expression.variables();
+            let has_empty = variables.iter().any(|name| is_empty(data.get(name)));
+            if has_empty && !expression.has_empty_check() {
+                continue;
+            }
+            // 计算出错按照校验失败处理
+            if !expression.eval_json(data).unwrap_or(false) {
+                if variables.is_empty() {
+                    error.add("form", validation.message.clone());
+                }
+                for name in variables.iter() {
+                    error.add(name, validation.message.clone());
+                }
+            }
+        }
+    }
+
+    /// 检查表达式是否能够解析，并且只引用了存在的题目
+    pub fn check_expressions(&self) -> Result<()> {
+        let validations = match &self.form.validations {
+            None => return Ok(()),
+            Some(validations) => &validations.expression_validations,
+        };
+        for validation in validations.iter() {
+            let expression = match Expression::parse(&validation.expression) {
+                Ok(expression) => expression,
+                Err(err) => bail!("表达式：{}，{}", validation.expression, err),
+            };
+            for name in expression.variables().iter() {
+                let exists = self.data_questions().iter().any(|item| item.name == *name);
+                if !exists {
+                    bail!("表达式：{}，引用的题目：{}，不存在", validation.expression, name)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Question {
+    /// 校验题目对应的数据
+    /// @param data 提交的数据
+    /// @param is_update 是否是更新
+    /// @param error 错误信息
+    fn validate(&self, data: &Map<String, Value>, is_update: bool, error: &mut ValidationError) {
+        let value = data.get(&self.name);
+        // 必填校验，新增时必须提交，更新时提交了不能为空
+        if is_empty(value) {
+            let required = self.required.unwrap_or(false);
+            if required && (!is_update || value.is_some()) {
+                error.add(&self.name, self.message(format!("{}不能为空", self.title)));
+            }
+            return;
+        }
+        let value = value.unwrap_or(&Value::Null);
+        let result = match self.r#type.as_str() {
+            "NumberType" | "LongitudeType" | "LatitudeType" => match value {
+                Value::Number(_) => Ok(()),
+                Value::String(item) if item.trim().parse::<f64>().is_ok() => Ok(()),
+                _ => Err(format!("{}必须是数字", self.title)),
+            },
+            "SingleChoiceType" => self.check_choice(value),
+            "MultipleChoiceType" | "MultiChoiceType" => match value {
+                Value::Array(items) => items.iter().try_for_each(|item| self.check_choice(item)),
+                _ => Err(format!("{}必须是选项数组", self.title)),
+            },
+            "ImageType" => match self.file_ids(value) {
+                Some(_) => Ok(()),
+                None => Err(format!("{}必须是文件编号数组", self.title)),
+            },
+            _ => Ok(()),
+        };
+        if let Err(message) = result {
+            error.add(&self.name, self.message(message));
+        }
+    }
+
+    /// 检查选项值是否在选项编码中
+    fn check_choice(&self, value: &Value) -> Result<(), String> {
+        let items = match &self.items {
+            None => return Ok(()),
+            Some(items) => items,
+        };
+        let code = match value {
+            Value::String(item) => item.clone(),
+            Value::Number(item) => item.to_string(),
+            _ => return Err(format!("{}选项值不正确", self.title)),
+        };
+        if items.iter().any(|item| item.code == code) {
+            Ok(())
+        } else {
+            Err(format!("{}选项值：{}，不存在", self.title, code))
+        }
+    }
+
+    /// 优先使用题目配置的错误信息
+    fn message(&self, message: String) -> String {
+        match &self.error {
+            Some(error) if !error.is_empty() => error.clone(),
+            _ => message,
+        }
+    }
+}
+
+/// 判断值是否为空，空字符串和空数组视为空
+fn is_empty(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(item)) => item.trim().is_empty(),
+        Some(Value::Array(items)) => items.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::form::form_util::parse;
+
+    #[test]
+    fn validate_form_data() {
+        let form = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"1","questions":[
+            {"type":"NumberType","name":"name1","title":"面积","required":true},
+            {"type":"SingleChoiceType","name":"name2","title":"类型","items":[{"name":"耕地","code":"01"}]}
+            ]}}"#,
+        )
+        .unwrap();
+        assert!(form.validate(&json!({"name1": "12.5", "name2": "01", "code": "a"}), None).is_ok());
+        // 状态和提交用户由服务端维护
+        let error = form
+            .validate(&json!({"name1": 1, "status": 4, "user_id": "1"}), None)
+            .unwrap_err();
+        assert!(error.errors.contains_key("status"));
+        assert!(error.errors.contains_key("user_id"));
+        let error = form
+            .validate(&json!({"name2": "02", "name3": 1}), None)
+            .unwrap_err();
+        assert_eq!(error.errors.len(), 3);
+        assert!(error.errors.contains_key("name1"));
+        assert!(error.errors.contains_key("name2"));
+        assert!(error.errors.contains_key("name3"));
+        // 更新时只校验提交的字段
+        let current = json!({"name1": "1"});
+        assert!(form.validate(&json!({"name2": "01"}), Some(&current)).is_ok());
+        assert!(form.validate(&json!({"name1": "abc"}), Some(&current)).is_err());
+        // 重新提交保存的数据时去掉服务端维护的字段和表单中已经删除的题目
+        let row = json!({"id": "1", "code": "a", "name1": 1, "name2": null, "name3": 2, "status": 2, "user_id": "1"});
+        let data = Value::Object(form.submit_data(row.as_object().unwrap()));
+        assert_eq!(data, json!({"code": "a", "name1": 1, "name2": null}));
+        assert!(form.validate(&data, Some(&row)).is_ok());
+    }
+
+    #[test]
+    fn validate_expression() {
+        let form = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"1","questions":[
+            {"type":"NumberType","name":"name1","title":"经度"},
+            {"type":"NumberType","name":"name2","title":"纬度"}],
+            "validations":{"expressionValidations":[{"expression":"-180<name1 && name1 <180","message":"经度错误"}]}}}"#,
+        )
+        .unwrap();
+        assert!(form.check_expressions().is_ok());
+        assert!(form.validate(&json!({"name1": 100}), None).is_ok());
+        assert!(form.validate(&json!({"name2": 100}), None).is_ok());
+        let error = form.validate(&json!({"name1": 190}), None).unwrap_err();
+        assert_eq!(error.errors.get("name1").unwrap(), "经度错误");
+        // 更新时使用原始数据计算
+        let current = json!({"name1": 190});
+        assert!(form.validate(&json!({"name2": 1}), Some(&current)).is_err());
+        assert!(form.validate(&json!({"name1": 1}), Some(&current)).is_ok());
+
+        let form = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"1","questions":[],
+            "validations":{"expressionValidations":[{"expression":"name9 > 1","message":""}]}}}"#,
+        )
+        .unwrap();
+        assert!(form.check_expressions().is_err());
+    }
+
+    #[test]
+    fn file_refs() {
+        let form = parse(
+            r#"{"form":{"name":"f","title":"f","description":"","version":"1","questions":[
+            {"type":"ImageType","name":"name1","title":"照片"},
+            {"type":"ImageType","name":"name2","title":"视频"},
+            {"type":"TextType","name":"name3","title":"备注"}]}}"#,
+        )
+        .unwrap();
+        let data = json!({"name1": ["a", {"id": "b", "subTitle": "土壤照片"}], "name3": "c"});
+        assert!(form.validate(&data, None).is_ok());
+        assert_eq!(
+            form.file_refs(&data),
+            vec![("name1".to_string(), vec!["a".to_string(), "b".to_string()])]
+        );
+        let error = form.validate(&json!({"name2": "a"}), None).unwrap_err();
+        assert!(error.errors.contains_key("name2"));
+    }
+}
@@ -2,3 +2,7 @@ pub mod question;
 pub mod form_json;
 pub mod form_util;
 pub mod validation;
+pub mod form_validate;
+pub mod form_diff;
+pub mod form_migrate;
+pub mod expression;
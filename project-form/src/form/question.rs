@@ -1,4 +1,6 @@
+use common::db::db_type::ColumnType;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,7 +17,43 @@ pub struct Question {
     pub items: Option<Vec<Item>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Question {
+    /// 根据题目类型获取数据表字段类型
+    pub fn column_type(&self) -> ColumnType {
+        match self.r#type.as_str() {
+            "NumberType" => ColumnType::Numeric,
+            "LongitudeType" | "LatitudeType" => ColumnType::Double,
+            // 多选保存选项编码数组，图片保存文件引用
+            "MultipleChoiceType" | "MultiChoiceType" | "ImageType" => ColumnType::Jsonb,
+            _ => ColumnType::Varchar,
+        }
+    }
+
+    /// 是否是文件题目，数据保存引用的文件编号
+    pub fn is_file(&self) -> bool {
+        self.r#type == "ImageType"
+    }
+
+    /// 读取文件题目引用的文件编号，数组元素为文件编号或者包含id的对象
+    /// 格式不正确时返回None
+    /// @param value 题目数据
+    pub fn file_ids(&self, value: &Value) -> Option<Vec<String>> {
+        match value {
+            Value::Null => Some(Vec::new()),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::String(id) => Some(id.clone()),
+                    Value::Object(item) => item.get("id")?.as_str().map(|id| id.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Item {
     pub name: String,
@@ -0,0 +1,265 @@
+use std::f64::consts::PI;
+
+use anyhow::{bail, Result};
+
+/// web墨卡托使用的球体半径
+const WEB_MERCATOR_RADIUS: f64 = 6378137.0;
+/// CGCS2000椭球
+const CGCS2000_A: f64 = 6378137.0;
+const CGCS2000_INVERSE_F: f64 = 298.257222101;
+
+/// 坐标系，只支持WGS84和CGCS2000基准，两者差异在厘米级，不做基准转换
+#[derive(Debug, Clone, PartialEq)]
+pub enum Crs {
+    /// 经纬度
+    Geographic,
+    /// web墨卡托
+    WebMercator,
+    /// 横轴墨卡托，高斯克吕格投影
+    TransverseMercator {
+        // 椭球长半轴
+        a: f64,
+        // 扁率
+        f: f64,
+        // 中央经线，度
+        lon0: f64,
+        // 起始纬度，度
+        lat0: f64,
+        // 比例系数
+        k0: f64,
+        // 东偏移
+        false_easting: f64,
+        // 北偏移
+        false_northing: f64,
+        // 长度单位转换成米的系数
+        unit: f64,
+    },
+}
+
+impl Crs {
+    /// 根据prj文件中的WKT解析坐标系
+    /// @param wkt 坐标系描述
+    pub fn from_wkt(wkt: &str) -> Result<Self> {
+        let upper = wkt.to_uppercase();
+        // 北京54和西安80需要七参数转换，暂不支持
+        let datum = quoted_after(&upper, "DATUM[").unwrap_or_default();
+        let is_supported = ["WGS", "2000", "CGCS"]
+            .iter()
+            .any(|item| datum.contains(item));
+        if !is_supported {
+            bail!("不支持的坐标系基准：{}", datum)
+        }
+        if !upper.trim_start().starts_with("PROJCS") {
+            return Ok(Crs::Geographic);
+        }
+        let projection = quoted_after(&upper, "PROJECTION[").unwrap_or_default();
+        if projection.contains("MERCATOR_AUXILIARY_SPHERE")
+            || projection.contains("PSEUDO_MERCATOR")
+            || (projection.contains("MERCATOR") && upper.contains("WGS_1984_WEB_MERCATOR"))
+        {
+            return Ok(Crs::WebMercator);
+        }
+        if !projection.contains("TRANSVERSE_MERCATOR") && !projection.contains("GAUSS_KRUGER") {
+            bail!("不支持的投影：{}", projection)
+        }
+        let (a, inverse_f) = spheroid(&upper).unwrap_or((CGCS2000_A, CGCS2000_INVERSE_F));
+        Ok(Crs::TransverseMercator {
+            a,
+            f: 1.0 / inverse_f,
+            lon0: parameter(&upper, "CENTRAL_MERIDIAN").unwrap_or(0.0),
+            lat0: parameter(&upper, "LATITUDE_OF_ORIGIN").unwrap_or(0.0),
+            k0: parameter(&upper, "SCALE_FACTOR").unwrap_or(1.0),
+            false_easting: parameter(&upper, "FALSE_EASTING").unwrap_or(0.0),
+            false_northing: parameter(&upper, "FALSE_NORTHING").unwrap_or(0.0),
+            unit: linear_unit(&upper).unwrap_or(1.0),
+        })
+    }
+
+    /// 根据EPSG编号获取坐标系，支持WGS84、CGCS2000经纬度、web墨卡托和CGCS2000高斯克吕格投影
+    /// @param code EPSG编号
+    pub fn from_epsg(code: u32) -> Result<Self> {
+        let result = match code {
+            4326 | 4490 | 4979 => Crs::Geographic,
+            3857 | 900913 | 102100 => Crs::WebMercator,
+            // 6度带，带号13-23，坐标带带号
+            4491..=4501 => {
+                let zone = (code - 4491 + 13) as f64;
+                Self::gauss_kruger(zone * 6.0 - 3.0, zone * 1000000.0 + 500000.0)
+            }
+            // 6度带，中央经线75E-135E
+            4502..=4512 => Self::gauss_kruger(75.0 + (code - 4502) as f64 * 6.0, 500000.0),
+            // 3度带，带号25-45，坐标带带号
+            4513..=4533 => {
+                let zone = (code - 4513 + 25) as f64;
+                Self::gauss_kruger(zone * 3.0, zone * 1000000.0 + 500000.0)
+            }
+            // 3度带，中央经线75E-135E
+            4534..=4554 => Self::gauss_kruger(75.0 + (code - 4534) as f64 * 3.0, 500000.0),
+            _ => bail!("不支持的坐标系：EPSG:{}", code),
+        };
+        Ok(result)
+    }
+
+    /// CGCS2000高斯克吕格投影
+    fn gauss_kruger(lon0: f64, false_easting: f64) -> Self {
+        Crs::TransverseMercator {
+            a: CGCS2000_A,
+            f: 1.0 / CGCS2000_INVERSE_F,
+            lon0,
+            lat0: 0.0,
+            k0: 1.0,
+            false_easting,
+            false_northing: 0.0,
+            unit: 1.0,
+        }
+    }
+
+    /// 是否需要转换
+    pub fn is_geographic(&self) -> bool {
+        matches!(self, Crs::Geographic)
+    }
+
+    /// 转换成WGS84经纬度
+    /// @param x 横坐标
+    /// @param y 纵坐标
+    pub fn to_wgs84(&self, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Crs::Geographic => (x, y),
+            Crs::WebMercator => {
+                let lon = x / WEB_MERCATOR_RADIUS;
+                let lat = 2.0 * (y / WEB_MERCATOR_RADIUS).exp().atan() - PI / 2.0;
+                (lon.to_degrees(), lat.to_degrees())
+            }
+            Crs::TransverseMercator {
+                a,
+                f,
+                lon0,
+                lat0,
+                k0,
+                false_easting,
+                false_northing,
+                unit,
+            } => {
+                let x = x * unit - false_easting;
+                let y = y * unit - false_northing;
+                let (lon, lat) = inverse_transverse_mercator(*a, *f, lat0.to_radians(), *k0, x, y);
+                (lon0 + lon.to_degrees(), lat.to_degrees())
+            }
+        }
+    }
+}
+
+/// 横轴墨卡托反算，返回相对中央经线的经度和纬度，弧度
+fn inverse_transverse_mercator(a: f64, f: f64, lat0: f64, k0: f64, x: f64, y: f64) -> (f64, f64) {
+    let e2 = f * (2.0 - f);
+    let ep2 = e2 / (1.0 - e2);
+    let m = meridian_arc(a, e2, lat0) + y / k0;
+    let mu = m / (a * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+    // 底点纬度
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+    let (sin, cos, tan) = (phi1.sin(), phi1.cos(), phi1.tan());
+    let c1 = ep2 * cos.powi(2);
+    let t1 = tan.powi(2);
+    let n1 = a / (1.0 - e2 * sin.powi(2)).sqrt();
+    let r1 = a * (1.0 - e2) / (1.0 - e2 * sin.powi(2)).powf(1.5);
+    let d = x / (n1 * k0);
+    let lat = phi1
+        - (n1 * tan / r1)
+            * (d.powi(2) / 2.0
+                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2)
+                    - 252.0 * ep2
+                    - 3.0 * c1.powi(2))
+                    * d.powi(6)
+                    / 720.0);
+    let lon = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
+            * d.powi(5)
+            / 120.0)
+        / cos;
+    (lon, lat)
+}
+
+/// 子午线弧长
+fn meridian_arc(a: f64, e2: f64, phi: f64) -> f64 {
+    a * ((1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0) * phi
+        - (3.0 * e2 / 8.0 + 3.0 * e2.powi(2) / 32.0 + 45.0 * e2.powi(3) / 1024.0)
+            * (2.0 * phi).sin()
+        + (15.0 * e2.powi(2) / 256.0 + 45.0 * e2.powi(3) / 1024.0) * (4.0 * phi).sin()
+        - (35.0 * e2.powi(3) / 3072.0) * (6.0 * phi).sin())
+}
+
+/// 读取关键字后第一个引号中的内容
+fn quoted_after(wkt: &str, key: &str) -> Option<String> {
+    let rest = &wkt[wkt.find(key)? + key.len()..];
+    let start = rest.find('"')? + 1;
+    let end = rest[start..].find('"')? + start;
+    Some(rest[start..end].to_string())
+}
+
+/// 读取关键字后的数值列表，例如 SPHEROID["name",6378137,298.25]
+fn values_after(wkt: &str, key: &str) -> Option<Vec<f64>> {
+    let rest = &wkt[wkt.find(key)? + key.len()..];
+    let end = rest.find(']')?;
+    let values = rest[..end]
+        .split(',')
+        .filter_map(|item| item.trim().parse::<f64>().ok())
+        .collect();
+    Some(values)
+}
+
+/// 椭球长半轴和扁率倒数
+fn spheroid(wkt: &str) -> Option<(f64, f64)> {
+    let values = values_after(wkt, "SPHEROID[")?;
+    match values.as_slice() {
+        [a, inverse_f, ..] => Some((*a, *inverse_f)),
+        _ => None,
+    }
+}
+
+/// 投影参数，例如 PARAMETER["CENTRAL_MERIDIAN",117.0]
+fn parameter(wkt: &str, name: &str) -> Option<f64> {
+    let key = format!("PARAMETER[\"{}\"", name);
+    values_after(wkt, &key)?.first().copied()
+}
+
+/// 投影坐标系的长度单位，在最后一个UNIT中
+fn linear_unit(wkt: &str) -> Option<f64> {
+    let index = wkt.rfind("UNIT[")?;
+    values_after(&wkt[index..], "UNIT[")?.first().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CGCS2000_117E: &str = r#"PROJCS["CGCS2000_3_Degree_GK_CM_117E",GEOGCS["GCS_China_Geodetic_Coordinate_System_2000",DATUM["D_China_2000",SPHEROID["CGCS2000",6378137.0,298.257222101]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]],PROJECTION["Gauss_Kruger"],PARAMETER["False_Easting",500000.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",117.0],PARAMETER["Scale_Factor",1.0],PARAMETER["Latitude_Of_Origin",0.0],UNIT["Meter",1.0]]"#;
+
+    #[test]
+    fn reproject() {
+        let crs = Crs::from_wkt(CGCS2000_117E).unwrap();
+        assert_eq!(crs, Crs::from_epsg(4548).unwrap());
+        // 中央经线上纬度30度
+        let (lon, lat) = crs.to_wgs84(500000.0, 3320113.398);
+        assert!((lon - 117.0).abs() < 1e-8);
+        assert!((lat - 30.0).abs() < 1e-6);
+        // 东经118度，纬度30度
+        let (lon, lat) = crs.to_wgs84(596488.748, 3320534.436);
+        assert!((lon - 118.0).abs() < 1e-7);
+        assert!((lat - 30.0).abs() < 1e-7);
+
+        let (lon, lat) = Crs::WebMercator.to_wgs84(13046644.321, 3503549.844);
+        assert!((lon - 117.2).abs() < 1e-6);
+        assert!((lat - 30.0).abs() < 1e-6);
+
+        let wgs84 = r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#;
+        assert!(Crs::from_wkt(wgs84).unwrap().is_geographic());
+        let xian80 = r#"GEOGCS["GCS_Xian_1980",DATUM["D_Xian_1980",SPHEROID["Xian_1980",6378140.0,298.257]]]"#;
+        assert!(Crs::from_wkt(xian80).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+use common::error::RowError;
+use common::file::csv::CsvRecord;
+
+use crate::import::crs::Crs;
+use crate::import::shapefile::read_shapefile_zip;
+
+/// 非点几何保存的字段名
+pub const GEOMETRY_FIELD: &str = "geometry";
+
+/// 要素，几何使用GeoJSON格式
+#[derive(Debug, Clone)]
+pub struct Feature {
+    // 要素序号，从1开始，用于错误提示
+    pub line: u64,
+    pub properties: Map<String, Value>,
+    pub geometry: Option<Value>,
+}
+
+/// 空间数据读取参数
+#[derive(Debug, Clone, Default)]
+pub struct GeoOptions {
+    // 是否为zip压缩的Shapefile，否则为GeoJSON
+    pub shapefile: bool,
+    // 经度字段名
+    pub lon_field: String,
+    // 纬度字段名
+    pub lat_field: String,
+}
+
+/// 读取GeoJSON或者zip压缩的Shapefile，返回和csv相同的记录，第一条记录为表头，行号为要素序号
+/// @param data 文件数据
+/// @param options 读取参数
+pub fn read_geo(data: Vec<u8>, options: &GeoOptions) -> Result<Vec<Result<CsvRecord, RowError>>> {
+    let features = if options.shapefile {
+        read_shapefile_zip(data)?
+    } else {
+        read_geojson(&data)?
+    };
+    features_to_records(&features, &options.lon_field, &options.lat_field)
+}
+
+/// 读取GeoJSON要素集合，带有crs的旧版本GeoJSON转换成WGS84
+/// @param data 文件数据
+pub fn read_geojson(data: &[u8]) -> Result<Vec<Feature>> {
+    let value: Value = serde_json::from_slice(data)?;
+    let crs = match value
+        .pointer("/crs/properties/name")
+        .and_then(|item| item.as_str())
+    {
+        None => Crs::Geographic,
+        Some(name) => parse_crs_name(name)?,
+    };
+    let features = match value.get("type").and_then(|item| item.as_str()) {
+        Some("FeatureCollection") => match value.get("features") {
+            Some(Value::Array(features)) => features.clone(),
+            _ => bail!("GeoJSON中没有features"),
+        },
+        Some("Feature") => vec![value.clone()],
+        _ => bail!("GeoJSON必须是FeatureCollection或者Feature"),
+    };
+    let mut result = Vec::with_capacity(features.len());
+    for (index, feature) in features.into_iter().enumerate() {
+        let properties = match feature.get("properties") {
+            Some(Value::Object(properties)) => properties.clone(),
+            _ => Map::new(),
+        };
+        let geometry = match feature.get("geometry") {
+            None | Some(Value::Null) => None,
+            Some(geometry) => Some(geometry.clone()),
+        };
+        result.push(Feature {
+            line: index as u64 + 1,
+            properties,
+            geometry,
+        });
+    }
+    reproject(&mut result, &crs)?;
+    Ok(result)
+}
+
+/// 解析GeoJSON中的坐标系名称，例如 EPSG:3857、urn:ogc:def:crs:EPSG::4490
+fn parse_crs_name(name: &str) -> Result<Crs> {
+    if name.ends_with("CRS84") {
+        return Ok(Crs::Geographic);
+    }
+    let code = name.rsplit(':').next().unwrap_or_default();
+    match code.parse::<u32>() {
+        Ok(code) => Crs::from_epsg(code),
+        Err(_) => bail!("不支持的坐标系：{}", name),
+    }
+}
+
+/// 要素坐标转换成WGS84
+/// @param features 要素列表
+/// @param crs 要素的坐标系
+pub fn reproject(features: &mut [Feature], crs: &Crs) -> Result<()> {
+    if crs.is_geographic() {
+        return Ok(());
+    }
+    for feature in features.iter_mut() {
+        if let Some(geometry) = &mut feature.geometry {
+            transform_geometry(geometry, crs)?;
+        }
+    }
+    Ok(())
+}
+
+fn transform_geometry(geometry: &mut Value, crs: &Crs) -> Result<()> {
+    if let Some(Value::Array(geometries)) = geometry.get_mut("geometries") {
+        for item in geometries.iter_mut() {
+            transform_geometry(item, crs)?;
+        }
+        return Ok(());
+    }
+    match geometry.get_mut("coordinates") {
+        Some(coordinates) => transform_coordinates(coordinates, crs),
+        None => bail!("几何对象没有坐标"),
+    }
+}
+
+/// 递归转换坐标数组，最内层为 [x, y] 或者 [x, y, z]
+fn transform_coordinates(coordinates: &mut Value, crs: &Crs) -> Result<()> {
+    let items = match coordinates {
+        Value::Array(items) => items,
+        _ => bail!("坐标格式错误"),
+    };
+    if let [Value::Number(x), Value::Number(y), ..] = items.as_slice() {
+        let (x, y) = match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => crs.to_wgs84(x, y),
+            _ => bail!("坐标格式错误"),
+        };
+        items[0] = x.into();
+        items[1] = y.into();
+        return Ok(());
+    }
+    for item in items.iter_mut() {
+        transform_coordinates(item, crs)?;
+    }
+    Ok(())
+}
+
+/// 要素转换成和csv相同的记录，第一条记录为表头
+/// 属性作为字段，点几何填充经纬度字段，其他几何保存到geometry字段，经纬度使用外包矩形中心
+/// @param features 要素列表
+/// @param lon_field 经度字段名
+/// @param lat_field 纬度字段名
+pub fn features_to_records(
+    features: &[Feature],
+    lon_field: &str,
+    lat_field: &str,
+) -> Result<Vec<Result<CsvRecord, RowError>>> {
+    // 属性字段，按照第一次出现的顺序
+    let mut headers: Vec<String> = Vec::new();
+    for feature in features.iter() {
+        for key in feature.properties.keys() {
+            if !headers.contains(key) {
+                headers.push(key.clone());
+            }
+        }
+    }
+    let has_geometry = features.iter().any(|feature| {
+        let geometry_type = feature
+            .geometry
+            .as_ref()
+            .and_then(|item| item.get("type"))
+            .and_then(|item| item.as_str());
+        !matches!(geometry_type, None | Some("Point"))
+    });
+    let mut extra_fields = vec![lon_field, lat_field];
+    if has_geometry {
+        extra_fields.push(GEOMETRY_FIELD);
+    }
+    for field in extra_fields.iter() {
+        if headers.iter().any(|header| header == field) {
+            bail!("属性字段：{}，与几何字段重名", field)
+        }
+        headers.push(field.to_string());
+    }
+    let mut result = vec![Ok(CsvRecord {
+        line: 0,
+        fields: headers.clone(),
+    })];
+    let property_len = headers.len() - extra_fields.len();
+    for feature in features.iter() {
+        let mut fields: Vec<String> = headers[..property_len]
+            .iter()
+            .map(|key| value_to_string(feature.properties.get(key)))
+            .collect();
+        match representative_point(feature.geometry.as_ref()) {
+            Err(err) => {
+                result.push(Err(RowError::new(feature.line, err.to_string())));
+                continue;
+            }
+            Ok(None) => fields.extend([String::new(), String::new()]),
+            Ok(Some((lon, lat))) => fields.extend([lon.to_string(), lat.to_string()]),
+        }
+        if has_geometry {
+            match &feature.geometry {
+                Some(geometry) if geometry.get("type") != Some(&Value::from("Point")) => {
+                    fields.push(geometry.to_string())
+                }
+                _ => fields.push(String::new()),
+            }
+        }
+        result.push(Ok(CsvRecord {
+            line: feature.line,
+            fields,
+        }));
+    }
+    Ok(result)
+}
+
+/// 属性值转换成文本，对象和数组使用json文本
+fn value_to_string(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// 几何的代表点，点使用坐标，其他几何使用外包矩形中心
+fn representative_point(geometry: Option<&Value>) -> Result<Option<(f64, f64)>> {
+    let bounds = match geometry {
+        None => return Ok(None),
+        Some(geometry) => geometry_bounds(geometry)?,
+    };
+    Ok(bounds.map(|bounds| ((bounds[0] + bounds[2]) / 2.0, (bounds[1] + bounds[3]) / 2.0)))
+}
+
+/// 几何的外包矩形 [minx, miny, maxx, maxy]，没有坐标时为None
+pub(crate) fn geometry_bounds(geometry: &Value) -> Result<Option<[f64; 4]>> {
+    let mut bounds = [f64::MAX, f64::MAX, f64::MIN, f64::MIN];
+    collect_bounds(geometry, &mut bounds)?;
+    if bounds[0] > bounds[2] {
+        return Ok(None);
+    }
+    Ok(Some(bounds))
+}
+
+fn collect_bounds(geometry: &Value, bounds: &mut [f64; 4]) -> Result<()> {
+    if let Some(Value::Array(geometries)) = geometry.get("geometries") {
+        for item in geometries.iter() {
+            collect_bounds(item, bounds)?;
+        }
+        return Ok(());
+    }
+    match geometry.get("coordinates") {
+        Some(coordinates) => coordinate_bounds(coordinates, bounds),
+        None => bail!("几何对象没有坐标"),
+    }
+}
+
+fn coordinate_bounds(coordinates: &Value, bounds: &mut [f64; 4]) -> Result<()> {
+    let items = match coordinates {
+        Value::Array(items) => items,
+        _ => bail!("坐标格式错误"),
+    };
+    if let [Value::Number(x), Value::Number(y), ..] = items.as_slice() {
+        let (x, y) = match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => bail!("坐标格式错误"),
+        };
+        bounds[0] = bounds[0].min(x);
+        bounds[1] = bounds[1].min(y);
+        bounds[2] = bounds[2].max(x);
+        bounds[3] = bounds[3].max(y);
+        return Ok(());
+    }
+    for item in items.iter() {
+        coordinate_bounds(item, bounds)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geojson_to_records() {
+        let data = r#"{"type":"FeatureCollection","crs":{"type":"name","properties":{"name":"EPSG:3857"}},"features":[
+            {"type":"Feature","properties":{"code":"01","面积":12.5},"geometry":{"type":"Point","coordinates":[13046644.321,3503549.844]}},
+            {"type":"Feature","properties":{"code":"02","备注":null},"geometry":{"type":"LineString","coordinates":[[0,0],[222638.982,0]]}}
+        ]}"#;
+        let features = read_geojson(data.as_bytes()).unwrap();
+        let records: Vec<CsvRecord> = features_to_records(&features, "lon", "lat")
+            .unwrap()
+            .into_iter()
+            .map(|item| item.unwrap())
+            .collect();
+        assert_eq!(
+            records[0].fields,
+            vec!["code", "面积", "备注", "lon", "lat", "geometry"]
+        );
+        let lon: f64 = records[1].fields[3].parse().unwrap();
+        assert!((lon - 117.2).abs() < 1e-6);
+        assert_eq!(records[1].fields[5], "");
+        let lon: f64 = records[2].fields[3].parse().unwrap();
+        assert!((lon - 1.0).abs() < 1e-6);
+        assert!(records[2].fields[5].starts_with(r#"{"coordinates":"#));
+        assert_eq!(records[2].line, 2);
+    }
+}
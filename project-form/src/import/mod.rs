@@ -0,0 +1,4 @@
+pub mod crs;
+pub mod geo;
+pub mod shapefile;
+pub mod xlsx;
@@ -0,0 +1,424 @@
+use std::io::{Cursor, Read};
+
+use anyhow::{anyhow, bail, Result};
+use encoding_rs::{Encoding, GBK, UTF_8};
+use serde_json::{json, Map, Value};
+use zip::ZipArchive;
+
+use crate::import::crs::Crs;
+use crate::import::geo::{geometry_bounds, reproject, Feature};
+
+/// shp文件头标识
+const SHP_FILE_CODE: i32 = 9994;
+/// shp文件头长度
+const SHP_HEADER_LEN: usize = 100;
+
+/// 读取zip压缩的Shapefile，需要包含shp和dbf文件，有prj文件时转换成WGS84
+/// @param data 压缩包数据
+pub fn read_shapefile_zip(data: Vec<u8>) -> Result<Vec<Feature>> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let names: Vec<String> = archive.file_names().map(|item| item.to_string()).collect();
+    // 跳过mac系统生成的目录
+    let shp_name = match names
+        .iter()
+        .find(|item| item.to_lowercase().ends_with(".shp") && !item.starts_with("__MACOSX"))
+    {
+        None => bail!("压缩包中没有shp文件"),
+        Some(name) => name.clone(),
+    };
+    let base = shp_name[..shp_name.len() - 4].to_lowercase();
+    let find = |extension: &str| {
+        names
+            .iter()
+            .find(|item| item.to_lowercase() == format!("{}.{}", base, extension))
+            .cloned()
+    };
+    let shp = read_entry(&mut archive, &shp_name)?;
+    let dbf = match find("dbf") {
+        None => bail!("压缩包中没有dbf文件"),
+        Some(name) => read_entry(&mut archive, &name)?,
+    };
+    let crs = match find("prj") {
+        None => None,
+        Some(name) => Some(Crs::from_wkt(&String::from_utf8_lossy(&read_entry(
+            &mut archive,
+            &name,
+        )?))?),
+    };
+    let encoding = match find("cpg") {
+        None => None,
+        Some(name) => cpg_encoding(&String::from_utf8_lossy(&read_entry(&mut archive, &name)?)),
+    };
+    let geometries = read_shp(&shp)?;
+    let records = read_dbf(&dbf, encoding)?;
+    if geometries.len() != records.len() {
+        bail!(
+            "shp文件要素数量：{}，和dbf文件记录数量：{}不一致",
+            geometries.len(),
+            records.len()
+        )
+    }
+    let mut features = Vec::with_capacity(records.len());
+    for (index, (geometry, properties)) in geometries.into_iter().zip(records).enumerate() {
+        // 已删除的记录跳过
+        if let Some(properties) = properties {
+            features.push(Feature {
+                line: index as u64 + 1,
+                properties,
+                geometry,
+            });
+        }
+    }
+    match crs {
+        Some(crs) => reproject(&mut features, &crs)?,
+        // 没有prj文件时只支持经纬度坐标
+        None => {
+            let mut projected = false;
+            for feature in features.iter() {
+                if let Some(geometry) = &feature.geometry {
+                    if let Some(bounds) = geometry_bounds(geometry)? {
+                        projected |= bounds.iter().any(|value| value.abs() > 360.0);
+                    }
+                }
+            }
+            if projected {
+                bail!("压缩包中没有prj文件，无法确定坐标系")
+            }
+        }
+    }
+    Ok(features)
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> Result<Vec<u8>> {
+    let mut file = archive.by_name(name)?;
+    let mut result = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut result)?;
+    Ok(result)
+}
+
+/// cpg文件中的编码，例如 UTF-8、GBK、936
+fn cpg_encoding(value: &str) -> Option<&'static Encoding> {
+    match value.trim() {
+        "936" => Some(GBK),
+        "65001" => Some(UTF_8),
+        label => Encoding::for_label(label.as_bytes()),
+    }
+}
+
+fn be_i32(data: &[u8], offset: usize) -> Result<i32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("shp文件数据不完整"))?;
+    Ok(i32::from_be_bytes(bytes.try_into()?))
+}
+
+fn le_i32(data: &[u8], offset: usize) -> Result<i32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("shp文件数据不完整"))?;
+    Ok(i32::from_le_bytes(bytes.try_into()?))
+}
+
+fn le_f64(data: &[u8], offset: usize) -> Result<f64> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or_else(|| anyhow!("shp文件数据不完整"))?;
+    Ok(f64::from_le_bytes(bytes.try_into()?))
+}
+
+/// 读取shp文件，几何转换成GeoJSON，空几何为None
+fn read_shp(data: &[u8]) -> Result<Vec<Option<Value>>> {
+    if data.len() < SHP_HEADER_LEN || be_i32(data, 0)? != SHP_FILE_CODE {
+        bail!("shp文件格式错误")
+    }
+    let mut result = Vec::new();
+    let mut offset = SHP_HEADER_LEN;
+    while offset + 8 <= data.len() {
+        // 记录长度单位为16位字
+        let length = be_i32(data, offset + 4)? as usize * 2;
+        let start = offset + 8;
+        let end = start + length;
+        if end > data.len() {
+            bail!("shp文件数据不完整")
+        }
+        result.push(read_shape(&data[start..end])?);
+        offset = end;
+    }
+    Ok(result)
+}
+
+/// 读取一条几何记录，Z和M类型只读取平面坐标
+fn read_shape(data: &[u8]) -> Result<Option<Value>> {
+    let shape_type = le_i32(data, 0)?;
+    let result = match shape_type {
+        0 => return Ok(None),
+        1 | 11 | 21 => json!({
+            "type": "Point",
+            "coordinates": [le_f64(data, 4)?, le_f64(data, 12)?],
+        }),
+        8 | 18 | 28 => {
+            let count = le_i32(data, 36)? as usize;
+            json!({
+                "type": "MultiPoint",
+                "coordinates": read_points(data, 40, count)?,
+            })
+        }
+        3 | 13 | 23 => {
+            let parts = read_parts(data)?;
+            if parts.len() == 1 {
+                json!({"type": "LineString", "coordinates": parts[0]})
+            } else {
+                json!({"type": "MultiLineString", "coordinates": parts})
+            }
+        }
+        5 | 15 | 25 => {
+            let polygons = group_rings(read_parts(data)?);
+            if polygons.len() == 1 {
+                json!({"type": "Polygon", "coordinates": polygons[0]})
+            } else {
+                json!({"type": "MultiPolygon", "coordinates": polygons})
+            }
+        }
+        _ => bail!("不支持的shp几何类型：{}", shape_type),
+    };
+    Ok(Some(result))
+}
+
+fn read_points(data: &[u8], offset: usize, count: usize) -> Result<Vec<[f64; 2]>> {
+    let mut result = Vec::with_capacity(count);
+    for index in 0..count {
+        let position = offset + index * 16;
+        result.push([le_f64(data, position)?, le_f64(data, position + 8)?]);
+    }
+    Ok(result)
+}
+
+/// 读取线和面的分段
+fn read_parts(data: &[u8]) -> Result<Vec<Vec<[f64; 2]>>> {
+    let part_count = le_i32(data, 36)? as usize;
+    let point_count = le_i32(data, 40)? as usize;
+    let points = read_points(data, 44 + part_count * 4, point_count)?;
+    let mut starts = Vec::with_capacity(part_count + 1);
+    for index in 0..part_count {
+        starts.push(le_i32(data, 44 + index * 4)? as usize);
+    }
+    starts.push(point_count);
+    let mut result = Vec::with_capacity(part_count);
+    for window in starts.windows(2) {
+        if window[0] > window[1] || window[1] > points.len() {
+            bail!("shp文件分段数据错误")
+        }
+        result.push(points[window[0]..window[1]].to_vec());
+    }
+    Ok(result)
+}
+
+/// 环的有向面积，逆时针为正
+fn signed_area(ring: &[[f64; 2]]) -> f64 {
+    let mut result = 0.0;
+    for window in ring.windows(2) {
+        result += window[0][0] * window[1][1] - window[1][0] * window[0][1];
+    }
+    result / 2.0
+}
+
+fn contains(ring: &[[f64; 2]], point: [f64; 2]) -> bool {
+    let mut result = false;
+    for window in ring.windows(2) {
+        let ([x1, y1], [x2, y2]) = (window[0], window[1]);
+        if (y1 > point[1]) != (y2 > point[1])
+            && point[0] < (x2 - x1) * (point[1] - y1) / (y2 - y1) + x1
+        {
+            result = !result;
+        }
+    }
+    result
+}
+
+/// 面的环分组，shp外环为顺时针，内环为逆时针，内环归属到包含它的外环
+/// 输出按照GeoJSON规范，外环逆时针，内环顺时针
+fn group_rings(rings: Vec<Vec<[f64; 2]>>) -> Vec<Vec<Vec<[f64; 2]>>> {
+    let mut polygons: Vec<Vec<Vec<[f64; 2]>>> = Vec::new();
+    let mut holes = Vec::new();
+    for mut ring in rings.into_iter() {
+        if signed_area(&ring) <= 0.0 {
+            ring.reverse();
+            polygons.push(vec![ring]);
+        } else {
+            ring.reverse();
+            holes.push(ring);
+        }
+    }
+    for hole in holes.into_iter() {
+        let point = match hole.first() {
+            None => continue,
+            Some(point) => *point,
+        };
+        match polygons
+            .iter_mut()
+            .find(|polygon| contains(&polygon[0], point))
+        {
+            Some(polygon) => polygon.push(hole),
+            // 找不到外环时作为外环处理
+            None => {
+                let mut ring = hole;
+                ring.reverse();
+                polygons.push(vec![ring]);
+            }
+        }
+    }
+    polygons
+}
+
+/// 读取dbf文件，已删除的记录为None
+/// @param data 文件数据
+/// @param encoding cpg文件中的编码，为空时根据语言标识或者内容判断
+fn read_dbf(
+    data: &[u8],
+    encoding: Option<&'static Encoding>,
+) -> Result<Vec<Option<Map<String, Value>>>> {
+    if data.len() < 32 {
+        bail!("dbf文件格式错误")
+    }
+    let count = u32::from_le_bytes(data[4..8].try_into()?) as usize;
+    let header_len = u16::from_le_bytes(data[8..10].try_into()?) as usize;
+    let record_len = u16::from_le_bytes(data[10..12].try_into()?) as usize;
+    // 语言标识，0x4D、0x7A为简体中文
+    let encoding = match (encoding, data[29]) {
+        (Some(encoding), _) => Some(encoding),
+        (None, 0x4D) | (None, 0x7A) => Some(GBK),
+        _ => None,
+    };
+    // 字段名、类型和长度
+    let mut fields = Vec::new();
+    let mut offset = 32;
+    while offset + 32 <= header_len.min(data.len()) && data[offset] != 0x0D {
+        let descriptor = &data[offset..offset + 32];
+        let name_len = descriptor[..11].iter().position(|c| *c == 0).unwrap_or(11);
+        let name = decode(&descriptor[..name_len], encoding).trim().to_string();
+        fields.push((name, descriptor[11], descriptor[16] as usize));
+        offset += 32;
+    }
+    let mut result = Vec::with_capacity(count);
+    for index in 0..count {
+        let start = header_len + index * record_len;
+        let record = match data.get(start..start + record_len) {
+            None => bail!("dbf文件数据不完整"),
+            Some(record) => record,
+        };
+        if record[0] == b'*' {
+            result.push(None);
+            continue;
+        }
+        let mut properties = Map::new();
+        let mut position = 1;
+        for (name, field_type, length) in fields.iter() {
+            let bytes = match record.get(position..position + length) {
+                None => bail!("dbf文件记录长度错误"),
+                Some(bytes) => bytes,
+            };
+            position += length;
+            let value = decode(bytes, encoding);
+            let value = value.trim();
+            let value = match field_type {
+                // 日期 yyyyMMdd 转换成 yyyy-MM-dd
+                b'D' if value.len() == 8 && value.bytes().all(|c| c.is_ascii_digit()) => {
+                    format!("{}-{}-{}", &value[..4], &value[4..6], &value[6..])
+                }
+                b'L' => match value {
+                    "T" | "t" | "Y" | "y" => "true".to_string(),
+                    "F" | "f" | "N" | "n" => "false".to_string(),
+                    _ => String::new(),
+                },
+                _ => value.to_string(),
+            };
+            let value = if value.is_empty() {
+                Value::Null
+            } else {
+                Value::String(value)
+            };
+            properties.insert(name.clone(), value);
+        }
+        result.push(Some(properties));
+    }
+    Ok(result)
+}
+
+/// 文本解码，没有指定编码时为UTF-8，不是UTF-8时使用GB18030
+fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> String {
+    let bytes = match bytes.iter().position(|c| *c == 0) {
+        Some(index) => &bytes[..index],
+        None => bytes,
+    };
+    match encoding {
+        Some(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        None => match std::str::from_utf8(bytes) {
+            Ok(value) => value.to_string(),
+            Err(_) => encoding_rs::GB18030
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polygon_with_hole() {
+        // 外环顺时针，内环逆时针
+        let outer = vec![
+            [0.0, 0.0],
+            [0.0, 10.0],
+            [10.0, 10.0],
+            [10.0, 0.0],
+            [0.0, 0.0],
+        ];
+        let hole = vec![[2.0, 2.0], [4.0, 2.0], [4.0, 4.0], [2.0, 4.0], [2.0, 2.0]];
+        let other = vec![
+            [20.0, 0.0],
+            [20.0, 5.0],
+            [25.0, 5.0],
+            [25.0, 0.0],
+            [20.0, 0.0],
+        ];
+        let polygons = group_rings(vec![outer, hole, other]);
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[0].len(), 2);
+        assert!(signed_area(&polygons[0][0]) > 0.0);
+        assert!(signed_area(&polygons[0][1]) < 0.0);
+    }
+
+    #[test]
+    fn dbf_records() {
+        let mut data = vec![0u8; 32];
+        data[0] = 3;
+        data[4] = 2;
+        // 表头长度 32 + 2 * 32 + 1
+        data[8] = 97;
+        // 记录长度 1 + 4 + 8
+        data[10] = 13;
+        data[29] = 0x4D;
+        for (name, field_type, length) in [("名称", b'C', 4u8), ("DATE", b'D', 8u8)] {
+            let mut descriptor = [0u8; 32];
+            let (name, _, _) = GBK.encode(name);
+            descriptor[..name.len()].copy_from_slice(&name);
+            descriptor[11] = field_type;
+            descriptor[16] = length;
+            data.extend_from_slice(&descriptor);
+        }
+        data.push(0x0D);
+        data.push(b' ');
+        data.extend_from_slice(&GBK.encode("耕地").0);
+        data.extend_from_slice(b"20240101");
+        data.push(b'*');
+        data.extend_from_slice(b"    20240102");
+        let records = read_dbf(&data, None).unwrap();
+        let first = records[0].as_ref().unwrap();
+        assert_eq!(first["名称"], "耕地");
+        assert_eq!(first["DATE"], "2024-01-01");
+        assert!(records[1].is_none());
+    }
+}
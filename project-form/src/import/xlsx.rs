@@ -0,0 +1,183 @@
+use std::io::Cursor;
+
+use anyhow::{bail, Result};
+use calamine::{open_workbook_from_rs, Data, Reader, Xlsx};
+
+use common::error::RowError;
+use common::file::csv::CsvRecord;
+
+/// 自动查找表头时检查的行数
+const HEADER_SEARCH_ROWS: usize = 20;
+
+/// xlsx读取参数，为空时使用第一个工作表，自动查找表头
+#[derive(Debug, Clone, Default)]
+pub struct XlsxOptions {
+    // 工作表名称
+    pub sheet: Option<String>,
+    // 表头行号，从1开始
+    pub header_row: Option<usize>,
+}
+
+/// 读取xlsx数据，返回和csv相同的记录，第一条记录为表头，行号为工作表中的行号
+/// 表头之前的行和空行跳过
+/// @param data 文件数据
+/// @param options 读取参数
+pub fn read_xlsx(data: Vec<u8>, options: &XlsxOptions) -> Result<Vec<Result<CsvRecord, RowError>>> {
+    let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(data))?;
+    let sheet = match &options.sheet {
+        Some(sheet) => {
+            if !workbook.sheet_names().contains(sheet) {
+                bail!("工作表：{}，不存在", sheet)
+            }
+            sheet.clone()
+        }
+        None => match workbook.sheet_names().first() {
+            None => bail!("文件中没有工作表"),
+            Some(sheet) => sheet.clone(),
+        },
+    };
+    let range = workbook.worksheet_range(&sheet)?;
+    // 工作表中第一行数据的行号
+    let start = match range.start() {
+        None => bail!("工作表：{}，没有数据", sheet),
+        Some((row, _)) => row as usize + 1,
+    };
+    let rows: Vec<&[Data]> = range.rows().collect();
+    let header_index = match options.header_row {
+        Some(row) if row < start || row - start >= rows.len() => {
+            bail!("表头行：{}，在工作表：{}中没有数据", row, sheet)
+        }
+        Some(row) => row - start,
+        None => find_header(&rows),
+    };
+    // 表头之后的空列不导入
+    let header = &rows[header_index];
+    let width = header
+        .iter()
+        .rposition(|cell| !is_empty(cell))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    if width == 0 {
+        bail!("工作表：{}，表头为空", sheet)
+    }
+    let mut result = Vec::new();
+    for (index, row) in rows.iter().enumerate().skip(header_index) {
+        let line = (start + index) as u64;
+        let cells = &row[..width.min(row.len())];
+        if index != header_index && cells.iter().all(is_empty) {
+            continue;
+        }
+        let mut fields = Vec::with_capacity(width);
+        let mut error = None;
+        for (column, cell) in cells.iter().enumerate() {
+            match cell_to_string(cell) {
+                Ok(value) => fields.push(value),
+                Err(message) => {
+                    error = Some(RowError::new(
+                        line,
+                        format!("第{}列{}", column + 1, message),
+                    ));
+                    break;
+                }
+            }
+        }
+        // 行末尾的空单元格补齐
+        fields.resize(width, String::new());
+        match error {
+            Some(error) => result.push(Err(error)),
+            None => result.push(Ok(CsvRecord { line, fields })),
+        }
+    }
+    Ok(result)
+}
+
+/// 查找表头，前几行中非空单元格最多的第一行，跳过表头上方的标题行
+fn find_header(rows: &[&[Data]]) -> usize {
+    let mut result = 0;
+    let mut max = 0;
+    for (index, row) in rows.iter().take(HEADER_SEARCH_ROWS).enumerate() {
+        let count = row.iter().filter(|cell| !is_empty(cell)).count();
+        if count > max {
+            result = index;
+            max = count;
+        }
+    }
+    result
+}
+
+fn is_empty(cell: &Data) -> bool {
+    match cell {
+        Data::Empty => true,
+        Data::String(value) => value.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// 单元格转换成文本，整数不带小数点，日期使用 yyyy-MM-dd 或者 yyyy-MM-dd HH:mm:ss
+fn cell_to_string(cell: &Data) -> Result<String, String> {
+    let result = match cell {
+        Data::Empty => String::new(),
+        Data::String(value) => value.trim().to_string(),
+        Data::Int(value) => value.to_string(),
+        Data::Float(value) => {
+            if value.fract() == 0.0 && value.abs() < 1e15 {
+                format!("{}", *value as i64)
+            } else {
+                value.to_string()
+            }
+        }
+        Data::Bool(value) => value.to_string(),
+        Data::DateTime(value) => match value.as_datetime() {
+            None => value.as_f64().to_string(),
+            Some(date) if date.time() == chrono::NaiveTime::MIN => {
+                date.format("%Y-%m-%d").to_string()
+            }
+            Some(date) => date.format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+        Data::DateTimeIso(value) | Data::DurationIso(value) => value.clone(),
+        Data::Error(error) => return Err(format!("单元格错误：{}", error)),
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{CellErrorType, Data, ExcelDateTime, ExcelDateTimeType};
+
+    use super::*;
+
+    #[test]
+    fn convert_cell() {
+        assert_eq!(cell_to_string(&Data::Float(12.0)).unwrap(), "12");
+        assert_eq!(cell_to_string(&Data::Float(12.5)).unwrap(), "12.5");
+        assert_eq!(
+            cell_to_string(&Data::String(" 耕地 ".to_string())).unwrap(),
+            "耕地"
+        );
+        // 45292 为 2024-01-01
+        let date = ExcelDateTime::new(45292.0, ExcelDateTimeType::DateTime, false);
+        assert_eq!(cell_to_string(&Data::DateTime(date)).unwrap(), "2024-01-01");
+        let date = ExcelDateTime::new(45292.5, ExcelDateTimeType::DateTime, false);
+        assert_eq!(
+            cell_to_string(&Data::DateTime(date)).unwrap(),
+            "2024-01-01 12:00:00"
+        );
+        assert!(cell_to_string(&Data::Error(CellErrorType::Div0)).is_err());
+    }
+
+    #[test]
+    fn find_header_row() {
+        let title = vec![
+            Data::String("任务清单".to_string()),
+            Data::Empty,
+            Data::Empty,
+        ];
+        let header = vec![
+            Data::String("code".to_string()),
+            Data::String("lon".to_string()),
+            Data::String("lat".to_string()),
+        ];
+        let rows: Vec<&[Data]> = vec![&title, &header, &header];
+        assert_eq!(find_header(&rows), 1);
+    }
+}
@@ -3,3 +3,5 @@ pub mod project;
 pub mod request;
 pub mod convert;
 pub mod file_util;
+pub mod import;
+pub mod export;
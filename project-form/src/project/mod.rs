@@ -3,7 +3,7 @@ use std::collections::BTreeMap;
 use anyhow::{bail, Result};
 use axum::body::Bytes;
 
-use models::dto::cts::request::project::AddProjectDto;
+use models::dto::cts::request::project::{AddProjectDto, ImportMode, ImportTaskDto};
 
 use crate::convert::TypeConvert;
 
@@ -31,6 +31,9 @@ pub fn parse_check_project(data: BTreeMap<String, Bytes>) -> Result<AddProjectDt
     } else {
         project.form_template_id = data.get("formTemplateId").unwrap().convert()?;
     }
+    if data.contains_key("formTemplateVersion") {
+        project.form_template_version = Some(data.get("formTemplateVersion").unwrap().convert()?);
+    }
 
     if !data.contains_key("taskCode") {
         bail!("taskCode 字段不能为空")
@@ -50,6 +53,21 @@ pub fn parse_check_project(data: BTreeMap<String, Bytes>) -> Result<AddProjectDt
         project.task_lat = data.get("taskLat").unwrap().convert()?;
     }
 
+    // 任务文件编码和分隔符，为空时自动检测
+    if data.contains_key("encoding") {
+        project.encoding = Some(data.get("encoding").unwrap().convert()?);
+    }
+    if data.contains_key("delimiter") {
+        project.delimiter = Some(data.get("delimiter").unwrap().convert()?);
+    }
+    // xlsx工作表和表头行号，为空时使用第一个工作表，自动查找表头
+    if data.contains_key("sheet") {
+        project.sheet = Some(data.get("sheet").unwrap().convert()?);
+    }
+    if data.contains_key("headerRow") {
+        project.header_row = Some(data.get("headerRow").unwrap().convert()?);
+    }
+
     if data.contains_key("description") {
         project.description = Some(data.get("description").unwrap().convert()?);
     }
@@ -58,4 +76,56 @@ pub fn parse_check_project(data: BTreeMap<String, Bytes>) -> Result<AddProjectDt
     }
 
     Ok(project)
-}
\ No newline at end of file
+}
+
+/// 解析已有项目导入任务的参数
+/// @param data multipart中文件之前的字段
+pub fn parse_import_task(data: BTreeMap<String, Bytes>) -> Result<ImportTaskDto> {
+    let mode = match data.get("mode") {
+        None => ImportMode::Append,
+        Some(mode) => {
+            let mode: String = mode.convert()?;
+            match mode.as_str() {
+                "append" => ImportMode::Append,
+                "upsert" => ImportMode::Upsert,
+                "replace" => ImportMode::Replace,
+                _ => bail!("mode：{}，必须是append、upsert或者replace", mode),
+            }
+        }
+    };
+    let field = |name: &str| -> Result<String> {
+        match data.get(name) {
+            None => bail!("{} 字段不能为空", name),
+            Some(value) => value.convert(),
+        }
+    };
+    let mut task = ImportTaskDto {
+        mode,
+        task_code: field("taskCode")?,
+        task_lon: field("taskLon")?,
+        task_lat: field("taskLat")?,
+        add_columns: false,
+        encoding: None,
+        delimiter: None,
+        sheet: None,
+        header_row: None,
+    };
+    if let Some(value) = data.get("addColumns") {
+        task.add_columns = value.convert()?;
+    }
+    // 任务文件编码和分隔符，为空时自动检测
+    if let Some(value) = data.get("encoding") {
+        task.encoding = Some(value.convert()?);
+    }
+    if let Some(value) = data.get("delimiter") {
+        task.delimiter = Some(value.convert()?);
+    }
+    // xlsx工作表和表头行号，为空时使用第一个工作表，自动查找表头
+    if let Some(value) = data.get("sheet") {
+        task.sheet = Some(value.convert()?);
+    }
+    if let Some(value) = data.get("headerRow") {
+        task.header_row = Some(value.convert()?);
+    }
+    Ok(task)
+}
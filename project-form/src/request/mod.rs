@@ -11,12 +11,14 @@ pub mod cts_multipart;
 pub struct CtsFile {
     pub path: String,
     pub filename: String,
+    // 文件大小，单位字节
+    pub size: u64,
+    // 文件类型，使用上传时提交的类型
+    pub mime: String,
+    // 文件内容sha256
+    pub sha256: String,
 }
 #[async_trait]
 pub trait FileParse {
     async fn parse(&mut self, path: &str) -> anyhow::Result<(BTreeMap<String, Bytes>, Vec<CtsFile>)>;
 }
-#[async_trait]
-pub trait CsvParse {
-    async fn read_csv(&mut self) -> anyhow::Result<(BTreeMap<String, Bytes>, (Vec<String>, Vec<Vec<String>>))>;
-}
\ No newline at end of file
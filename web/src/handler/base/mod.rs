@@ -1,8 +1,2 @@
 pub mod login_logout;
 pub mod upload_download;
-
-pub enum FileType {
-    Image,
-    Video,
-    Other,
-}
@@ -1,60 +1,202 @@
+use std::collections::HashMap;
+
+use crate::handler::handle_result;
 use crate::service::base::upload_download;
-use axum::extract::{Multipart, Path};
-use axum::http::{HeaderMap, HeaderName, HeaderValue};
-use axum::response::IntoResponse;
+use axum::body::Body;
+use axum::extract::{Multipart, Path, Query};
+use axum::http::header::{
+    ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE, X_CONTENT_TYPE_OPTIONS,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use common::error::StorageError;
+use common::file::serve::{
+    content_disposition, etag_matches, http_date, parse_http_date, parse_range, sniff_mime,
+    ByteRange,
+};
+use common::storage::{check_key, FileMeta};
+use models::dto::sys::response::sys_user::ResponseUser;
 use response_utils::res::ResResult;
 
-use super::FileType;
+/// 上传函数，返回文件信息
+/// @param user 当前用户
+/// @param multipart 上传数据
+/// return IntoResponse
+pub async fn upload(
+    Extension(user): Extension<ResponseUser>,
+    multipart: Multipart,
+) -> impl IntoResponse {
+    let result = upload_download::upload(user, multipart).await;
+    handle_result(result)
+}
 
-/// 上传函数
-pub async fn upload(multipart: Multipart) -> impl IntoResponse {
-    let result = upload_download::upload(multipart).await;
+/// 浏览文件函数，在浏览器中直接打开
+/// @param path 文件路径
+/// @param headers 支持Range和缓存校验请求头
+/// return Response
+pub async fn image(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    serve_file(path, &headers, true).await
+}
 
-    match result {
-        Ok(data) => ResResult::with_success(data),
-        Err(_err) => ResResult::<()>::with_error("用户名或密码错误"),
-    }
+/// 下载文件
+/// @param path 文件路径
+/// @param headers 支持Range和缓存校验请求头
+/// return Response
+pub async fn download(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    serve_file(path, &headers, false).await
+}
+
+/// 生成文件预签名下载地址函数
+/// @param path 文件路径
+/// @param params expires为有效期（秒），默认1小时
+/// return IntoResponse
+pub async fn presign(
+    Path(path): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let expires = params
+        .get("expires")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(3600);
+    let result = upload_download::presigned_url(path, expires).await;
+    handle_result(result)
 }
 
-/// 浏览文件函数
-pub async fn image(Path(path): Path<String>) -> (HeaderMap, Vec<u8>) {
-    let result = upload_download::read_file(path.clone()).await;
-    let headers = parse_type(path, FileType::Image);
-    match result {
-        Ok(data) => (headers, data),
-        Err(_err) => (headers, vec![]),
+/// 预签名地址下载文件，不需要登录
+/// @param path 文件路径
+/// @param params expires为过期时间戳，signature为签名
+/// @param headers 支持Range和缓存校验请求头
+/// return Response
+pub async fn presigned(
+    Path(path): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let expires = params
+        .get("expires")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default();
+    let signature = params
+        .get("signature")
+        .map(String::as_str)
+        .unwrap_or_default();
+    if !upload_download::verify_presigned(&path, expires, signature) {
+        return ResResult::<()>::with_error_code(
+            "下载地址不正确或者已经过期",
+            403,
+            StatusCode::FORBIDDEN,
+        )
+        .into_response();
     }
+    serve_file(path, &headers, false).await
 }
 
-/// 下载文件
-pub async fn download(Path(path): Path<String>) -> (HeaderMap, Vec<u8>) {
-    let result = upload_download::read_file(path.clone()).await;
-    let headers = parse_type(path, FileType::Other);
-    match result {
-        Ok(data) => (headers, data),
-        Err(_err) => (headers, vec![]),
-    }
-}
-
-fn parse_type(path: String, file_type: FileType) -> HeaderMap {
-    // 查找是否有点符号
-    let index = path.find('.').unwrap_or(usize::MAX);
-    //文件扩展名
-    let mut ext_name = "png";
-    if index != usize::MAX {
-        ext_name = &path[index + 1..];
-    }
-    let content_type = match file_type {
-        FileType::Image => {
-            format!("image/{}", ext_name)
+/// 返回存储中的文件，支持Range请求和缓存校验，文件类型根据文件内容识别
+/// @param path 文件路径，只能访问存储目录中的文件
+/// @param headers 请求头
+/// @param inline 是否在浏览器中直接打开
+async fn serve_file(path: String, headers: &HeaderMap, inline: bool) -> Response {
+    if check_key(&path).is_err() {
+        return not_found();
+    }
+    let meta = match upload_download::metadata(&path).await {
+        Ok(meta) => meta,
+        Err(err) if err.downcast_ref::<StorageError>().is_some() => return not_found(),
+        Err(err) => return handle_result::<()>(Err(err)).into_response(),
+    };
+    let etag = meta.etag.as_deref().unwrap_or_default();
+    let last_modified = meta.modified.as_ref().map(http_date).unwrap_or_default();
+    let mut response_headers = HeaderMap::new();
+    insert_header(&mut response_headers, ETAG, etag);
+    insert_header(&mut response_headers, LAST_MODIFIED, &last_modified);
+    insert_header(&mut response_headers, ACCEPT_RANGES, "bytes");
+    if not_modified(headers, &meta) {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+    let range = match header(headers, RANGE) {
+        Some(range) if if_range(headers, &meta) => parse_range(range, meta.size),
+        _ => ByteRange::Full,
+    };
+    let (status, start, end) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, meta.size.saturating_sub(1)),
+        ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end),
+        ByteRange::Unsatisfiable => {
+            let content_range = format!("bytes */{}", meta.size);
+            insert_header(&mut response_headers, CONTENT_RANGE, &content_range);
+            return (StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response();
         }
-        FileType::Video => "video/*".to_string(),
-        FileType::Other => "application/octet-stream".to_string(),
     };
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        HeaderName::from_static("content-type"),
-        HeaderValue::from_str(&content_type).unwrap(),
+    let filename = match upload_download::original_name(&path).await {
+        Ok(filename) => filename,
+        Err(err) => return handle_result::<()>(Err(err)).into_response(),
+    };
+    let (head, stream) = match upload_download::read_file(&path, meta.size, start, end).await {
+        Ok(result) => result,
+        Err(err) if err.downcast_ref::<StorageError>().is_some() => return not_found(),
+        Err(err) => return handle_result::<()>(Err(err)).into_response(),
+    };
+    let length = if meta.size == 0 { 0 } else { end - start + 1 };
+    insert_header(&mut response_headers, CONTENT_TYPE, sniff_mime(&head));
+    insert_header(&mut response_headers, CONTENT_LENGTH, &length.to_string());
+    insert_header(&mut response_headers, X_CONTENT_TYPE_OPTIONS, "nosniff");
+    insert_header(
+        &mut response_headers,
+        CONTENT_DISPOSITION,
+        &content_disposition(inline, &filename),
     );
-    headers
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {}-{}/{}", start, end, meta.size);
+        insert_header(&mut response_headers, CONTENT_RANGE, &content_range);
+    }
+    (status, response_headers, Body::from_stream(stream)).into_response()
+}
+
+/// 缓存是否有效，优先使用If-None-Match校验
+fn not_modified(headers: &HeaderMap, meta: &FileMeta) -> bool {
+    if let Some(if_none_match) = header(headers, IF_NONE_MATCH) {
+        return meta
+            .etag
+            .as_deref()
+            .is_some_and(|etag| etag_matches(if_none_match, etag));
+    }
+    match (
+        header(headers, IF_MODIFIED_SINCE).and_then(parse_http_date),
+        meta.modified,
+    ) {
+        (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+        _ => false,
+    }
+}
+
+/// If-Range请求头和文件一致时才返回部分数据，没有请求头时直接返回部分数据
+fn if_range(headers: &HeaderMap, meta: &FileMeta) -> bool {
+    let value = match header(headers, IF_RANGE) {
+        Some(value) => value,
+        None => return true,
+    };
+    match parse_http_date(value) {
+        Some(time) => meta
+            .modified
+            .is_some_and(|modified| modified.timestamp() == time.timestamp()),
+        None => meta.etag.as_deref() == Some(value.trim()),
+    }
+}
+
+fn header(headers: &HeaderMap, name: HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn insert_header(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+fn not_found() -> Response {
+    ResResult::<()>::with_error_code("文件不存在", 404, StatusCode::NOT_FOUND).into_response()
 }
@@ -1,47 +1,69 @@
 use std::collections::HashMap;
 
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
-use axum::Json;
+use axum::{Extension, Json};
 use serde_json::Value;
 
-use models::dto::cts::request::form::{AddFormData, SearchFormData, UpdateFormData};
+use common::db::form::get_table_name;
+use models::dto::cts::request::form::{
+    AddFormData, AggregateFormData, SearchFormData, UpdateFormData,
+};
+use models::dto::sys::response::sys_user::ResponseUser;
 
-use crate::handler::{handle_force, handle_result};
-use crate::service::cts::form;
+use crate::handler::{handle_force, handle_if_match, handle_result};
+use crate::service::cts::{attachment, form, history};
 
 /// 添加form data函数
-/// @param data 类型Value
+/// @param table_id 数据表id
+/// @param data 类型Value，id为对应的任务编号
 /// return IntoResponse
-pub async fn add(Path(table_id): Path<String>, Json(data): Json<Value>) -> impl IntoResponse {
-    let result = form::add(table_id, data).await;
+pub async fn add(
+    Extension(user): Extension<ResponseUser>,
+    Path(table_id): Path<String>,
+    Json(data): Json<Value>,
+) -> impl IntoResponse {
+    let result = form::add(user, table_id, data).await;
     handle_result(result)
 }
 
 /// 添加数据
 ///
 ///
-pub async fn add_data(Json(data): Json<AddFormData>) -> impl IntoResponse {
-    let result = form::add_data(data).await;
+pub async fn add_data(
+    Extension(user): Extension<ResponseUser>,
+    Json(data): Json<AddFormData>,
+) -> impl IntoResponse {
+    let result = form::add_data(user, data).await;
     handle_result(result)
 }
 
-/// 更新数据
-pub async fn update_data(Json(data): Json<UpdateFormData>) -> impl IntoResponse {
-    let result = form::update_data(data).await;
+/// 更新数据，数据版本可以放在If-Match请求头中
+pub async fn update_data(
+    Extension(user): Extension<ResponseUser>,
+    headers: HeaderMap,
+    Json(mut data): Json<UpdateFormData>,
+) -> impl IntoResponse {
+    data.version = data.version.or_else(|| handle_if_match(&headers));
+    let result = form::update_data(user, data).await;
     handle_result(result)
 }
 
 /// 更新form data函数
 /// @param id 类型String
 /// @param table_id 数据表id
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 数据data
 /// return IntoResponse
 pub async fn update(
+    Extension(user): Extension<ResponseUser>,
     Path((table_id, id)): Path<(String, String)>,
+    headers: HeaderMap,
     Json(data): Json<Value>,
 ) -> impl IntoResponse {
-    let result = form::update(table_id, id, data).await;
+    let version = handle_if_match(&headers);
+    let result = form::update(user, table_id, id, data, version).await;
     handle_result(result)
 }
 
@@ -51,12 +73,13 @@ pub async fn update(
 /// @param params 类型HashMap<String, String>
 /// return IntoResponse
 pub async fn delete(
+    Extension(user): Extension<ResponseUser>,
     Path((table_id, id)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
     // 判断是否真删除
     let force = handle_force(params);
-    let result = form::delete_by_id(table_id, id, force).await;
+    let result = form::delete_by_id(user, table_id, id, force).await;
     handle_result(result)
 }
 
@@ -88,3 +111,48 @@ pub async fn search(
     let result = form::search(table_id, data).await;
     handle_result(result)
 }
+
+/// 统计form data函数
+/// @param table_id 数据表id
+/// @param data 类型AggregateFormData
+/// return IntoResponse
+pub async fn aggregate(
+    Path(table_id): Path<String>,
+    Json(data): Json<AggregateFormData>,
+) -> impl IntoResponse {
+    let result = form::aggregate(table_id, data).await;
+    handle_result(result)
+}
+
+/// 查询form data变更历史函数
+/// @param table_id 数据表编号
+/// @param id 数据编号
+/// return IntoResponse
+pub async fn history(Path((table_id, id)): Path<(String, String)>) -> impl IntoResponse {
+    let result = history::list(get_table_name(&table_id, true), id).await;
+    handle_result(result)
+}
+
+/// 恢复form data到历史版本函数
+/// @param table_id 数据表编号
+/// @param history_id 变更历史编号
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
+/// return IntoResponse
+pub async fn restore(
+    Extension(user): Extension<ResponseUser>,
+    Path((table_id, history_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let version = handle_if_match(&headers);
+    let result = history::restore(user, table_id, history_id, version).await;
+    handle_result(result)
+}
+
+/// 查询数据引用的文件函数
+/// @param table_id 数据表编号
+/// @param id 数据编号
+/// return IntoResponse
+pub async fn attachments(Path((table_id, id)): Path<(String, String)>) -> impl IntoResponse {
+    let result = attachment::list(get_table_name(&table_id, true), id).await;
+    handle_result(result)
+}
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::Json;
 use axum::response::IntoResponse;
-use models::dto::cts::request::form_template::{AddFormTemplateDto, SearchFormTemplateDto, UpdateFormTemplateDto};
-use crate::handler::{handle_force, handle_result};
+use models::dto::cts::request::form_template::{AddFormTemplateDto, DiffFormTemplateDto, PublishFormTemplateDto, SearchFormTemplateDto, UpdateFormTemplateDto};
+use crate::handler::{handle_force, handle_if_match, handle_result};
 use crate::service::cts::form_template;
 
 /// 添加表单函数
@@ -16,10 +17,15 @@ pub async fn add(Json(data): Json<AddFormTemplateDto>) -> impl IntoResponse {
 
 /// 更新表单函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateFormTemplateDto
 /// return IntoResponse
-pub async fn update(Path(id): Path<String>, Json(data): Json<UpdateFormTemplateDto>) -> impl IntoResponse {
-    let result = form_template::update(id, data).await;
+pub async fn update(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(data): Json<UpdateFormTemplateDto>,
+) -> impl IntoResponse {
+    let result = form_template::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
@@ -50,4 +56,38 @@ pub async fn query(Path(id): Path<String>) -> impl IntoResponse {
 pub async fn search(Json(data): Json<SearchFormTemplateDto>) -> impl IntoResponse {
     let result = form_template::search(data).await;
     handle_result(result)
-}
\ No newline at end of file
+}
+/// 发布表单函数
+/// @param id 类型String
+/// @param data 类型PublishFormTemplateDto
+/// return IntoResponse
+pub async fn publish(Path(id): Path<String>, Json(data): Json<PublishFormTemplateDto>) -> impl IntoResponse {
+    let result = form_template::publish(id, data).await;
+    handle_result(result)
+}
+
+/// 查询表单发布历史函数
+/// @param id 类型String
+/// return IntoResponse
+pub async fn versions(Path(id): Path<String>) -> impl IntoResponse {
+    let result = form_template::versions(id).await;
+    handle_result(result)
+}
+
+/// 查询表单发布版本函数
+/// @param id 类型String
+/// @param version 类型String
+/// return IntoResponse
+pub async fn version(Path((id, version)): Path<(String, String)>) -> impl IntoResponse {
+    let result = form_template::get_version(id, version).await;
+    handle_result(result)
+}
+
+/// 比较表单版本函数
+/// @param id 类型String
+/// @param data 类型DiffFormTemplateDto
+/// return IntoResponse
+pub async fn diff(Path(id): Path<String>, Query(data): Query<DiffFormTemplateDto>) -> impl IntoResponse {
+    let result = form_template::diff(id, data).await;
+    handle_result(result)
+}
@@ -1,4 +1,5 @@
 pub mod form_template;
 pub mod project;
+pub mod sync;
 pub mod task;
 pub mod form;
\ No newline at end of file
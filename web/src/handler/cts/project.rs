@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 
 use axum::extract::{Multipart, Path, Query};
-use axum::response::IntoResponse;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 
-use models::dto::cts::request::project::{AddProjectDto, SearchProjectDto, UpdateProjectDto};
+use common::file::serve::content_disposition;
+use models::dto::cts::request::project::{
+    AddProjectDto, ExportProjectDto, MigrateProjectDto, ProjectStatsDto, SearchProjectDto,
+    UpdateProjectDto,
+};
 
-use crate::handler::{handle_force, handle_result};
-use crate::service::cts::project;
+use crate::handler::{handle_force, handle_if_match, handle_result};
+use crate::service::cts::{project, stats};
 
 /// 添加项目函数
 /// @param data 类型AddProjectDto
@@ -19,13 +24,15 @@ pub async fn add(Json(data): Json<AddProjectDto>) -> impl IntoResponse {
 
 /// 更新项目数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateProjectDto
 /// return IntoResponse
 pub async fn update(
     Path(id): Path<String>,
+    headers: HeaderMap,
     Json(data): Json<UpdateProjectDto>,
 ) -> impl IntoResponse {
-    let result = project::update(id, data).await;
+    let result = project::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
@@ -51,6 +58,61 @@ pub async fn query(Path(id): Path<String>) -> impl IntoResponse {
     handle_result(result)
 }
 
+/// 项目数据表迁移到表单版本函数
+/// @param id 类型String
+/// @param data 类型MigrateProjectDto
+/// return IntoResponse
+pub async fn migrate(
+    Path(id): Path<String>,
+    Json(data): Json<MigrateProjectDto>,
+) -> impl IntoResponse {
+    let result = project::migrate(id, data).await;
+    handle_result(result)
+}
+
+/// 已有项目导入任务函数
+/// @param id 类型String
+/// @param multipart 导入参数和任务文件
+/// return IntoResponse
+pub async fn import(Path(id): Path<String>, multipart: Multipart) -> impl IntoResponse {
+    let result = project::import(id, multipart).await;
+    handle_result(result)
+}
+
+/// 项目进度统计函数
+/// @param id 类型String
+/// @param data 类型ProjectStatsDto
+/// return IntoResponse
+pub async fn stats(
+    Path(id): Path<String>,
+    Query(data): Query<ProjectStatsDto>,
+) -> impl IntoResponse {
+    let result = stats::project_stats(id, data).await;
+    handle_result(result)
+}
+
+/// 导出项目数据函数，导出失败时返回错误信息
+/// @param id 类型String
+/// @param data 类型ExportProjectDto
+/// return Response
+pub async fn export(
+    Path(id): Path<String>,
+    Query(data): Query<ExportProjectDto>,
+) -> Response {
+    match project::export(id, data).await {
+        Ok(file) => {
+            // 文件名可能包含中文，使用RFC 5987编码
+            let disposition = content_disposition(false, &file.file_name);
+            let headers = [
+                (header::CONTENT_TYPE, file.content_type.to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ];
+            (headers, file.body).into_response()
+        }
+        Err(err) => handle_result::<()>(Err(err)).into_response(),
+    }
+}
+
 /// 分页查询项目函数
 /// @param data 类型SearchProjectDto
 /// return IntoResponse
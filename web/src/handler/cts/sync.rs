@@ -0,0 +1,35 @@
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+
+use models::dto::cts::request::sync::{SyncPullDto, SyncPushDto};
+use models::dto::sys::response::sys_user::ResponseUser;
+
+use crate::handler::handle_result;
+use crate::service::cts::sync;
+
+/// 增量同步函数
+/// @param table_id 数据表编号
+/// @param data 类型SyncPullDto
+/// return IntoResponse
+pub async fn pull(
+    Extension(user): Extension<ResponseUser>,
+    Path(table_id): Path<String>,
+    Json(data): Json<SyncPullDto>,
+) -> impl IntoResponse {
+    let result = sync::pull(user, table_id, data).await;
+    handle_result(result)
+}
+
+/// 提交离线数据函数
+/// @param table_id 数据表编号
+/// @param data 类型SyncPushDto
+/// return IntoResponse
+pub async fn push(
+    Extension(user): Extension<ResponseUser>,
+    Path(table_id): Path<String>,
+    Json(data): Json<SyncPushDto>,
+) -> impl IntoResponse {
+    let result = sync::push(user, table_id, data).await;
+    handle_result(result)
+}
@@ -1,32 +1,45 @@
 use std::collections::HashMap;
 
 use axum::extract::{Path, Query};
-use axum::Json;
+use axum::http::HeaderMap;
+use axum::{Extension, Json};
 use axum::response::IntoResponse;
 use serde_json::Value;
 
-use models::dto::cts::request::task::SearchTask;
+use common::db::form::get_table_name;
+use models::dto::cts::request::task::{
+    AssignTaskDto, SearchTask, SplitTaskDto, TransitionTaskDto,
+};
+use models::dto::sys::response::sys_user::ResponseUser;
 
-use crate::handler::{handle_force, handle_result};
-use crate::service::cts::task;
+use crate::handler::{handle_force, handle_if_match, handle_result};
+use crate::service::cts::{history, task, workflow};
 
 /// 添加task函数
 /// @param data 类型AddTenantDto
 /// return IntoResponse
 pub async fn add(
+    Extension(user): Extension<ResponseUser>,
     Path(table_id): Path<String>,
     Json(data): Json<Value>,
 ) -> impl IntoResponse {
-    let result = task::add(table_id, data).await;
+    let result = task::add(user.id, table_id, data).await;
     handle_result(result)
 }
 
 /// 更新task函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取任务时的版本，版本不一致时返回409
 /// @param data 类型UpdateApiDto
 /// return IntoResponse
-pub async fn update(Path((table_id, id)): Path<(String, String)>, Json(data): Json<Value>) -> impl IntoResponse {
-    let result = task::update(table_id, id, data).await;
+pub async fn update(
+    Extension(user): Extension<ResponseUser>,
+    Path((table_id, id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(data): Json<Value>,
+) -> impl IntoResponse {
+    let version = handle_if_match(&headers);
+    let result = task::update(user.id, table_id, id, data, version).await;
     handle_result(result)
 }
 
@@ -35,11 +48,15 @@ pub async fn update(Path((table_id, id)): Path<(String, String)>, Json(data): Js
 /// @param id 类型String
 /// @param params 类型HashMap<String, String>
 /// return IntoResponse
-pub async fn delete(Path((table_id, id)): Path<(String, String)>, Query(params): Query<HashMap<String, String>>) -> impl IntoResponse {
+pub async fn delete(
+    Extension(user): Extension<ResponseUser>,
+    Path((table_id, id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
 
     // 判断是否真删除
     let force = handle_force(params);
-    let result = task::delete_by_id(table_id, id, force).await;
+    let result = task::delete_by_id(user.id, table_id, id, force).await;
     handle_result(result)
 }
 
@@ -57,4 +74,90 @@ pub async fn query(Path((task_name, id)): Path<(String, String)>) -> impl IntoRe
 pub async fn search(Path(table_id): Path<String>, Json(data): Json<SearchTask>) -> impl IntoResponse {
     let result = task::search(table_id, data).await;
     handle_result(result)
-}
\ No newline at end of file
+}
+
+/// 分页查询当前用户的task函数
+/// @param table_id 任务表编号
+/// @param data 类型SearchTask
+/// return IntoResponse
+pub async fn mine(
+    Extension(user): Extension<ResponseUser>,
+    Path(table_id): Path<String>,
+    Json(data): Json<SearchTask>,
+) -> impl IntoResponse {
+    let result = task::search_mine(table_id, user.id, data).await;
+    handle_result(result)
+}
+
+/// 分配task函数
+/// @param table_id 任务表编号
+/// @param data 类型AssignTaskDto
+/// return IntoResponse
+pub async fn assign(
+    Extension(user): Extension<ResponseUser>,
+    Path(table_id): Path<String>,
+    Json(data): Json<AssignTaskDto>,
+) -> impl IntoResponse {
+    let result = task::assign(user, table_id, data).await;
+    handle_result(result)
+}
+
+/// 平均分配task函数
+/// @param table_id 任务表编号
+/// @param data 类型SplitTaskDto
+/// return IntoResponse
+pub async fn split(
+    Extension(user): Extension<ResponseUser>,
+    Path(table_id): Path<String>,
+    Json(data): Json<SplitTaskDto>,
+) -> impl IntoResponse {
+    let result = task::split(user, table_id, data).await;
+    handle_result(result)
+}
+
+/// 流转task状态函数
+/// @param table_id 任务表编号
+/// @param id 任务编号
+/// @param data 类型TransitionTaskDto
+/// return IntoResponse
+pub async fn transition(
+    Extension(user): Extension<ResponseUser>,
+    Path((table_id, id)): Path<(String, String)>,
+    Json(data): Json<TransitionTaskDto>,
+) -> impl IntoResponse {
+    let result = workflow::transition(user, table_id, id, data).await;
+    handle_result(result)
+}
+
+/// 查询task流转记录函数
+/// @param table_id 任务表编号
+/// @param id 任务编号
+/// return IntoResponse
+pub async fn transitions(Path((table_id, id)): Path<(String, String)>) -> impl IntoResponse {
+    let result = workflow::transitions(table_id, id).await;
+    handle_result(result)
+}
+
+/// 查询task变更历史函数
+/// @param table_id 任务表编号
+/// @param id 任务编号
+/// return IntoResponse
+pub async fn history(Path((table_id, id)): Path<(String, String)>) -> impl IntoResponse {
+    let result = history::list(get_table_name(&table_id, false), id).await;
+    handle_result(result)
+}
+
+/// 恢复task到历史版本函数
+/// @param table_id 任务表编号
+/// @param history_id 变更历史编号
+/// @param headers If-Match请求头为读取任务时的版本，版本不一致时返回409
+/// return IntoResponse
+pub async fn restore(
+    Extension(user): Extension<ResponseUser>,
+    Path((table_id, history_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let version = handle_if_match(&headers);
+    let result = history::restore_task(user.id, table_id, history_id, version).await;
+    handle_result(result)
+}
@@ -1,9 +1,13 @@
 use anyhow::Result;
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::IntoResponse;
+use axum::Json;
+use common::error::{ConflictError, ImportError, RowError, ValidationError};
 use common::FORCE;
 use response_utils::res::ResResult;
 use serde::Serialize;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
 
 pub mod base;
 pub mod cts;
@@ -17,13 +21,80 @@ fn handle_force(params: HashMap<String, String>) -> bool {
     }
 }
 
+/// 读取If-Match请求头中的数据版本，没有时返回None
+fn handle_if_match(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::IF_MATCH)?.to_str().ok()?.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value).trim_matches('"');
+    match value {
+        "" | "*" => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// 字段校验错误响应，errors的key为字段名，客户端根据字段名提示错误
+#[derive(Serialize)]
+struct FieldErrorResult<'a> {
+    code: u16,
+    message: String,
+    errors: &'a BTreeMap<String, String>,
+}
+
+/// 导入错误响应，errors为带行号的错误列表
+#[derive(Serialize)]
+struct RowErrorResult<'a> {
+    code: u16,
+    message: String,
+    errors: &'a Vec<RowError>,
+}
+
+/// 版本冲突响应，data为服务器当前数据
+#[derive(Serialize)]
+struct ConflictResult<'a> {
+    code: u16,
+    message: String,
+    data: &'a Value,
+}
+
 fn handle_result<T>(data: Result<T>) -> impl IntoResponse
 where
     T: Serialize,
 {
     match data {
-        Ok(data) => ResResult::with_success(data),
-        Err(_err) => ResResult::<()>::with_error(&_err.to_string()),
+        Ok(data) => ResResult::with_success(data).into_response(),
+        Err(_err) => match _err.downcast_ref::<ValidationError>() {
+            Some(error) => {
+                let status = StatusCode::UNPROCESSABLE_ENTITY;
+                let result = FieldErrorResult {
+                    code: status.as_u16(),
+                    message: error.to_string(),
+                    errors: &error.errors,
+                };
+                (status, Json(result)).into_response()
+            }
+            None => match _err.downcast_ref::<ImportError>() {
+                Some(error) => {
+                    let status = StatusCode::UNPROCESSABLE_ENTITY;
+                    let result = RowErrorResult {
+                        code: status.as_u16(),
+                        message: format!("数据导入失败，共{}行错误", error.errors.len()),
+                        errors: &error.errors,
+                    };
+                    (status, Json(result)).into_response()
+                }
+                None => match _err.downcast_ref::<ConflictError>() {
+                    Some(error) => {
+                        let status = StatusCode::CONFLICT;
+                        let result = ConflictResult {
+                            code: status.as_u16(),
+                            message: error.to_string(),
+                            data: &error.current,
+                        };
+                        (status, Json(result)).into_response()
+                    }
+                    None => ResResult::<()>::with_error(&_err.to_string()).into_response(),
+                },
+            },
+        },
     }
 }
 
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::Json;
 use axum::response::IntoResponse;
 use models::dto::sys::request::sys_api::{AddApiDto, SearchApiDto, UpdateApiDto};
-use crate::handler::{handle_force, handle_result};
+use crate::handler::{handle_force, handle_if_match, handle_result};
 use crate::service::sys::sys_api;
 
 /// 添加Api函数
@@ -16,10 +17,15 @@ pub async fn add(Json(data): Json<AddApiDto>) -> impl IntoResponse {
 
 /// 更新Api函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateApiDto
 /// return IntoResponse
-pub async fn update(Path(id): Path<String>, Json(data): Json<UpdateApiDto>) -> impl IntoResponse {
-    let result = sys_api::update(id, data).await;
+pub async fn update(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(data): Json<UpdateApiDto>,
+) -> impl IntoResponse {
+    let result = sys_api::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
@@ -2,11 +2,12 @@ use std::collections::HashMap;
 
 use axum::Json;
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
 
 use models::dto::sys::request::sys_menu::{AddMenuDto, SearchMenuDto, UpdateMenuDto};
 
-use crate::handler::{handle_force, handle_result};
+use crate::handler::{handle_force, handle_if_match, handle_result};
 use crate::service::sys::sys_menu;
 
 /// 添加菜单函数
@@ -19,10 +20,15 @@ pub async fn add(Json(data): Json<AddMenuDto>) -> impl IntoResponse {
 
 /// 更新菜单函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateMenuDto
 /// return IntoResponse
-pub async fn update(Path(id): Path<String>, Json(data): Json<UpdateMenuDto>) -> impl IntoResponse {
-    let result = sys_menu::update(id, data).await;
+pub async fn update(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(data): Json<UpdateMenuDto>,
+) -> impl IntoResponse {
+    let result = sys_menu::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
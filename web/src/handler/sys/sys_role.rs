@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::{Extension, Json};
 use axum::response::IntoResponse;
 use models::dto::sys::request::sys_role::{AddRoleDto, SearchRoleDto, UpdateRoleDto, UpdateRoleStatusDto};
 use models::dto::sys::response::sys_user::ResponseUser;
-use crate::handler::{handle_force, handle_result};
+use crate::handler::{handle_force, handle_if_match, handle_result};
 use crate::service::sys::sys_role;
 
 /// 添加角色函数
@@ -17,10 +18,15 @@ pub async fn add(Json(data): Json<AddRoleDto>) -> impl IntoResponse {
 
 /// 更新角色函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateRoleDto
 /// return IntoResponse
-pub async fn update(Path(id): Path<String>, Json(data): Json<UpdateRoleDto>) -> impl IntoResponse {
-    let result = sys_role::update(id, data).await;
+pub async fn update(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(data): Json<UpdateRoleDto>,
+) -> impl IntoResponse {
+    let result = sys_role::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::Json;
 use axum::response::IntoResponse;
 use models::dto::sys::request::sys_tenant::{AddTenantDto, SearchTenantDto, UpdateTenantDto, UpdateTenantStatusDto};
-use crate::handler::{handle_force, handle_result};
+use crate::handler::{handle_force, handle_if_match, handle_result};
 use crate::service::sys::sys_tenant;
 
 /// 添加租户函数
@@ -16,10 +17,15 @@ pub async fn add(Json(data): Json<AddTenantDto>) -> impl IntoResponse {
 
 /// 更新租户函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateTenantDto
 /// return IntoResponse
-pub async fn update(Path(id): Path<String>, Json(data): Json<UpdateTenantDto>) -> impl IntoResponse {
-    let result = sys_tenant::update(id, data).await;
+pub async fn update(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(data): Json<UpdateTenantDto>,
+) -> impl IntoResponse {
+    let result = sys_tenant::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
@@ -1,6 +1,7 @@
-use crate::handler::{handle_force, handle_result};
+use crate::handler::{handle_force, handle_if_match, handle_result};
 use crate::service::sys::sys_user;
 use axum::extract::{Path, Query};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
 use axum::{Extension, Json};
 use models::dto::sys::request::sys_user::{
@@ -19,10 +20,15 @@ pub async fn add(Json(data): Json<AddUserDto>) -> impl IntoResponse {
 
 /// 更新用户函数
 /// @param id 类型String
+/// @param headers If-Match请求头为读取数据时的版本，版本不一致时返回409
 /// @param data 类型UpdateUserDto
 /// return IntoResponse
-pub async fn update(Path(id): Path<String>, Json(data): Json<UpdateUserDto>) -> impl IntoResponse {
-    let result = sys_user::update(id, data).await;
+pub async fn update(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(data): Json<UpdateUserDto>,
+) -> impl IntoResponse {
+    let result = sys_user::update(id, data, handle_if_match(&headers)).await;
     handle_result(result)
 }
 
@@ -3,11 +3,7 @@ use axum::Router;
 
 use crate::handler::base::upload_download;
 
-/// 基础路由
-/// @author tanghy
-/// login 登录路由
-/// logout 登出路由
-/// register 注册路由
-pub fn image_route() -> Router {
-    Router::new().route("/image/*path", get(upload_download::image))
+/// 预签名下载路由，不需要登录，浏览文件使用需要认证的/sys/image
+pub fn presigned_route() -> Router {
+    Router::new().route("/presigned/*path", get(upload_download::presigned))
 }
@@ -14,7 +14,11 @@ pub fn form_data_route() -> Router {
         .route("/delete/:table_id/:id", get(form::delete))
         .route("/query/:table_id/:id", get(form::query))
         .route("/queryCode/:table_id/:code", get(form::get_by_code))
-        .route("/search/:table_id", post(form::search));
+        .route("/search/:table_id", post(form::search))
+        .route("/aggregate/:table_id", post(form::aggregate))
+        .route("/history/:table_id/:id", get(form::history))
+        .route("/restore/:table_id/:history_id", post(form::restore))
+        .route("/attachments/:table_id/:id", get(form::attachments));
 
     Router::new().nest("/form", router)
 }
@@ -11,7 +11,11 @@ pub fn form_template_route() -> Router {
         .route("/update/:id", post(form_template::update))
         .route("/delete/:id", get(form_template::delete))
         .route("/query/:id", get(form_template::query))
-        .route("/search", post(form_template::search));
+        .route("/search", post(form_template::search))
+        .route("/publish/:id", post(form_template::publish))
+        .route("/versions/:id", get(form_template::versions))
+        .route("/version/:id/:version", get(form_template::version))
+        .route("/diff/:id", get(form_template::diff));
 
     Router::new()
         .nest("/form/template", router)
@@ -11,6 +11,10 @@ pub fn project_route() -> Router {
         .route("/update/:id", post(project::update))
         .route("/delete/:id", get(project::delete))
         .route("/query/:id", get(project::query))
+        .route("/migrate/:id", post(project::migrate))
+        .route("/import/:id", post(project::import))
+        .route("/export/:id", get(project::export))
+        .route("/stats/:id", get(project::stats))
         .route("/search", post(project::search));
 
     Router::new().nest("/project", router)
@@ -0,0 +1,13 @@
+use crate::handler::cts::sync;
+use axum::routing::post;
+use axum::Router;
+
+/// 离线同步路由
+///
+pub fn sync_route() -> Router {
+    let router = Router::new()
+        .route("/pull/:table_id", post(sync::pull))
+        .route("/push/:table_id", post(sync::push));
+
+    Router::new().nest("/sync", router)
+}
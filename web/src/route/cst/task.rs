@@ -11,7 +11,14 @@ pub fn task_route() -> Router {
         .route("/update/:table_id/:id", post(task::update))
         .route("/delete/:table_id/:id", get(task::delete))
         .route("/query/:table_id/:id", get(task::query))
-        .route("/search/:table_id", post(task::search));
+        .route("/search/:table_id", post(task::search))
+        .route("/mine/:table_id", post(task::mine))
+        .route("/assign/:table_id", post(task::assign))
+        .route("/split/:table_id", post(task::split))
+        .route("/transition/:table_id/:id", post(task::transition))
+        .route("/transitions/:table_id/:id", get(task::transitions))
+        .route("/history/:table_id/:id", get(task::history))
+        .route("/restore/:table_id/:history_id", post(task::restore));
 
     Router::new()
         .nest("/task", router)
@@ -2,6 +2,7 @@ use crate::route::base::login_logout::login_route;
 use crate::route::cst::form::form_data_route;
 use crate::route::cst::form_template::form_template_route;
 use crate::route::cst::project::project_route;
+use crate::route::cst::sync::sync_route;
 use crate::route::cst::task::task_route;
 use crate::route::sys::sys_api::api_route;
 use crate::route::sys::sys_menu::menu_route;
@@ -19,7 +20,7 @@ use axum::Router;
 use middleware::layers as my_layers;
 use response_utils::res::ResResult;
 
-use self::base::upload_download::image_route;
+use self::base::upload_download::presigned_route;
 use self::sys::sys_table::table_route;
 
 pub mod base;
@@ -67,6 +68,7 @@ fn auth_api() -> Router {
         .merge(form_template_route())
         .merge(task_route())
         .merge(form_data_route())
+        .merge(sync_route())
         .merge(project_route());
 
     Router::new()
@@ -78,7 +80,7 @@ fn auth_api() -> Router {
 
 /// 无需认证api
 fn no_auth_api() -> Router {
-    Router::new().merge(login_route()).merge(image_route())
+    Router::new().merge(login_route()).merge(presigned_route())
 }
 
 /// 服务错误处理函数
@@ -12,5 +12,6 @@ pub fn upload_download_route() -> Router {
         .route("/upload", post(upload_download::upload))
         .route("/image/*path", get(upload_download::image))
         .route("/download/*path", get(upload_download::download))
+        .route("/presign/*path", get(upload_download::presign))
 }
 
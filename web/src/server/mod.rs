@@ -1,8 +1,11 @@
 use crate::route::api;
+use common::storage::get_storage;
 use tracing::info;
 
 /// 启动服务
 pub async fn start() {
+    // 检查文件存储配置，配置不正确时不启动服务
+    get_storage().expect("初始化文件存储失败");
     // 系统api
     let app = api();
     // 监听
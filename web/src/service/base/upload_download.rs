@@ -1,16 +1,125 @@
-use std::fs::read;
+use std::io;
+use std::time::Duration;
 
 use anyhow::{Ok, Result};
 use axum::extract::Multipart;
+use chrono::Local;
+use futures::{stream, StreamExt};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter};
+use uuid::Uuid;
 
-use project_form::request::{CtsFile, FileParse};
+use common::db::get_db;
+use common::file::serve::SNIFF_SIZE;
+use common::storage::{get_storage, ByteStream, FileMeta};
+use entity::sys_file::{ActiveModel, Column, Entity as SysFile};
+use models::dto::sys::response::sys_file::ResponseFile;
+use models::dto::sys::response::sys_user::ResponseUser;
+use project_form::request::FileParse;
 
-pub async fn upload(mut multipart: Multipart) -> Result<Vec<CtsFile>> {
-    let (_, result) = multipart.parse("upload").await?;
+/// 上传文件，保存文件信息，表单数据使用返回的文件编号引用文件
+/// @param user 上传用户
+/// @param multipart 上传数据
+pub async fn upload(user: ResponseUser, mut multipart: Multipart) -> Result<Vec<ResponseFile>> {
+    let (_, files) = multipart.parse("upload").await?;
+    let db = get_db().await;
+    let tenant_id = user.tenant.map(|tenant| tenant.id);
+    let mut result = Vec::with_capacity(files.len());
+    for file in files.into_iter() {
+        let current = ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            original_name: Set(file.filename),
+            path: Set(file.path),
+            size: Set(file.size as i64),
+            mime: Set(file.mime),
+            sha256: Set(file.sha256),
+            user_id: Set(user.id.clone()),
+            tenant_id: Set(tenant_id.clone()),
+            created_at: Set(Local::now().naive_local()),
+        };
+        let add_file = current.insert(&db).await?;
+        result.push(add_file.into());
+    }
     Ok(result)
 }
 
-pub async fn read_file(path: String) -> Result<Vec<u8>> {
-    let data = read(path)?;
-    Ok(data)
+/// 查询文件信息，文件不存在时返回StorageError::NotFound
+/// @param path 文件路径
+pub async fn metadata(path: &str) -> Result<FileMeta> {
+    get_storage()?.metadata(path).await
+}
+
+/// 查询上传时的文件名，没有上传记录时使用路径中的文件名
+/// @param path 文件路径
+pub async fn original_name(path: &str) -> Result<String> {
+    let db = get_db().await;
+    let file = SysFile::find()
+        .filter(Column::Path.eq(path))
+        .one(&db)
+        .await?;
+    let name = match file {
+        Some(file) => file.original_name,
+        None => path.rsplit('/').next().unwrap_or(path).to_string(),
+    };
+    Ok(name)
+}
+
+/// 读取文件的一部分，同时返回文件开始的数据用于识别文件类型
+/// @param path 文件路径
+/// @param size 文件大小
+/// @param start 开始位置
+/// @param end 结束位置，包含结束位置
+pub async fn read_file(
+    path: &str,
+    size: u64,
+    start: u64,
+    end: u64,
+) -> Result<(Vec<u8>, ByteStream<'static>)> {
+    if size == 0 {
+        return Ok((vec![], Box::pin(stream::empty())));
+    }
+    let storage = get_storage()?;
+    let stream = storage.get_range(path, start, end).await?;
+    if start == 0 {
+        return read_head(stream).await;
+    }
+    // 不是从文件开始读取时，单独读取文件开始的数据
+    let head_end = (SNIFF_SIZE as u64).min(size) - 1;
+    let (head, _) = read_head(storage.get_range(path, 0, head_end).await?).await?;
+    Ok((head, stream))
+}
+
+/// 读取数据流开始的数据，返回读取到的数据和完整的数据流
+/// @param stream 数据流
+async fn read_head(mut stream: ByteStream<'static>) -> Result<(Vec<u8>, ByteStream<'static>)> {
+    let mut head = Vec::new();
+    let mut chunks = Vec::new();
+    while head.len() < SNIFF_SIZE {
+        match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                head.extend_from_slice(&chunk);
+                chunks.push(io::Result::Ok(chunk));
+            }
+            None => break,
+        }
+    }
+    Ok((head, Box::pin(stream::iter(chunks).chain(stream))))
+}
+
+/// 生成文件的预签名下载地址
+/// @param path 文件路径
+/// @param expires 有效期（秒）
+pub async fn presigned_url(path: String, expires: u64) -> Result<String> {
+    get_storage()?
+        .presigned_url(&path, Duration::from_secs(expires))
+        .await
+}
+
+/// 校验预签名地址，签名不正确或者已经过期时不通过
+/// @param path 文件路径
+/// @param expires 过期时间戳（秒）
+/// @param signature 签名
+pub fn verify_presigned(path: &str, expires: i64, signature: &str) -> bool {
+    get_storage().is_ok_and(|storage| storage.verify_presigned(path, expires, signature))
 }
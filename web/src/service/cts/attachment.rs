@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::Local;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder};
+use uuid::Uuid;
+
+use common::db::get_db;
+use common::error::ValidationError;
+use entity::sys_file::{Column as SysFileColumn, Entity as SysFile};
+use entity::sys_file_link::{ActiveModel, Column as SysFileLinkColumn, Entity as SysFileLink};
+use models::dto::sys::response::sys_file::{ResponseAttachment, ResponseFile};
+
+/// 保存数据引用的文件，替换提交了的题目原有的引用，文件不存在时返回字段校验错误
+/// @param db 数据库连接
+/// @param table_name 数据表名
+/// @param record_id 数据编号
+/// @param refs 题目名称和引用的文件编号
+pub(crate) async fn link<C>(
+    db: &C,
+    table_name: &str,
+    record_id: &str,
+    refs: Vec<(String, Vec<String>)>,
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    if refs.is_empty() {
+        return Ok(());
+    }
+    // 检查文件是否存在
+    let ids: HashSet<&String> = refs.iter().flat_map(|(_, ids)| ids.iter()).collect();
+    let exists: HashSet<String> = SysFile::find()
+        .filter(SysFileColumn::Id.is_in(ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|file| file.id)
+        .collect();
+    let mut error = ValidationError::new();
+    for (name, ids) in refs.iter() {
+        if let Some(id) = ids.iter().find(|id| !exists.contains(*id)) {
+            error.add(name, format!("文件：{}，不存在", id));
+        }
+    }
+    if !error.is_empty() {
+        return Err(error.into());
+    }
+    let created_at = Local::now().naive_local();
+    for (name, ids) in refs.into_iter() {
+        SysFileLink::delete_many()
+            .filter(SysFileLinkColumn::TableName.eq(table_name))
+            .filter(SysFileLinkColumn::RecordId.eq(record_id))
+            .filter(SysFileLinkColumn::QuestionName.eq(&name))
+            .exec(db)
+            .await?;
+        if ids.is_empty() {
+            continue;
+        }
+        let links = ids
+            .into_iter()
+            .enumerate()
+            .map(|(index, file_id)| ActiveModel {
+                id: Set(Uuid::new_v4().to_string()),
+                file_id: Set(file_id),
+                table_name: Set(table_name.to_string()),
+                record_id: Set(record_id.to_string()),
+                question_name: Set(name.clone()),
+                sort: Set(index as i32),
+                created_at: Set(created_at),
+            });
+        SysFileLink::insert_many(links).exec(db).await?;
+    }
+    Ok(())
+}
+
+/// 删除数据引用的全部文件，文件本身保留
+/// @param db 数据库连接
+/// @param table_name 数据表名
+/// @param record_id 数据编号
+pub(crate) async fn unlink<C>(db: &C, table_name: &str, record_id: &str) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    SysFileLink::delete_many()
+        .filter(SysFileLinkColumn::TableName.eq(table_name))
+        .filter(SysFileLinkColumn::RecordId.eq(record_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 查询数据引用的文件，按照题目和引用顺序排列
+/// @param table_name 数据表名
+/// @param record_id 数据编号
+pub async fn list(table_name: String, record_id: String) -> Result<Vec<ResponseAttachment>> {
+    let db = get_db().await;
+    let links = SysFileLink::find()
+        .filter(SysFileLinkColumn::TableName.eq(table_name))
+        .filter(SysFileLinkColumn::RecordId.eq(record_id))
+        .order_by_asc(SysFileLinkColumn::QuestionName)
+        .order_by_asc(SysFileLinkColumn::Sort)
+        .all(&db)
+        .await?;
+    let ids: HashSet<&String> = links.iter().map(|link| &link.file_id).collect();
+    let files: HashMap<String, ResponseFile> = SysFile::find()
+        .filter(SysFileColumn::Id.is_in(ids))
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|file| (file.id.clone(), file.into()))
+        .collect();
+    let result = links
+        .into_iter()
+        .filter_map(|link| {
+            let file = files.get(&link.file_id)?.clone();
+            Some(ResponseAttachment {
+                question_name: link.question_name,
+                file,
+            })
+        })
+        .collect();
+    Ok(result)
+}
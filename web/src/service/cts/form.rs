@@ -1,11 +1,34 @@
 use anyhow::{bail, Ok, Result};
-use sea_orm::{PaginatorTrait, TransactionTrait};
+use sea_orm::sea_query::Cond;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, JsonValue, PaginatorTrait,
+    QueryFilter, TransactionTrait,
+};
 use serde_json::{Map, Value};
 
-use common::db::form::get_table_name;
+use common::db::aggregate::{aggregate_sql, frequency_sql};
+use common::db::db_type::ColumnType;
+use common::db::form::{get_table_name, FormCommonField};
 use common::db::get_db;
+use common::db::schema::TableSchema;
 use common::db::select::CtsSelect;
-use models::dto::cts::request::form::{AddFormData, SearchFormData, UpdateFormData};
+use common::db::workflow::TaskAction;
+use entity::form_template::Entity as FormTemplateEntity;
+use entity::project::{Column as ProjectColumn, Entity as Project};
+use project_form::form::form_json::FormTemplate;
+use project_form::form::form_util::parse;
+
+use crate::service::cts::attachment::{link, unlink};
+use crate::service::cts::form_template::find_version;
+use crate::service::cts::task::check_task_user;
+use crate::service::cts::workflow::{apply, check_edit, check_role};
+use models::dto::cts::request::form::{
+    AddFormData, AggregateFormData, SearchFormData, UpdateFormData,
+};
+use models::dto::cts::response::form::{
+    AggregateGroup, AggregateResult, FrequencyRow, FrequencyTable, MetricValue,
+};
+use models::dto::sys::response::sys_user::ResponseUser;
 use models::dto::{handler_page, PageResult};
 
 const IS_DATA: bool = true;
@@ -26,76 +49,117 @@ pub async fn get_by_code(table_id: String, code: String) -> Result<Option<Value>
     let table_name = get_table_name(&table_id, IS_DATA);
     let mut cts_select = CtsSelect::table(&table_name);
     cts_select.defualt_filter();
-    cts_select.filter(&format!("code ='{}'", code));
+    cts_select.filter_eq("code", Value::String(code));
     let result = cts_select.find().one(&db).await?;
     Ok(result)
 }
 
 /// 根据编号删除数据
+/// 可以审核的角色可以删除所有数据，其他角色只能删除分配给自己的任务，审核中和审核通过的数据不能删除
+/// @param user 当前用户
 /// @param id 编号
-pub async fn delete_by_id(table_id: String, id: String, force: bool) -> Result<String> {
+pub async fn delete_by_id(
+    user: ResponseUser,
+    table_id: String,
+    id: String,
+    force: bool,
+) -> Result<String> {
     let db = get_db().await;
     // 查询数据是否存在
     let table_name = get_table_name(&table_id, IS_DATA);
+    let tx = db.begin().await?;
     let result = CtsSelect::table(&table_name)
         .find_by_id(&id)
-        .one(&db)
+        .one(&tx)
         .await?;
     if result.is_some() {
+        check_edit(&tx, &user, &table_id, &id).await?;
         // 判断是否强制删除，如果是删除数据，如果不是更新删除字段
-        let _ = CtsSelect::table(&table_name)
-            .delete_by_id(&id, force)
-            .execute(&db)
-            .await?;
+        let mut cts_select = CtsSelect::table(&table_name);
+        cts_select.user(&user.id);
+        let _ = cts_select.delete_by_id(&id, force).execute(&tx).await?;
+        // 彻底删除时删除文件引用
+        if force {
+            unlink(&tx, &table_name, &id).await?;
+        }
+        tx.commit().await?;
         Ok(id)
     } else {
         bail!("无法删除，数据不存在")
     }
 }
 
-/// 添加数据
+/// 添加数据，数据编号为对应的任务编号
+/// 可以审核的角色直接添加，其他角色只能添加分配给自己的任务，添加后任务流转为已提交
+/// @param user 当前用户
+/// @param table_id 数据表编号
 /// @param data 数据对象
-pub async fn add(table_id: String, data: Value) -> Result<String> {
-    // 获取数据库连接
+pub async fn add(user: ResponseUser, table_id: String, data: Value) -> Result<String> {
+    let task_id = match data.get(FormCommonField::Id.to_string()) {
+        Some(Value::String(id)) if !id.is_empty() => id.clone(),
+        _ => bail!("请指定数据对应的任务编号"),
+    };
     let db = get_db().await;
-    let table_name = get_table_name(&table_id, IS_DATA);
-    let mut data_id = String::from("0");
-    let _ = CtsSelect::table(&table_name)
-        .add(data, |id| {
-            data_id.clone_from(id);
-        })?
-        .execute(&db)
-        .await?;
-
-    Ok(data_id)
+    let tx = db.begin().await?;
+    let reviewer = check_edit(&tx, &user, &table_id, &task_id).await?;
+    add_in(&tx, &user.id, &table_id, &task_id, data).await?;
+    if !reviewer {
+        apply(&tx, &table_id, &task_id, TaskAction::Submit, &user.id, None).await?;
+    }
+    tx.commit().await?;
+    Ok(task_id)
 }
 
-// 添加数据2
-pub async fn add_data(data: AddFormData) -> Result<String> {
+// 添加数据2，分配给其他用户的任务不能提交，提交用户保存在user_id字段
+// 提交后任务流转为已提交，驳回的任务流转为重新提交，审核中和审核通过的任务不能提交
+pub async fn add_data(user: ResponseUser, data: AddFormData) -> Result<String> {
+    check_role(&user, TaskAction::Submit)?;
     // 获取数据库连接
     let db = get_db().await;
-    let table_id = data.name;
-    let table_name = get_table_name(&table_id, true);
-    // 添加 id 字段
-    let data_value = insert_id_to_data(data.data, &data.task_id);
     // 事务
     let tx = db.begin().await?;
-    let _ = CtsSelect::table(&table_name)
-        .add(data_value, |data_id|{})?
-        .execute(&tx)
-        .await?;
+    check_task_user(&tx, &data.name, &data.task_id, &user.id).await?;
+    add_in(&tx, &user.id, &data.name, &data.task_id, data.data).await?;
     // 更新任务状态
-    let mut  map_value = Map::new();
-    map_value.insert("status".to_string(),Value::Number(1i32.into()));
-    // 任务表
-    let task_name = get_table_name(&table_id, false);
-    let _ = CtsSelect::table(&task_name)
-        .update(&data.task_id, Value::Object(map_value))?
-        .execute(&tx)
-        .await?;
+    apply(&tx, &data.name, &data.task_id, TaskAction::Submit, &user.id, None).await?;
     tx.commit().await?;
     Ok(data.task_id)
+}
 
+/// 在调用方的连接或者事务中添加数据，数据编号和任务编号相同
+/// @param db 数据库连接或者事务
+/// @param user_id 提交用户编号
+/// @param table_id 数据表编号
+/// @param id 数据编号
+/// @param data 数据对象
+pub(crate) async fn add_in<C>(
+    db: &C,
+    user_id: &str,
+    table_id: &str,
+    id: &str,
+    data: Value,
+) -> Result<String>
+where
+    C: ConnectionTrait,
+{
+    let table_name = get_table_name(table_id, IS_DATA);
+    // 根据表单模板校验数据
+    let form_template = get_form_template(db, table_id).await?;
+    form_template.validate(&data, None)?;
+    let file_refs = form_template.file_refs(&data);
+    // 添加 id 字段
+    let mut data_value = insert_id_to_data(data, id);
+    if let Value::Object(value_map) = &mut data_value {
+        value_map.insert(
+            FormCommonField::UserId.to_string(),
+            Value::String(user_id.to_string()),
+        );
+    }
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.user(user_id);
+    let _ = cts_select.add(data_value, |_| {})?.execute(db).await?;
+    link(db, &table_name, id, file_refs).await?;
+    Ok(id.to_string())
 }
 
 pub fn insert_id_to_data(mut data:Value, id:&str) -> Value{
@@ -110,36 +174,120 @@ pub fn insert_id_to_data(mut data:Value, id:&str) -> Value{
     }
 }
 
-// 更新数据2
-pub async fn update_data(data: UpdateFormData) -> Result<String> {
-    let table_id = data.name;
-    let data_id = data.task_id;
-    let form_data = data.data;
-    update(table_id, data_id, form_data).await
+// 更新数据2，分配给其他用户的任务不能提交，审核中和审核通过的数据不能修改
+pub async fn update_data(user: ResponseUser, data: UpdateFormData) -> Result<String> {
+    check_role(&user, TaskAction::Submit)?;
+    let db = get_db().await;
+    // 流转任务状态和更新数据在同一个事务中执行，数据更新失败时回滚
+    let tx = db.begin().await?;
+    check_task_user(&tx, &data.name, &data.task_id, &user.id).await?;
+    apply(&tx, &data.name, &data.task_id, TaskAction::Submit, &user.id, None).await?;
+    let result = update_in(
+        &tx,
+        &user.id,
+        &data.name,
+        &data.task_id,
+        data.data,
+        data.version,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(result)
 }
 
 /// 更新数据信息
+/// 可以审核的角色可以修改所有数据，其他角色只能修改分配给自己的任务，修改后任务流转为已提交
+/// @param user 当前用户
+/// @param table_id 数据表编号
+/// @param id 数据编号
 /// @param data 数据对象
-pub async fn update(table_id: String, id: String, data: Value) -> Result<String> {
-    // 获取数据库连接
+/// @param version 读取数据时的版本，None不检查
+pub async fn update(
+    user: ResponseUser,
+    table_id: String,
+    id: String,
+    data: Value,
+    version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
+    // 检查权限、版本和更新数据在同一个事务中执行
+    let tx = db.begin().await?;
+    let reviewer = check_edit(&tx, &user, &table_id, &id).await?;
+    if !reviewer {
+        apply(&tx, &table_id, &id, TaskAction::Submit, &user.id, None).await?;
+    }
+    let result = update_in(&tx, &user.id, &table_id, &id, data, version).await?;
+    tx.commit().await?;
+    Ok(result)
+}
+
+/// 在调用方的连接或者事务中更新数据信息
+/// @param db 数据库连接或者事务
+/// @param user_id 操作用户编号
+/// @param table_id 数据表编号
+/// @param id 数据编号
+/// @param data 数据对象
+/// @param version 读取数据时的版本，None不检查
+pub(crate) async fn update_in<C>(
+    db: &C,
+    user_id: &str,
+    table_id: &str,
+    id: &str,
+    data: Value,
+    version: Option<String>,
+) -> Result<String>
+where
+    C: ConnectionTrait,
+{
     // 查询是否有数据
-    let table_name = get_table_name(&table_id, IS_DATA);
+    let table_name = get_table_name(table_id, IS_DATA);
     let result = CtsSelect::table(&table_name)
-        .find_by_id(&id)
-        .one(&db)
+        .find_by_id(id)
+        .one(db)
         .await?;
-    match result {
-        None => {
-            bail!("数据不存在，无法更新")
-        }
-        Some(_) => {
-            let _ = CtsSelect::table(&table_name)
-                .update(&id, data)?
-                .execute(&db)
-                .await?;
-            Ok(id)
-        }
+    let current = match result {
+        None => bail!("数据不存在，无法更新"),
+        Some(current) => current,
+    };
+    // 根据表单模板校验数据，表达式使用合并后的数据计算
+    let form_template = get_form_template(db, table_id).await?;
+    form_template.validate(&data, Some(&current))?;
+    let file_refs = form_template.file_refs(&data);
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.user(user_id);
+    cts_select.version(version);
+    let _ = cts_select.update(id, data)?.execute(db).await?;
+    link(db, &table_name, id, file_refs).await?;
+    Ok(id.to_string())
+}
+
+/// 根据数据表编号查询项目使用的表单模板
+/// @param db 数据库连接
+/// @param table_id 数据表编号
+pub(crate) async fn get_form_template<C>(db: &C, table_id: &str) -> Result<FormTemplate>
+where
+    C: ConnectionTrait,
+{
+    let project = Project::find()
+        .filter(ProjectColumn::DataTableName.eq(table_id))
+        .filter(ProjectColumn::DeletedAt.is_null())
+        .one(db)
+        .await?;
+    let project = match project {
+        None => bail!("数据表：{}，对应的项目不存在", table_id),
+        Some(project) => project,
+    };
+    // 使用项目固定的表单版本，没有固定版本的项目使用表单当前内容
+    if let Some(version) = &project.form_template_version {
+        let form_version = find_version(db, &project.form_template_id, Some(version)).await?;
+        return parse(&form_version.content);
+    }
+    let form_template = FormTemplateEntity::find_by_id(project.form_template_id)
+        .one(db)
+        .await?;
+    match form_template.and_then(|form| form.content) {
+        None => bail!("表单不存在"),
+        Some(content) => parse(&content),
     }
 }
 
@@ -152,23 +300,21 @@ pub async fn search(table_id: String, data: SearchFormData) -> Result<PageResult
     cts_select.columns(data.fields);
     // 设置查询条件
     if let Some(wheres) = data.wheres {
-        for where_str in wheres.iter() {
-            cts_select.filter(where_str);
-        }
+        cts_select.filter(wheres);
     }
     cts_select.defualt_filter();
     // 设置排序
     if let Some(order_bys) = data.orders {
         for order_by in order_bys.iter() {
-            cts_select.order_by(order_by);
+            cts_select.order_by(order_by)?;
         }
     }
-    let select = cts_select.select();
+    let select = cts_select.select(&db).await?;
     // 查询数据数量
     let total = select.count(&db).await?;
     let (page_no, page_size) = handler_page(data.page);
     // 创建查询对象
-    let select = CtsSelect::table(&table_name).select();
+    let select = cts_select.select(&db).await?;
     // 分页对象
     let paginate = select.paginate(&db, page_size);
     // 页数
@@ -183,3 +329,113 @@ pub async fn search(table_id: String, data: SearchFormData) -> Result<PageResult
     let result = PageResult::new(list, total, pages, page_no);
     Ok(result)
 }
+
+/// 统计表单数据，题目根据表单模板和数据表结构校验，在数据库中分组统计
+/// 单选题频数统计时选项编码转换成选项名称
+/// @param table_id 数据表编号
+/// @param data 统计参数
+pub async fn aggregate(table_id: String, data: AggregateFormData) -> Result<AggregateResult> {
+    let db = get_db().await;
+    let form_template = get_form_template(&db, &table_id).await?;
+    let questions = form_template.data_questions();
+    let table_name = get_table_name(&table_id, IS_DATA);
+    let schema = TableSchema::load(&db, &table_name).await?;
+    // 题目必须在表单模板和数据表中
+    let find_question = |name: &str| match questions.iter().find(|item| item.name == name) {
+        Some(question) if schema.contains(name) => Ok(*question),
+        _ => bail!("题目：{}，不在表单或者数据表中", name),
+    };
+    let group_by = data.group_by.unwrap_or_default();
+    for name in group_by.iter() {
+        find_question(name)?;
+    }
+    let metrics = data.metrics.unwrap_or_default();
+    for metric in metrics.iter() {
+        let question = find_question(&metric.field)?;
+        let numeric = matches!(
+            question.column_type(),
+            ColumnType::Numeric | ColumnType::Double
+        );
+        if metric.op.numeric() && !numeric {
+            bail!("题目：{}，不是数值题目，只能统计数量", metric.field)
+        }
+    }
+    let condition = match &data.wheres {
+        Some(wheres) => wheres.condition(&schema)?,
+        None => Cond::all(),
+    };
+    // 分组统计数量和指标
+    let statement = aggregate_sql(&schema, &group_by, &metrics, condition.clone())?;
+    let groups = JsonValue::find_by_statement(statement)
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|mut row| AggregateGroup {
+            keys: group_keys(&mut row, &group_by),
+            count: row["count"].as_u64().unwrap_or(0),
+            metrics: metrics
+                .iter()
+                .enumerate()
+                .map(|(index, metric)| MetricValue {
+                    field: metric.field.clone(),
+                    op: metric.op,
+                    value: take_value(&mut row, &format!("m{}", index)),
+                })
+                .collect(),
+        })
+        .collect();
+    // 单选题频数统计
+    let mut frequencies = Vec::new();
+    for name in data.frequencies.unwrap_or_default().iter() {
+        let question = find_question(name)?;
+        if question.r#type != "SingleChoiceType" {
+            bail!("题目：{}，不是单选题，不能统计频数", name)
+        }
+        let items = question.items.clone().unwrap_or_default();
+        let statement = frequency_sql(&schema, name, &group_by, condition.clone())?;
+        let rows = JsonValue::find_by_statement(statement)
+            .all(&db)
+            .await?
+            .into_iter()
+            .map(|mut row| {
+                let code = take_value(&mut row, "value");
+                let name = match &code {
+                    Value::String(code) => items
+                        .iter()
+                        .find(|item| &item.code == code)
+                        .map(|item| item.name.clone()),
+                    _ => None,
+                };
+                FrequencyRow {
+                    keys: group_keys(&mut row, &group_by),
+                    code,
+                    name,
+                    count: row["count"].as_u64().unwrap_or(0),
+                }
+            })
+            .collect();
+        frequencies.push(FrequencyTable {
+            field: question.name.clone(),
+            title: question.title.clone(),
+            rows,
+        });
+    }
+    Ok(AggregateResult {
+        groups,
+        frequencies,
+    })
+}
+
+/// 取出分组题目的值，分组别名为g0、g1
+fn group_keys(row: &mut Value, group_by: &[String]) -> Map<String, Value> {
+    group_by
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.clone(), take_value(row, &format!("g{}", index))))
+        .collect()
+}
+
+/// 取出查询结果中的字段值
+fn take_value(row: &mut Value, alias: &str) -> Value {
+    row.get_mut(alias).map(Value::take).unwrap_or_default()
+}
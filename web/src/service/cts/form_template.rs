@@ -2,18 +2,32 @@ use anyhow::{bail, Result};
 use chrono::Local;
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, Iterable, NotSet, PaginatorTrait, QueryFilter,
-    QuerySelect,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, Iterable, NotSet,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 use uuid::Uuid;
 
 use common::db::get_db;
+use common::db::version::{check_version, version};
 use entity::form_template::{ActiveModel, Column as FormTemplateColumn, Entity as FormTemplate};
+use entity::form_template_version::{
+    ActiveModel as VersionActiveModel, Column as VersionColumn, Entity as FormTemplateVersion,
+};
 use models::dto::cts::request::form_template::{
-    AddFormTemplateDto, SearchFormTemplateDto, UpdateFormTemplateDto,
+    AddFormTemplateDto, DiffFormTemplateDto, PublishFormTemplateDto, SearchFormTemplateDto,
+    UpdateFormTemplateDto,
+};
+use models::dto::cts::response::form_template::{
+    ResponseFormTemplate, ResponseFormTemplateVersion,
 };
-use models::dto::cts::response::form_template::ResponseFormTemplate;
 use models::dto::{handler_page, PageResult};
+use project_form::form::form_diff::FormDiff;
+use project_form::form::form_util::parse;
+
+/// 表单状态，草稿
+pub const TEMPLATE_DRAFT: i32 = 0;
+/// 表单状态，已发布
+pub const TEMPLATE_PUBLISHED: i32 = 1;
 
 /// 根据表单编号查询数据
 /// @param id 编号
@@ -72,6 +86,8 @@ pub async fn add(data: AddFormTemplateDto) -> Result<String> {
     if data.name.is_empty() {
         bail!("名称不能为空".to_string())
     }
+    // 检查表单内容和校验表达式
+    parse(&data.content)?.check_expressions()?;
 
     let current: ActiveModel = ActiveModel {
         id: Set(Uuid::new_v4().to_string()),
@@ -81,6 +97,7 @@ pub async fn add(data: AddFormTemplateDto) -> Result<String> {
         remark: Set(data.remark),
         version: Set(data.version),
         description: Set(data.description),
+        status: Set(TEMPLATE_DRAFT),
         created_at: Set(Local::now().naive_local()),
         updated_at: NotSet,
         deleted_at: NotSet,
@@ -92,16 +109,26 @@ pub async fn add(data: AddFormTemplateDto) -> Result<String> {
 
 /// 更新表单信息
 /// @param update_form_template 待更新的表单对象
-pub async fn update(id: String, update_form_template: UpdateFormTemplateDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_form_template: UpdateFormTemplateDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
         bail!("编号不能为空".to_string())
     }
     // 查询表单信息
-    let result = FormTemplate::find_by_id(id).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = FormTemplate::find_by_id(id).lock_exclusive().one(&tx).await?;
     // 更新表单信息
     if let Some(current) = result {
+        let current_version = version(current.updated_at, current.created_at);
+        let response = ResponseFormTemplate::from(current.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
         let mut current: ActiveModel = current.into();
         // 名称是否为空
         if update_form_template.name.is_some() {
@@ -111,13 +138,17 @@ pub async fn update(id: String, update_form_template: UpdateFormTemplateDto) ->
         if update_form_template.title.is_some() {
             current.title = Set(update_form_template.title.unwrap())
         }
-        // 更新content
-        if update_form_template.content.is_some() {
-            current.content = Set(update_form_template.content)
+        // 更新content，检查表单内容和校验表达式
+        if let Some(content) = &update_form_template.content {
+            parse(content)?.check_expressions()?;
+            current.content = Set(update_form_template.content);
+            // 内容修改后变为草稿，需要重新发布
+            current.status = Set(TEMPLATE_DRAFT);
         }
         // 更新 version
         if update_form_template.version.is_some() {
-            current.version = Set(update_form_template.version.unwrap())
+            current.version = Set(update_form_template.version.unwrap());
+            current.status = Set(TEMPLATE_DRAFT);
         }
         // 更新备注
         if update_form_template.remark.is_some() {
@@ -130,7 +161,8 @@ pub async fn update(id: String, update_form_template: UpdateFormTemplateDto) ->
         // 更新时间
         current.updated_at = Set(Some(Local::now().naive_local()));
         // 更新数据
-        let update_data = current.update(&db).await?;
+        let update_data = current.update(&tx).await?;
+        tx.commit().await?;
         Ok(update_data.id)
     } else {
         bail!("表单数据不存在，无法更新".to_string())
@@ -191,3 +223,116 @@ pub async fn search(data: SearchFormTemplateDto) -> Result<PageResult<ResponseFo
     Ok(result)
 }
 
+
+/// 发布表单，当前内容保存到发布历史，发布后的版本不能修改
+/// @param id 表单编号
+/// @param data 发布信息
+pub async fn publish(id: String, data: PublishFormTemplateDto) -> Result<String> {
+    let db = get_db().await;
+    let result = FormTemplate::find_by_id(id.clone())
+        .filter(FormTemplateColumn::DeletedAt.is_null())
+        .one(&db)
+        .await?;
+    let form_template = match result {
+        None => bail!("表单数据不存在，无法发布"),
+        Some(data) => data,
+    };
+    let content = match &form_template.content {
+        Some(content) if !content.is_empty() => content.clone(),
+        _ => bail!("表单内容为空，无法发布"),
+    };
+    // 检查表单内容和校验表达式
+    parse(&content)?.check_expressions()?;
+    // 判断版本是否已经发布
+    let count = FormTemplateVersion::find()
+        .filter(VersionColumn::FormTemplateId.eq(id.clone()))
+        .filter(VersionColumn::Version.eq(form_template.version.clone()))
+        .count(&db)
+        .await?;
+    if count > 0 {
+        bail!("版本：{}，已经发布，请修改版本后发布", form_template.version)
+    }
+    let tx = db.begin().await?;
+    let version = VersionActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        form_template_id: Set(id),
+        version: Set(form_template.version.clone()),
+        content: Set(content),
+        remark: Set(data.remark),
+        created_at: Set(Local::now().naive_local()),
+    };
+    let version = version.insert(&tx).await?;
+    let mut current: ActiveModel = form_template.into();
+    current.status = Set(TEMPLATE_PUBLISHED);
+    current.updated_at = Set(Some(Local::now().naive_local()));
+    current.update(&tx).await?;
+    tx.commit().await?;
+    Ok(version.version)
+}
+
+/// 查询表单发布历史，不返回表单内容
+/// @param id 表单编号
+pub async fn versions(id: String) -> Result<Vec<ResponseFormTemplateVersion>> {
+    let db = get_db().await;
+    let list = FormTemplateVersion::find()
+        .filter(VersionColumn::FormTemplateId.eq(id))
+        .order_by_desc(VersionColumn::CreatedAt)
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(|item| {
+            let mut version: ResponseFormTemplateVersion = item.into();
+            version.content = None;
+            version
+        })
+        .collect();
+    Ok(list)
+}
+
+/// 查询表单发布的版本
+/// @param id 表单编号
+/// @param version 版本
+pub async fn get_version(id: String, version: String) -> Result<ResponseFormTemplateVersion> {
+    let db = get_db().await;
+    let result = find_version(&db, &id, Some(&version)).await?;
+    Ok(result.into())
+}
+
+/// 比较表单两个发布版本的题目差异
+/// @param id 表单编号
+/// @param data 比较的版本
+pub async fn diff(id: String, data: DiffFormTemplateDto) -> Result<FormDiff> {
+    let db = get_db().await;
+    let from = find_version(&db, &id, Some(&data.from)).await?;
+    let to = find_version(&db, &id, Some(&data.to)).await?;
+    Ok(parse(&from.content)?.diff(&parse(&to.content)?))
+}
+
+/// 查询表单发布的版本，版本为空时查询最新发布的版本
+/// @param db 数据库连接
+/// @param id 表单编号
+/// @param version 版本
+pub async fn find_version<C>(
+    db: &C,
+    id: &str,
+    version: Option<&str>,
+) -> Result<entity::form_template_version::Model>
+where
+    C: ConnectionTrait,
+{
+    let mut select = FormTemplateVersion::find().filter(VersionColumn::FormTemplateId.eq(id));
+    if let Some(version) = version {
+        select = select.filter(VersionColumn::Version.eq(version));
+    }
+    let result = select
+        .order_by_desc(VersionColumn::CreatedAt)
+        .one(db)
+        .await?;
+    match result {
+        None => match version {
+            None => bail!("表单：{}，没有发布的版本", id),
+            Some(version) => bail!("表单：{}，版本：{}，不存在", id, version),
+        },
+        Some(data) => Ok(data),
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{bail, Result};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde_json::{Map, Value};
+
+use common::db::form::{get_table_name, FormCommonField};
+use common::db::get_db;
+use common::db::history::revert;
+use common::db::select::CtsSelect;
+use common::db::workflow::TaskAction;
+use entity::record_history::{self, Column as HistoryColumn, Entity as RecordHistory};
+use models::dto::cts::response::history::ResponseRecordHistory;
+use models::dto::sys::response::sys_user::ResponseUser;
+
+use crate::service::cts::form::{add_in, get_form_template, update_in};
+use crate::service::cts::task::check_task_user;
+use crate::service::cts::workflow::{apply, check_role};
+
+/// 查询数据的变更历史，最新的在前
+/// @param table_name 数据表名
+/// @param record_id 数据编号
+pub async fn list(table_name: String, record_id: String) -> Result<Vec<ResponseRecordHistory>> {
+    let db = get_db().await;
+    let result = RecordHistory::find()
+        .filter(HistoryColumn::TableName.eq(table_name))
+        .filter(HistoryColumn::RecordId.eq(record_id))
+        .order_by_desc(HistoryColumn::CreatedAt)
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(ResponseRecordHistory::from)
+        .collect();
+    Ok(result)
+}
+
+/// 将数据恢复到某次变更后的版本，从当前数据依次撤销之后的变更
+/// 恢复和提交数据的检查相同：只能恢复分配给自己的任务，审核中和审核通过的数据不能恢复，
+/// 恢复的数据根据表单模板校验，恢复后任务流转为已提交
+/// 只恢复表单题目和编码、经纬度字段，彻底删除的数据重新添加，删除的数据同时取消删除
+/// 恢复操作本身也记录在变更历史中
+/// @param user 当前用户
+/// @param table_id 数据表编号
+/// @param history_id 变更历史编号
+/// @param version 读取数据时的版本，None不检查
+pub async fn restore(
+    user: ResponseUser,
+    table_id: String,
+    history_id: String,
+    version: Option<String>,
+) -> Result<String> {
+    check_role(&user, TaskAction::Submit)?;
+    let db = get_db().await;
+    let table_name = get_table_name(&table_id, true);
+    let history = find_history(&db, &table_name, &history_id).await?;
+    let record_id = history.record_id.clone();
+    let tx = db.begin().await?;
+    check_task_user(&tx, &table_id, &record_id, &user.id).await?;
+    // 先流转任务状态并锁定任务，锁定后再读取数据和变更历史
+    apply(
+        &tx,
+        &table_id,
+        &record_id,
+        TaskAction::Submit,
+        &user.id,
+        None,
+    )
+    .await?;
+    let (current, data) = revert_newer(&tx, &table_name, &history).await?;
+    let form_template = get_form_template(&tx, &table_id).await?;
+    let submit_data = Value::Object(form_template.submit_data(&data));
+    match current {
+        Some(current) => {
+            update_in(&tx, &user.id, &table_id, &record_id, submit_data, version).await?;
+            // 恢复到删除前的版本时取消删除
+            if is_deleted(&current) && !is_deleted(&Value::Object(data)) {
+                let mut map_value = Map::new();
+                map_value.insert(FormCommonField::DeletedAt.to_string(), Value::Null);
+                let mut cts_select = CtsSelect::table(&table_name);
+                cts_select.user(&user.id);
+                cts_select
+                    .update(&record_id, Value::Object(map_value))?
+                    .execute(&tx)
+                    .await?;
+            }
+        }
+        None => {
+            add_in(&tx, &user.id, &table_id, &record_id, submit_data).await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(record_id)
+}
+
+/// 将任务恢复到某次变更后的版本，从当前任务依次撤销之后的变更
+/// 状态由任务流转维护，采集人员由任务分配维护，都不恢复，彻底删除的任务重新添加
+/// 恢复操作本身也记录在变更历史中
+/// @param user_id 操作用户编号
+/// @param table_id 任务表编号
+/// @param history_id 变更历史编号
+/// @param version 读取任务时的版本，None不检查
+pub async fn restore_task(
+    user_id: String,
+    table_id: String,
+    history_id: String,
+    version: Option<String>,
+) -> Result<String> {
+    let db = get_db().await;
+    let table_name = get_table_name(&table_id, false);
+    let history = find_history(&db, &table_name, &history_id).await?;
+    let record_id = history.record_id.clone();
+    let tx = db.begin().await?;
+    let (current, mut data) = revert_newer(&tx, &table_name, &history).await?;
+    // 编号和时间字段由添加和更新操作生成
+    for field in [
+        FormCommonField::Id,
+        FormCommonField::Status,
+        FormCommonField::UserId,
+        FormCommonField::CreatedAt,
+        FormCommonField::UpdatedAt,
+    ] {
+        data.remove(&field.to_string());
+    }
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.user(&user_id);
+    // 设置版本时更新前锁定任务并检查版本
+    cts_select.version(version);
+    match current {
+        Some(_) => {
+            cts_select.update(&record_id, Value::Object(data))?;
+        }
+        None => {
+            data.insert(
+                FormCommonField::Id.to_string(),
+                Value::String(record_id.clone()),
+            );
+            cts_select.add(Value::Object(data), |_| {})?;
+        }
+    }
+    cts_select.execute(&tx).await?;
+    tx.commit().await?;
+    Ok(record_id)
+}
+
+/// 查询要恢复的变更历史，彻底删除的版本不能恢复
+/// @param db 数据库连接
+/// @param table_name 数据表名
+/// @param history_id 变更历史编号
+async fn find_history<C>(
+    db: &C,
+    table_name: &str,
+    history_id: &str,
+) -> Result<record_history::Model>
+where
+    C: ConnectionTrait,
+{
+    let history = match RecordHistory::find_by_id(history_id).one(db).await? {
+        Some(history) if history.table_name == table_name => history,
+        _ => bail!("变更历史：{}，不存在", history_id),
+    };
+    if history.after.is_none() {
+        bail!("该版本数据已经彻底删除，不能恢复")
+    }
+    Ok(history)
+}
+
+/// 从当前数据依次撤销变更历史之后的变更，返回当前数据和恢复后的数据
+/// @param db 数据库事务
+/// @param table_name 数据表名
+/// @param history 要恢复的变更历史
+async fn revert_newer<C>(
+    db: &C,
+    table_name: &str,
+    history: &record_history::Model,
+) -> Result<(Option<Value>, Map<String, Value>)>
+where
+    C: ConnectionTrait,
+{
+    let newer = RecordHistory::find()
+        .filter(HistoryColumn::TableName.eq(table_name))
+        .filter(HistoryColumn::RecordId.eq(&history.record_id))
+        .filter(HistoryColumn::CreatedAt.gt(history.created_at))
+        .order_by_desc(HistoryColumn::CreatedAt)
+        .all(db)
+        .await?;
+    if newer.is_empty() {
+        bail!("该版本已经是当前版本")
+    }
+    let current = CtsSelect::table(table_name)
+        .find_by_id(&history.record_id)
+        .one(db)
+        .await?;
+    let mut data = match &current {
+        Some(Value::Object(current)) => current.clone(),
+        _ => Map::new(),
+    };
+    for item in newer.iter() {
+        revert(&mut data, item.before.as_ref())?;
+    }
+    Ok((current, data))
+}
+
+/// 数据是否已经删除
+fn is_deleted(data: &Value) -> bool {
+    data.get(FormCommonField::DeletedAt.to_string())
+        .is_some_and(|deleted_at| !deleted_at.is_null())
+}
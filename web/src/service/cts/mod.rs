@@ -1,4 +1,9 @@
 pub mod form_template;
 pub mod project;
 pub mod form;
-pub mod task;
\ No newline at end of file
+pub mod task;
+pub mod stats;
+pub mod workflow;
+pub mod history;
+pub mod sync;
+pub mod attachment;
@@ -1,25 +1,77 @@
-use anyhow::{bail, Ok, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+
+use anyhow::{bail, Result};
+use axum::body::{Body, Bytes};
 use axum::extract::Multipart;
 use chrono::Local;
-use common::file::csv::Csv;
+use futures::channel::mpsc;
+use futures::{SinkExt, Stream, StreamExt};
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, NotSet, PaginatorTrait,
-    QueryFilter, QuerySelect, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    FromQueryResult, JsonValue, NotSet, PaginatorTrait, QueryFilter, QuerySelect, Statement,
+    TransactionTrait, Value,
 };
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+use tracing::error;
 use uuid::Uuid;
 
-use common::db::{create_table_sql, get_db, insert_data_sql};
+use common::db::db_type::ColumnType;
+use common::db::filter::{FilterOp, FilterTree};
+use common::db::form::{get_table_name, FormCommonField};
+use common::db::history::{execute_with_history, HistoryAction};
+use common::db::schema::TableSchema;
+use common::db::select::CtsSelect;
+use common::db::version::{check_version, version};
+use common::db::{
+    add_column_sql, create_table_sql, delete_all_sql, get_db, insert_batch_sql,
+    join_task_data_sql, update_row_sql,
+};
+use common::error::{ImportError, RowError};
+use common::file::csv::{CsvOptions, CsvReader, CsvRecord};
+use common::storage::{check_key, get_storage};
 use entity::form_template::{Column as FormTemplateColumn, Entity as FormTemplate};
 use entity::project::{ActiveModel, Column as ProjectColumn, Entity as Project};
-use models::dto::cts::request::project::{AddProjectDto, SearchProjectDto, UpdateProjectDto};
-use models::dto::cts::response::project::ResponseProject;
+use models::dto::cts::request::project::{
+    AddProjectDto, ExportProjectDto, ImportMode, ImportTaskDto, MigrateProjectDto,
+    SearchProjectDto, UpdateProjectDto,
+};
+use models::dto::cts::response::project::{ImportTaskResult, ResponseProject};
 use models::dto::{handler_page, PageResult};
+use project_form::export::{ExportColumn, ExportEnd, ExportFormat, ExportRow, Exporter};
+use project_form::form::form_migrate::MigratePlan;
 use project_form::form::form_util::{
     filter_code_lon_lat, handler_form_data, handler_form_header, parse,
 };
-use project_form::project::parse_check_project;
-use project_form::request::CsvParse;
+use project_form::import::geo::{read_geo, GeoOptions, GEOMETRY_FIELD};
+use project_form::import::xlsx::{read_xlsx, XlsxOptions};
+use project_form::project::{parse_check_project, parse_import_task};
+
+use crate::service::cts::form::get_form_template;
+use crate::service::cts::form_template::find_version;
+
+/// 导入任务时每批插入的数量
+const IMPORT_BATCH_SIZE: usize = 1000;
+/// 单条语句绑定参数的最大数量，数据库限制为65535
+const MAX_BIND_PARAMS: usize = 60000;
+/// xlsx、GeoJSON和Shapefile需要读取全部数据，文件的最大大小
+const MAX_FILE_SIZE: usize = 100 * 1024 * 1024;
+/// zip文件头
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+/// 导出时每次输出的数据大小
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+/// 导出数据通道缓存的数据块数量
+const EXPORT_CHANNEL_SIZE: usize = 8;
+/// 导出查询结果中经纬度、几何、编号和状态字段的别名
+const EXPORT_LON: &str = "x_lon";
+const EXPORT_LAT: &str = "x_lat";
+const EXPORT_GEOMETRY: &str = "x_geometry";
+const EXPORT_CODE: &str = "x_code";
+const EXPORT_STATUS: &str = "x_status";
 
 /// 根据项目编号查询数据
 /// @param id 编号
@@ -46,6 +98,7 @@ pub async fn get_by_id(id: String) -> Result<Option<ResponseProject>> {
                     FormTemplateColumn::Version,
                     FormTemplateColumn::Remark,
                     FormTemplateColumn::Title,
+                    FormTemplateColumn::Status,
                     FormTemplateColumn::CreatedAt,
                     FormTemplateColumn::UpdatedAt,
                 ])
@@ -91,180 +144,87 @@ pub async fn delete_by_id(id: String, force: bool) -> Result<String> {
     }
 }
 
+/// 添加项目，从文件存储中的任务文件导入任务
+/// @param data 项目对象，file为上传文件在存储中的路径
 pub async fn add_project(data: AddProjectDto) -> Result<String> {
-    let db = get_db().await;
-    //  读取文件属性
-    let file_path = data.file.clone();
-    let csv = Csv::read(&file_path)?;
-    // 查询表单数据
-    let form_template = FormTemplate::find_by_id(data.form_template_id.clone())
-        .one(&db)
-        .await?;
-    // 表单字符串
-    let form_str = match form_template {
-        None => {
-            bail!("表单不存在")
-        }
-        Some(form) => form.content.unwrap(),
-    };
-    // 解析表单数据，转换成表单对象
-    let form_template = parse(&form_str)?;
-    // 获取事务对象
-    let tx = db.begin().await?;
-    // 数据表名
-    let mut data_table_name = String::from("data_");
-    // 任务表名
-    let mut task_table_name = String::from("task_");
-    // 数据源uuid
-    let uuid = Uuid::new_v4().to_string().replace('-', "");
-    // 数据表和任务表拼接uuid
-    data_table_name.push_str(&uuid);
-    task_table_name.push_str(&uuid);
-    // 数据表字段，过滤掉无用字段
-    let fields: Vec<String> = form_template
-        .form
-        .questions
-        .into_iter()
-        .filter(|item| item.r#type != "SectionType")
-        .map(|item| item.name)
-        .collect();
-    // 根据字段列表，创建数据表sql
-    let data_sql = create_table_sql(&data_table_name, &fields, true);
-    // 根据字段列表，创建任务表sql
-    let task_sql = create_table_sql(&task_table_name, &csv.header, true);
-    // 创建数据表和任务表
-    tx.execute_unprepared(&data_sql).await?;
-    tx.execute_unprepared(&task_sql).await?;
-    // 处理表头，如果有跟公共字段重复的，重命名字段，并且添加公共表字段
-    let headers = handler_form_header(&csv.header);
-    // 创建任务字段列表
-    let fields_common_index = vec![data.task_code.clone(), data.task_lon.clone(), data.task_lat];
-    // 找出code、lon、lat位置列表
-    let index_common = filter_code_lon_lat(&headers, &fields_common_index);
-    // 收集插入数据sql
-    let mut insert_sqls = Vec::new();
-    // 遍历数据列表
-    for csv_datum in csv.data.iter() {
-        // 转换数据
-        let columns = handler_form_data(csv_datum, &index_common);
-        // 生成插入数据sql
-        let insert_sql = insert_data_sql(&task_table_name, &headers, &columns);
-        // 收集sql
-        insert_sqls.push(insert_sql);
-    }
-    // 遍历sql列表
-    for insert_sql in insert_sqls.iter() {
-        // 执行插入数据sql
-        tx.execute_unprepared(insert_sql).await?;
-    }
-    // 读入任务数据后赋值该变量
-    let total = csv.data.len();
-    // 创建项目对象
-    let current: ActiveModel = ActiveModel {
-        id: Set(Uuid::new_v4().to_string()),
-        name: Set(data.name),
-        code: Set(data.code),
-        form_template_id: Set(data.form_template_id),
-        data_table_name: Set(uuid),
-        total: Set(total as i32),
-        r#type: Set(data.r#type),
-        remark: Set(data.remark),
-        status: Set(data.status),
-        description: Set(data.description),
-        created_at: Set(Local::now().naive_local()),
-        updated_at: NotSet,
-        deleted_at: NotSet,
-    };
-    // 插入项目数据到数据库
-    let add_role = current.insert(&tx).await?;
-    // 提交事务
-    tx.commit().await?;
-    Ok(add_role.id)
+    check_key(&data.file)?;
+    let stream = get_storage()?.get_stream(&data.file).await?;
+    let file_name = data.file.clone();
+    import_project(data, &file_name, stream).await
 }
 
-/// 添加项目
-/// @param 项目对象
+/// 添加项目，multipart中的任务文件导入任务，支持csv和xlsx
+/// 项目属性需要放在文件之前，读取到文件时直接流式导入
+/// @param multipart 项目属性和任务文件
 pub async fn add(mut multipart: Multipart) -> Result<String> {
+    let mut fields = BTreeMap::new();
+    while let Some(field) = multipart.next_field().await? {
+        // 处理文件
+        if let Some(file_name) = field.file_name() {
+            let file_name = file_name.to_string();
+            // 检查项目属性是否正确
+            let data = parse_check_project(fields)?;
+            return import_project(data, &file_name, field).await;
+        }
+        let name = field.name().unwrap_or_default().to_string();
+        let data = field.bytes().await?;
+        fields.insert(name, data);
+    }
+    bail!("任务文件不能为空")
+}
+
+/// 导入项目，创建数据表和任务表，读取任务数据并分批插入任务表
+/// 有行错误时回滚事务，返回带行号的错误信息
+/// @param data 项目对象
+/// @param file_name 文件名，根据扩展名判断格式，没有扩展名时根据文件内容判断
+/// @param stream 文件数据流
+async fn import_project<S, E>(data: AddProjectDto, file_name: &str, stream: S) -> Result<String>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
     let db = get_db().await;
-    //  解析数据，如果有文件解析成csv
-    let (data, (csv_headers, csv_data)) = multipart.read_csv().await?;
-    // 检查项目属性是否正确
-    let data = parse_check_project(data)?;
     // 判断名是否为空
     if data.name.is_empty() {
         bail!("名称不能为空".to_string())
     }
-    // 查询表单数据
-    let form_template = FormTemplate::find_by_id(data.form_template_id.clone())
-        .one(&db)
-        .await?;
-    // 表单字符串
-    let form_str = match form_template {
-        None => {
-            bail!("表单不存在")
-        }
-        Some(form) => form.content.unwrap(),
-    };
+    // 查询表单发布的版本，项目固定使用该版本
+    let form_version = find_version(
+        &db,
+        &data.form_template_id,
+        data.form_template_version.as_deref(),
+    )
+    .await?;
     // 解析表单数据，转换成表单对象
-    let form_template = parse(&form_str)?;
+    let form_template = parse(&form_version.content)?;
     // 获取事务对象
     let tx = db.begin().await?;
-    // 数据表名
-    let mut data_table_name = String::from("data_");
-    // 任务表名
-    let mut task_table_name = String::from("task_");
-    // 数据源uuid
+    // 数据源uuid，数据表和任务表拼接uuid
     let uuid = Uuid::new_v4().to_string().replace('-', "");
-    // 数据表和任务表拼接uuid
-    data_table_name.push_str(&uuid);
-    task_table_name.push_str(&uuid);
-    // 数据表字段，过滤掉无用字段
-    let fields: Vec<String> = form_template
-        .form
-        .questions
+    let data_table_name = get_table_name(&uuid, true);
+    let task_table_name = get_table_name(&uuid, false);
+    // 数据表字段，过滤掉无用字段，根据题目类型设置字段类型
+    let fields: Vec<(String, ColumnType)> = form_template
+        .data_questions()
         .into_iter()
-        .filter(|item| item.r#type != "SectionType")
-        .map(|item| item.name)
+        .map(|item| (item.name.clone(), item.column_type()))
         .collect();
-    // 根据字段列表，创建数据表sql
+    // 根据字段列表，创建数据表
     let data_sql = create_table_sql(&data_table_name, &fields, true);
-    // 根据字段列表，创建任务表sql
-    let task_sql = create_table_sql(&task_table_name, &csv_headers, true);
-    // 创建数据表和任务表
     tx.execute_unprepared(&data_sql).await?;
-    tx.execute_unprepared(&task_sql).await?;
-    // 处理表头，如果有跟公共字段重复的，重命名字段，并且添加公共表字段
-    let headers = handler_form_header(&csv_headers);
-    // 创建任务字段列表
-    let fields_common_index = vec![data.task_code.clone(), data.task_lon.clone(), data.task_lat];
-    // 找出code、lon、lat位置列表
-    let index_common = filter_code_lon_lat(&headers, &fields_common_index);
-    // 收集插入数据sql
-    let mut insert_sqls = Vec::new();
-    // 遍历数据列表
-    for csv_datum in csv_data.iter() {
-        // 转换数据
-        let columns = handler_form_data(csv_datum, &index_common);
-        // 生成插入数据sql
-        let insert_sql = insert_data_sql(&task_table_name, &headers, &columns);
-        // 收集sql
-        insert_sqls.push(insert_sql);
-    }
-    // 遍历sql列表
-    for insert_sql in insert_sqls.iter() {
-        // 执行插入数据sql
-        tx.execute_unprepared(insert_sql).await?;
-    }
-    // 读入任务数据后赋值该变量
-    let total = csv_data.len();
+    // 读取任务文件，创建任务表并导入
+    let target = TaskTarget::Create(task_table_name);
+    let options = TaskFileOptions::from(&data);
+    let result = import_tasks(&tx, &target, &options, file_name, stream).await?;
     // 创建项目对象
     let current: ActiveModel = ActiveModel {
         id: Set(Uuid::new_v4().to_string()),
         name: Set(data.name),
         code: Set(data.code),
         form_template_id: Set(data.form_template_id),
+        form_template_version: Set(Some(form_version.version)),
         data_table_name: Set(uuid),
-        total: Set(total as i32),
+        total: Set(result.inserted as i32),
         r#type: Set(data.r#type),
         remark: Set(data.remark),
         status: Set(data.status),
@@ -280,18 +240,586 @@ pub async fn add(mut multipart: Multipart) -> Result<String> {
     Ok(add_role.id)
 }
 
+/// 已有项目导入任务，支持追加、按照任务编号更新和替换，导入后重新计算项目任务总数
+/// 导入参数需要放在文件之前，读取到文件时直接流式导入
+/// @param id 项目编号
+/// @param multipart 导入参数和任务文件
+pub async fn import(id: String, mut multipart: Multipart) -> Result<ImportTaskResult> {
+    let mut fields = BTreeMap::new();
+    while let Some(field) = multipart.next_field().await? {
+        // 处理文件
+        if let Some(file_name) = field.file_name() {
+            let file_name = file_name.to_string();
+            // 检查导入参数是否正确
+            let data = parse_import_task(fields)?;
+            return import_project_tasks(id, data, &file_name, field).await;
+        }
+        let name = field.name().unwrap_or_default().to_string();
+        let data = field.bytes().await?;
+        fields.insert(name, data);
+    }
+    bail!("任务文件不能为空")
+}
+
+/// 导入任务到已有项目的任务表，有行错误时回滚事务
+/// @param id 项目编号
+/// @param data 导入参数
+/// @param file_name 文件名
+/// @param stream 文件数据流
+async fn import_project_tasks<S, E>(
+    id: String,
+    data: ImportTaskDto,
+    file_name: &str,
+    stream: S,
+) -> Result<ImportTaskResult>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let db = get_db().await;
+    let project = match Project::find_by_id(id.clone())
+        .filter(ProjectColumn::DeletedAt.is_null())
+        .one(&db)
+        .await?
+    {
+        None => bail!("编号：{}，项目不存在", id),
+        Some(project) => project,
+    };
+    let task_table_name = get_table_name(&project.data_table_name, false);
+    let tx = db.begin().await?;
+    // 替换会删除全部任务，已经采集的数据使用任务编号关联，有数据时不能替换
+    if data.mode == ImportMode::Replace {
+        let mut cts_select = CtsSelect::table(&get_table_name(&project.data_table_name, true));
+        cts_select.defualt_filter();
+        if cts_select.select(&tx).await?.count(&tx).await? > 0 {
+            bail!("项目已经有采集的数据，不能替换任务，请使用追加或者更新方式导入")
+        }
+    }
+    let target = TaskTarget::Existing(task_table_name.clone(), data.mode, data.add_columns);
+    let options = TaskFileOptions::from(&data);
+    let mut result = import_tasks(&tx, &target, &options, file_name, stream).await?;
+    // 重新计算任务总数，不包括删除的任务
+    let mut cts_select = CtsSelect::table(&task_table_name);
+    cts_select.defualt_filter();
+    result.total = cts_select.select(&tx).await?.count(&tx).await? as usize;
+    let mut current: ActiveModel = project.into();
+    current.total = Set(result.total as i32);
+    current.updated_at = Set(Some(Local::now().naive_local()));
+    current.update(&tx).await?;
+    tx.commit().await?;
+    Ok(result)
+}
+
+/// 读取任务文件并分批导入任务表，有行错误时返回带行号的错误信息，事务由调用方提交
+/// @param tx 事务
+/// @param target 导入的任务表
+/// @param options 任务文件读取参数
+/// @param file_name 文件名，根据扩展名判断格式，没有扩展名时根据文件内容判断
+/// @param stream 文件数据流
+async fn import_tasks<C, S, E>(
+    tx: &C,
+    target: &TaskTarget,
+    options: &TaskFileOptions,
+    file_name: &str,
+    stream: S,
+) -> Result<ImportTaskResult>
+where
+    C: ConnectionTrait,
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    // 任务字段列表
+    let common_fields = [
+        options.task_code.clone(),
+        options.task_lon.clone(),
+        options.task_lat.clone(),
+    ];
+    let stream = stream.peekable();
+    futures::pin_mut!(stream);
+    let first = match stream.as_mut().peek().await {
+        Some(Ok(chunk)) => chunk.as_ref(),
+        _ => &[],
+    };
+    let mut reader = TaskReader::new(options, file_name, first)?;
+    let mut importer: Option<TaskImporter> = None;
+    let mut errors = ImportError::new();
+    loop {
+        let (records, is_end) = reader.next(stream.as_mut()).await?;
+        for record in records.into_iter() {
+            match (&mut importer, record) {
+                (Some(_), Err(error)) => errors.add(error),
+                (None, Err(error)) => bail!("任务文件表头错误：{}", error.message),
+                // 第一条记录为表头，创建任务表
+                (None, Ok(record)) => {
+                    let task_importer =
+                        TaskImporter::open(tx, target, &record.fields, &common_fields).await?;
+                    importer = Some(task_importer);
+                }
+                (Some(task_importer), Ok(record)) => {
+                    task_importer.push(tx, record, &mut errors).await?;
+                }
+            }
+            if errors.is_full() {
+                break;
+            }
+        }
+        if is_end || errors.is_full() {
+            break;
+        }
+    }
+    match importer {
+        None => bail!("任务文件不能为空"),
+        Some(mut task_importer) => {
+            if !errors.is_empty() {
+                // 事务未提交，已经插入的数据回滚
+                return Err(errors.into());
+            }
+            task_importer.flush(tx).await?;
+            Ok(task_importer.result)
+        }
+    }
+}
+
+/// 任务文件读取参数
+struct TaskFileOptions {
+    // code、lon、lat对应的表头
+    task_code: String,
+    task_lon: String,
+    task_lat: String,
+    encoding: Option<String>,
+    delimiter: Option<String>,
+    sheet: Option<String>,
+    header_row: Option<i32>,
+}
+
+impl From<&AddProjectDto> for TaskFileOptions {
+    fn from(value: &AddProjectDto) -> Self {
+        Self {
+            task_code: value.task_code.clone(),
+            task_lon: value.task_lon.clone(),
+            task_lat: value.task_lat.clone(),
+            encoding: value.encoding.clone(),
+            delimiter: value.delimiter.clone(),
+            sheet: value.sheet.clone(),
+            header_row: value.header_row,
+        }
+    }
+}
+
+impl From<&ImportTaskDto> for TaskFileOptions {
+    fn from(value: &ImportTaskDto) -> Self {
+        Self {
+            task_code: value.task_code.clone(),
+            task_lon: value.task_lon.clone(),
+            task_lat: value.task_lat.clone(),
+            encoding: value.encoding.clone(),
+            delimiter: value.delimiter.clone(),
+            sheet: value.sheet.clone(),
+            header_row: value.header_row,
+        }
+    }
+}
+
+/// 任务导入的目标表
+enum TaskTarget {
+    // 新建任务表
+    Create(String),
+    // 已有任务表，导入方式和是否添加任务表中没有的字段
+    Existing(String, ImportMode, bool),
+}
+
+/// 任务文件读取，csv流式读取，xlsx、GeoJSON和Shapefile需要随机访问，读取全部数据后解析
+enum TaskReader {
+    Csv(Box<CsvReader>),
+    Xlsx(Vec<u8>, XlsxOptions),
+    Geo(Vec<u8>, GeoOptions),
+}
+
+impl TaskReader {
+    /// 根据扩展名或者文件内容创建读取对象
+    /// @param data 任务文件读取参数
+    /// @param file_name 文件名
+    /// @param first 第一个数据块
+    fn new(data: &TaskFileOptions, file_name: &str, first: &[u8]) -> Result<Self> {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|item| item.to_str())
+            .map(|item| item.to_lowercase());
+        let is_zip = first.starts_with(ZIP_MAGIC);
+        let is_json = first
+            .iter()
+            .find(|item| !item.is_ascii_whitespace())
+            .is_some_and(|item| *item == b'{');
+        let mut geo = GeoOptions {
+            shapefile: false,
+            lon_field: data.task_lon.clone(),
+            lat_field: data.task_lat.clone(),
+        };
+        match extension.as_deref() {
+            Some("xlsx") => {}
+            Some("csv") | Some("txt") => return Self::csv(data),
+            Some("geojson") | Some("json") => return Ok(TaskReader::Geo(Vec::new(), geo)),
+            Some("zip") => {
+                geo.shapefile = true;
+                return Ok(TaskReader::Geo(Vec::new(), geo));
+            }
+            Some("shp") => bail!("Shapefile需要和dbf、prj文件一起压缩成zip文件上传"),
+            // xlsx和Shapefile压缩包都是zip格式，根据第一个文件名判断
+            _ if is_zip && is_shapefile_zip(first) => {
+                geo.shapefile = true;
+                return Ok(TaskReader::Geo(Vec::new(), geo));
+            }
+            _ if is_json => return Ok(TaskReader::Geo(Vec::new(), geo)),
+            _ if !is_zip => return Self::csv(data),
+            _ => {}
+        }
+        let header_row = match data.header_row {
+            None => None,
+            Some(row) if row > 0 => Some(row as usize),
+            Some(row) => bail!("表头行号：{}，必须大于0", row),
+        };
+        let options = XlsxOptions {
+            sheet: data.sheet.clone(),
+            header_row,
+        };
+        Ok(TaskReader::Xlsx(Vec::new(), options))
+    }
+
+    fn csv(data: &TaskFileOptions) -> Result<Self> {
+        // 编码和分隔符为空时根据文件内容检测
+        let options = CsvOptions::parse(data.encoding.as_deref(), data.delimiter.as_deref())?;
+        Ok(TaskReader::Csv(Box::new(CsvReader::new(options))))
+    }
+
+    /// 读取下一批记录，返回记录和是否读取结束
+    /// @param stream 文件数据流
+    async fn next<S, E>(
+        &mut self,
+        mut stream: Pin<&mut S>,
+    ) -> Result<(Vec<Result<CsvRecord, RowError>>, bool)>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        match self {
+            TaskReader::Csv(reader) => match stream.next().await {
+                Some(chunk) => Ok((reader.push(&chunk?), false)),
+                None => Ok((reader.finish(), true)),
+            },
+            TaskReader::Xlsx(buffer, options) => {
+                read_all(buffer, stream).await?;
+                let buffer = std::mem::take(buffer);
+                let options = options.clone();
+                let records =
+                    tokio::task::spawn_blocking(move || read_xlsx(buffer, &options)).await??;
+                Ok((records, true))
+            }
+            TaskReader::Geo(buffer, options) => {
+                read_all(buffer, stream).await?;
+                let buffer = std::mem::take(buffer);
+                let options = options.clone();
+                let records =
+                    tokio::task::spawn_blocking(move || read_geo(buffer, &options)).await??;
+                Ok((records, true))
+            }
+        }
+    }
+}
+
+/// 读取全部文件数据
+/// @param buffer 数据缓存
+/// @param stream 文件数据流
+async fn read_all<S, E>(buffer: &mut Vec<u8>, mut stream: Pin<&mut S>) -> Result<()>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk?);
+        if buffer.len() > MAX_FILE_SIZE {
+            bail!("文件不能超过{}MB", MAX_FILE_SIZE / 1024 / 1024)
+        }
+    }
+    Ok(())
+}
+
+/// zip第一个文件是否为Shapefile，本地文件头第26字节为文件名长度，第30字节开始为文件名
+/// @param first 第一个数据块
+fn is_shapefile_zip(first: &[u8]) -> bool {
+    let name_len = match first.get(26..28) {
+        None => return false,
+        Some(bytes) => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+    };
+    match first.get(30..30 + name_len) {
+        None => false,
+        Some(name) => {
+            let name = String::from_utf8_lossy(name).to_lowercase();
+            [".shp", ".shx", ".dbf", ".prj", ".cpg"]
+                .iter()
+                .any(|extension| name.ends_with(extension))
+        }
+    }
+}
+
+/// 任务数据导入，缓存一批数据后生成一条插入语句
+struct TaskImporter {
+    // 任务表结构
+    schema: TableSchema,
+    // 插入的字段，包括公共字段
+    headers: Vec<String>,
+    // csv表头数量
+    header_len: usize,
+    // code、lon、lat位置列表
+    index_common: Vec<usize>,
+    // 已有任务表的导入方式，新建任务表时为空
+    mode: Option<ImportMode>,
+    // 文件中已经出现的任务编号
+    codes: HashSet<String>,
+    // 待导入的任务编号和数据
+    batch: Vec<(String, Vec<Option<Value>>)>,
+    // 每批插入的数量
+    batch_size: usize,
+    // 导入结果
+    result: ImportTaskResult,
+}
+
+impl TaskImporter {
+    /// 根据csv表头打开任务表，新建任务表时字段全部使用文本类型
+    /// 已有任务表缺少字段时，设置添加字段才添加，替换时删除原有任务，调用方检查没有采集的数据
+    /// @param tx 事务
+    /// @param target 导入的任务表
+    /// @param csv_headers csv表头
+    /// @param common_fields code、lon、lat对应的表头
+    async fn open<C>(
+        tx: &C,
+        target: &TaskTarget,
+        csv_headers: &[String],
+        common_fields: &[String],
+    ) -> Result<Self>
+    where
+        C: ConnectionTrait,
+    {
+        // 处理表头，如果有跟公共字段重复的，重命名字段，并且添加公共表字段
+        let headers = handler_form_header(csv_headers);
+        // 任务表使用重命名后的表头，第一个字段为编号
+        let task_fields = &headers[1..=csv_headers.len()];
+        let (table_name, mode) = match target {
+            TaskTarget::Create(table_name) => {
+                let task_fields: Vec<(String, ColumnType)> = task_fields
+                    .iter()
+                    .map(|header| (header.clone(), ColumnType::Varchar))
+                    .collect();
+                let task_sql = create_table_sql(table_name, &task_fields, true);
+                tx.execute_unprepared(&task_sql).await?;
+                (table_name, None)
+            }
+            TaskTarget::Existing(table_name, mode, add_columns) => {
+                let schema = TableSchema::load(tx, table_name).await?;
+                let missing: Vec<&String> = task_fields
+                    .iter()
+                    .filter(|field| !schema.contains(field))
+                    .collect();
+                if !missing.is_empty() && !add_columns {
+                    let missing: Vec<&str> = missing.iter().map(|item| item.as_str()).collect();
+                    bail!(
+                        "任务表中没有字段：{}，需要添加字段时设置addColumns为true",
+                        missing.join("、")
+                    )
+                }
+                for field in missing.into_iter() {
+                    tx.execute_unprepared(&add_column_sql(table_name, field, ColumnType::Varchar))
+                        .await?;
+                }
+                if *mode == ImportMode::Replace {
+                    let statement = delete_all_sql(&schema)?;
+                    execute_with_history(tx, statement, table_name, HistoryAction::Delete, None)
+                        .await?;
+                }
+                (table_name, Some(*mode))
+            }
+        };
+        // 找出code、lon、lat位置列表
+        let index_common = filter_code_lon_lat(csv_headers, common_fields)?;
+        // 读取任务表结构，插入字段根据表结构校验
+        let schema = TableSchema::load(tx, table_name).await?;
+        // 绑定参数数量不能超过数据库限制
+        let batch_size = (MAX_BIND_PARAMS / headers.len()).clamp(1, IMPORT_BATCH_SIZE);
+        Ok(Self {
+            schema,
+            headers,
+            header_len: csv_headers.len(),
+            index_common,
+            mode,
+            codes: HashSet::new(),
+            batch: Vec::with_capacity(batch_size),
+            batch_size,
+            result: ImportTaskResult::default(),
+        })
+    }
+
+    /// 添加一行数据，达到批量数量时导入数据库，有错误后只校验不导入
+    /// 已有任务表导入时，文件中重复的任务编号跳过
+    /// @param tx 事务
+    /// @param record csv记录
+    /// @param errors 行错误
+    async fn push<C>(&mut self, tx: &C, record: CsvRecord, errors: &mut ImportError) -> Result<()>
+    where
+        C: ConnectionTrait,
+    {
+        if record.fields.len() != self.header_len {
+            let message = format!(
+                "列数：{}，与表头列数：{}，不一致",
+                record.fields.len(),
+                self.header_len
+            );
+            errors.add(RowError::new(record.line, message));
+            return Ok(());
+        }
+        if !errors.is_empty() {
+            self.batch.clear();
+            return Ok(());
+        }
+        // 任务编号为空时不判断重复
+        let code = record.fields[self.index_common[0] - 1].trim().to_string();
+        if self.mode.is_some() && !code.is_empty() && !self.codes.insert(code.clone()) {
+            self.result.skipped += 1;
+            return Ok(());
+        }
+        // 转换数据
+        let columns = handler_form_data(&record.fields, &self.index_common)
+            .iter()
+            .map(|item| item.value())
+            .collect();
+        self.batch.push((code, columns));
+        if self.batch.len() >= self.batch_size {
+            self.flush(tx).await?;
+        }
+        Ok(())
+    }
+
+    /// 导入缓存的数据，追加时任务编号已经存在的跳过，更新时任务编号已经存在的更新
+    /// @param tx 事务
+    async fn flush<C>(&mut self, tx: &C) -> Result<()>
+    where
+        C: ConnectionTrait,
+    {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut self.batch);
+        let existing = match self.mode {
+            Some(ImportMode::Append) | Some(ImportMode::Upsert) => {
+                self.find_codes(tx, &batch).await?
+            }
+            _ => HashMap::new(),
+        };
+        let mut rows = Vec::with_capacity(batch.len());
+        for (code, row) in batch.into_iter() {
+            match existing.get(&code) {
+                None => rows.push(row),
+                Some(id) if self.mode == Some(ImportMode::Upsert) => {
+                    let (fields, values) = self.update_values(row);
+                    let statement = update_row_sql(&self.schema, id, &fields, &values)?;
+                    let table_name = &self.schema.table_name;
+                    execute_with_history(tx, statement, table_name, HistoryAction::Update, None)
+                        .await?;
+                    self.result.updated += 1;
+                }
+                Some(_) => self.result.skipped += 1,
+            }
+        }
+        if !rows.is_empty() {
+            let insert_sql = insert_batch_sql(&self.schema, &self.headers, &rows)?;
+            let table_name = &self.schema.table_name;
+            execute_with_history(tx, insert_sql, table_name, HistoryAction::Insert, None).await?;
+            self.result.inserted += rows.len();
+        }
+        self.batch = Vec::with_capacity(self.batch_size);
+        Ok(())
+    }
+
+    /// 查询任务表中已经存在的任务编号，返回任务编号到任务id的映射
+    /// @param tx 事务
+    /// @param batch 待导入的数据
+    async fn find_codes<C>(
+        &self,
+        tx: &C,
+        batch: &[(String, Vec<Option<Value>>)],
+    ) -> Result<HashMap<String, String>>
+    where
+        C: ConnectionTrait,
+    {
+        let codes: Vec<serde_json::Value> = batch
+            .iter()
+            .filter(|(code, _)| !code.is_empty())
+            .map(|(code, _)| code.clone().into())
+            .collect();
+        if codes.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let id_field = FormCommonField::Id.to_string();
+        let code_field = FormCommonField::Code.to_string();
+        let mut cts_select = CtsSelect::table(&self.schema.table_name);
+        cts_select.columns(Some(vec![id_field.clone(), code_field.clone()]));
+        cts_select.filter(FilterTree::field(
+            &code_field,
+            FilterOp::In,
+            serde_json::Value::Array(codes),
+        ));
+        cts_select.defualt_filter();
+        let mut result = HashMap::new();
+        for item in cts_select.find().all(tx).await? {
+            if let (Some(id), Some(code)) = (
+                item.get(&id_field).and_then(|item| item.as_str()),
+                item.get(&code_field).and_then(|item| item.as_str()),
+            ) {
+                result.insert(code.to_string(), id.to_string());
+            }
+        }
+        Ok(result)
+    }
+
+    /// 更新的字段和数据，包括任务字段、经纬度和更新时间，不更新编号、状态和创建时间
+    /// @param row 插入的数据
+    fn update_values(&self, row: Vec<Option<Value>>) -> (Vec<String>, Vec<Option<Value>>) {
+        let lon = FormCommonField::Lon.to_string();
+        let lat = FormCommonField::Lat.to_string();
+        let mut fields = Vec::new();
+        let mut values = Vec::new();
+        for (index, (field, value)) in self.headers.iter().zip(row).enumerate() {
+            if (1..=self.header_len).contains(&index) || *field == lon || *field == lat {
+                fields.push(field.clone());
+                values.push(value);
+            }
+        }
+        fields.push(FormCommonField::UpdatedAt.to_string());
+        values.push(Some(Local::now().naive_local().into()));
+        (fields, values)
+    }
+}
+
 /// 更新项目信息
 /// @param update_project 待更新的表单对象
-pub async fn update(id: String, update_project: UpdateProjectDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_project: UpdateProjectDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
         bail!("编号不能为空".to_string())
     }
     // 查询表单信息
-    let result = Project::find_by_id(id).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = Project::find_by_id(id).lock_exclusive().one(&tx).await?;
     // 更新表单信息
     if let Some(current) = result {
+        let current_version = version(current.updated_at, current.created_at);
+        let response = ResponseProject::from(current.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
         let mut current: ActiveModel = current.into();
         // 名称是否为空
         if update_project.name.is_some() {
@@ -316,13 +844,235 @@ pub async fn update(id: String, update_project: UpdateProjectDto) -> Result<Stri
         // 更新时间
         current.updated_at = Set(Some(Local::now().naive_local()));
         // 更新数据
-        let update_data = current.update(&db).await?;
+        let update_data = current.update(&tx).await?;
+        tx.commit().await?;
         Ok(update_data.id)
     } else {
         bail!("项目数据不存在，无法更新".to_string())
     }
 }
 
+/// 项目数据表迁移到表单的发布版本，只新增和重命名字段，指定删除时才删除字段
+/// @param id 项目编号
+/// @param data 迁移参数，dry_run为true时只返回迁移计划
+pub async fn migrate(id: String, data: MigrateProjectDto) -> Result<MigratePlan> {
+    let db = get_db().await;
+    let result = Project::find_by_id(id.clone())
+        .filter(ProjectColumn::DeletedAt.is_null())
+        .one(&db)
+        .await?;
+    let project = match result {
+        None => bail!("编号：{}，项目不存在", id),
+        Some(project) => project,
+    };
+    let form_version = find_version(&db, &project.form_template_id, Some(&data.version)).await?;
+    let form_template = parse(&form_version.content)?;
+    let renames = data.renames.unwrap_or_default();
+    let drop_columns = data.drop_columns.unwrap_or(false);
+    // 迁移计划
+    let table_name = get_table_name(&project.data_table_name, true);
+    let schema = TableSchema::load(&db, &table_name).await?;
+    let mut plan = form_template.migrate_plan(&schema, &renames, drop_columns)?;
+    if data.dry_run.unwrap_or(false) {
+        return Ok(plan);
+    }
+    // 开启事务，执行ddl并更新项目的表单版本
+    let tx = db.begin().await?;
+    // 事务中重新读取表结构，避免计划执行前表结构被修改
+    let schema = TableSchema::load(&tx, &table_name).await?;
+    let current_plan = form_template.migrate_plan(&schema, &renames, drop_columns)?;
+    if current_plan.statements != plan.statements {
+        bail!("数据表：{}，结构已经变化，请重新迁移", table_name)
+    }
+    for statement in plan.statements.iter() {
+        tx.execute_unprepared(statement).await?;
+    }
+    let mut current: ActiveModel = project.into();
+    current.form_template_version = Set(Some(form_version.version));
+    current.updated_at = Set(Some(Local::now().naive_local()));
+    current.update(&tx).await?;
+    tx.commit().await?;
+    plan.executed = true;
+    Ok(plan)
+}
+
+/// 导出的文件
+pub struct ExportFile {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub body: Body,
+}
+
+/// 导出项目采集的数据，数据表和任务表根据编号关联，排除删除的数据，列标题使用题目标题
+/// kml、kmz和gpkg导出全部任务点，按照任务状态区分，没有采集的任务题目字段为空
+/// 查询结果逐行写入文件并流式输出，不在内存中生成整个文件
+/// @param id 项目编号
+/// @param data 导出参数
+pub async fn export(id: String, data: ExportProjectDto) -> Result<ExportFile> {
+    let format = ExportFormat::parse(data.format.as_deref())?;
+    let item_name = data.item_name.unwrap_or(false);
+    let db = get_db().await;
+    let project = match Project::find_by_id(id.clone())
+        .filter(ProjectColumn::DeletedAt.is_null())
+        .one(&db)
+        .await?
+    {
+        None => bail!("编号：{}，项目不存在", id),
+        Some(project) => project,
+    };
+    let form_template = get_form_template(&db, &project.data_table_name).await?;
+    let task_table_name = get_table_name(&project.data_table_name, false);
+    let data_table_name = get_table_name(&project.data_table_name, true);
+    let task_schema = TableSchema::load(&db, &task_table_name).await?;
+    let data_schema = TableSchema::load(&db, &data_table_name).await?;
+    // 导出的列，查询结果使用列序号作为别名
+    let mut columns = Vec::new();
+    let mut task_fields = Vec::new();
+    // 任务字段，不包括公共字段和几何字段
+    for field in task_schema.fields.iter() {
+        if FormCommonField::contains(&field.name) || field.name == GEOMETRY_FIELD {
+            continue;
+        }
+        task_fields.push((field.name.clone(), format!("c{}", columns.len())));
+        columns.push(ExportColumn {
+            title: field.name.clone(),
+            items: None,
+            numeric: [ColumnType::Numeric, ColumnType::Double]
+                .iter()
+                .any(|column_type| column_type.type_name() == field.base_type()),
+        });
+    }
+    let status = FormCommonField::Status.to_string();
+    task_fields.push((status.clone(), format!("c{}", columns.len())));
+    columns.push(ExportColumn {
+        title: status,
+        items: None,
+        numeric: true,
+    });
+    // 题目字段，数据表中没有的字段跳过
+    let mut data_fields = Vec::new();
+    for question in form_template.data_questions() {
+        if !data_schema.contains(&question.name) {
+            continue;
+        }
+        data_fields.push((question.name.clone(), format!("c{}", columns.len())));
+        columns.push(ExportColumn {
+            title: question.title.clone(),
+            items: question.items.clone(),
+            numeric: matches!(question.column_type(), ColumnType::Numeric | ColumnType::Double),
+        });
+    }
+    // 经纬度和几何字段用于生成几何，编号和状态用于kml地标名称和样式
+    task_fields.push((FormCommonField::Lon.to_string(), EXPORT_LON.to_string()));
+    task_fields.push((FormCommonField::Lat.to_string(), EXPORT_LAT.to_string()));
+    if task_schema.contains(GEOMETRY_FIELD) {
+        task_fields.push((GEOMETRY_FIELD.to_string(), EXPORT_GEOMETRY.to_string()));
+    }
+    task_fields.push((FormCommonField::Code.to_string(), EXPORT_CODE.to_string()));
+    task_fields.push((FormCommonField::Status.to_string(), EXPORT_STATUS.to_string()));
+    let statement = join_task_data_sql(
+        &task_schema,
+        &data_schema,
+        &task_fields,
+        &data_fields,
+        format.all_tasks(),
+    )?;
+    let exporter = Exporter::new(format, &project.name, &columns).await?;
+    let (mut sender, receiver) = mpsc::channel(EXPORT_CHANNEL_SIZE);
+    tokio::spawn(async move {
+        let result = export_rows(&db, statement, &columns, item_name, exporter, &mut sender).await;
+        if let Err(err) = result {
+            // 响应已经开始输出，只能中断数据流
+            error!("项目导出失败：{}", err);
+            let _ = sender.send(Err(io::Error::other(err.to_string()))).await;
+        }
+    });
+    Ok(ExportFile {
+        file_name: format!("{}.{}", project.name, format.extension()),
+        content_type: format.content_type(),
+        body: Body::from_stream(receiver),
+    })
+}
+
+/// 逐行读取查询结果写入导出文件，数据达到输出大小时发送
+/// @param db 数据库连接
+/// @param statement 查询语句
+/// @param columns 导出的列
+/// @param item_name 选择题是否导出选项名称
+/// @param exporter 导出文件
+/// @param sender 数据发送通道
+async fn export_rows(
+    db: &DatabaseConnection,
+    statement: Statement,
+    columns: &[ExportColumn],
+    item_name: bool,
+    mut exporter: Exporter,
+    sender: &mut mpsc::Sender<io::Result<Bytes>>,
+) -> Result<()> {
+    let mut stream = JsonValue::find_by_statement(statement).stream(db).await?;
+    while let Some(row) = stream.next().await {
+        let mut row = match row? {
+            JsonValue::Object(row) => row,
+            _ => continue,
+        };
+        let values = columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let value = row.remove(&format!("c{}", index)).unwrap_or_default();
+                column.convert(value, item_name)
+            })
+            .collect();
+        let point = match (
+            coordinate(row.get(EXPORT_LON)),
+            coordinate(row.get(EXPORT_LAT)),
+        ) {
+            (Some(lon), Some(lat)) => Some((lon, lat)),
+            _ => None,
+        };
+        let geometry = match row.get(EXPORT_GEOMETRY) {
+            Some(JsonValue::String(text)) => serde_json::from_str(text).ok(),
+            _ => None,
+        };
+        let code = match row.remove(EXPORT_CODE) {
+            Some(JsonValue::String(code)) => Some(code),
+            _ => None,
+        };
+        let status = row.get(EXPORT_STATUS).and_then(|status| status.as_i64());
+        exporter
+            .write(&ExportRow {
+                values,
+                point,
+                geometry,
+                code,
+                status,
+            })
+            .await?;
+        if exporter.buffered() >= EXPORT_CHUNK_SIZE {
+            sender.send(Ok(Bytes::from(exporter.take()))).await?;
+        }
+    }
+    match exporter.finish().await? {
+        ExportEnd::Bytes(bytes) => sender.send(Ok(Bytes::from(bytes))).await?,
+        ExportEnd::File(file) => {
+            let mut stream = ReaderStream::new(File::from_std(file));
+            while let Some(chunk) = stream.next().await {
+                sender.send(Ok(chunk?)).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 经纬度转换成数值，不是有效数值时为空
+pub(crate) fn coordinate(value: Option<&JsonValue>) -> Option<f64> {
+    match value {
+        Some(JsonValue::String(value)) => value.trim().parse().ok(),
+        Some(JsonValue::Number(value)) => value.as_f64(),
+        _ => None,
+    }
+}
+
 /// 查询项目列表
 /// @param data 类型SearchProjectDto
 pub async fn search(data: SearchProjectDto) -> Result<PageResult<ResponseProject>> {
@@ -0,0 +1,123 @@
+use std::cmp::Reverse;
+
+use anyhow::{bail, Result};
+use sea_orm::sea_query::{Alias, Expr, Func};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, FromQueryResult, JsonValue, QueryFilter,
+    Statement,
+};
+
+use common::db::form::{get_table_name, FormCommonField, TaskStatus};
+use common::db::schema::TableSchema;
+use common::db::{get_db, group_count_sql};
+use entity::project::{Column as ProjectColumn, Entity as Project};
+use models::dto::cts::request::project::ProjectStatsDto;
+use models::dto::cts::response::project::{ProjectStats, StatsCount};
+
+/// 提交日期格式
+const DAY_FORMAT: &str = "YYYY-MM-DD";
+
+/// 项目进度统计，任务表按照状态统计，数据表按照提交用户和提交日期统计
+/// 分组字段不为空时，任务表按照该字段统计任务数量和完成数量
+/// @param id 项目编号
+/// @param data 统计参数
+pub async fn project_stats(id: String, data: ProjectStatsDto) -> Result<ProjectStats> {
+    let db = get_db().await;
+    let project = match Project::find_by_id(id.clone())
+        .filter(ProjectColumn::DeletedAt.is_null())
+        .one(&db)
+        .await?
+    {
+        None => bail!("编号：{}，项目不存在", id),
+        Some(project) => project,
+    };
+    let task_table_name = get_table_name(&project.data_table_name, false);
+    let data_table_name = get_table_name(&project.data_table_name, true);
+    let task_schema = TableSchema::load(&db, &task_table_name).await?;
+    let data_schema = TableSchema::load(&db, &data_table_name).await?;
+    let status = Alias::new(FormCommonField::Status.to_string());
+    // 按照任务状态统计，已经提交的任务为已完成
+    let statement = group_count_sql(&task_schema, Expr::col(status.clone()).into(), None)?;
+    let by_status = find_counts(&db, statement).await?;
+    let total = by_status.iter().map(|item| item.count).sum();
+    let completed = by_status
+        .iter()
+        .filter(|item| match TaskStatus::from_value(item.key.as_i64()) {
+            Ok(status) => status.is_collected(),
+            Err(_) => false,
+        })
+        .map(|item| item.count)
+        .sum();
+    // 按照提交用户统计，数量多的在前
+    let user_id = Alias::new(FormCommonField::UserId.to_string());
+    let statement = group_count_sql(&data_schema, Expr::col(user_id).into(), None)?;
+    let mut by_user = find_counts(&db, statement).await?;
+    by_user.sort_by_key(|item| Reverse(item.count));
+    // 按照提交日期统计
+    let created_at = Alias::new(FormCommonField::CreatedAt.to_string());
+    let day = Func::cust(Alias::new("to_char"))
+        .arg(Expr::col(created_at))
+        .arg(DAY_FORMAT);
+    let statement = group_count_sql(&data_schema, day.into(), None)?;
+    let by_day = find_counts(&db, statement).await?;
+    // 按照任务字段分组统计
+    let by_group = match data.group_by {
+        Some(group_by) => {
+            task_schema.field(&group_by)?;
+            let collected = TaskStatus::ALL
+                .iter()
+                .filter(|status| status.is_collected())
+                .map(|status| status.value());
+            let completed = Expr::col(status).is_in(collected);
+            let statement = group_count_sql(
+                &task_schema,
+                Expr::col(Alias::new(&group_by)).into(),
+                Some(completed),
+            )?;
+            let mut result = find_counts(&db, statement).await?;
+            for item in result.iter_mut() {
+                item.completion = item
+                    .completed
+                    .map(|completed| completion(completed, item.count));
+            }
+            Some(result)
+        }
+        None => None,
+    };
+    Ok(ProjectStats {
+        total,
+        completed,
+        completion: completion(completed, total),
+        by_status,
+        by_user,
+        by_day,
+        by_group,
+    })
+}
+
+/// 查询分组统计结果
+/// @param db 数据库连接
+/// @param statement 分组统计语句
+async fn find_counts(db: &DatabaseConnection, statement: Statement) -> Result<Vec<StatsCount>> {
+    let rows = JsonValue::find_by_statement(statement).all(db).await?;
+    let result = rows
+        .into_iter()
+        .map(|mut row| StatsCount {
+            key: row.get_mut("key").map(JsonValue::take).unwrap_or_default(),
+            count: row["count"].as_u64().unwrap_or(0),
+            completed: row.get("completed").and_then(|item| item.as_u64()),
+            completion: None,
+        })
+        .collect();
+    Ok(result)
+}
+
+/// 完成百分比，保留两位小数
+/// @param completed 完成数量
+/// @param total 总数量
+fn completion(completed: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (completed as f64 * 10000.0 / total as f64).round() / 100.0
+}
@@ -0,0 +1,371 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, FromQueryResult, JsonValue,
+    QueryFilter,
+};
+use serde_json::Value;
+
+use common::db::form::{get_table_name, FormCommonField};
+use common::db::get_db;
+use common::db::schema::TableSchema;
+use common::db::select::CtsSelect;
+use common::db::sync::{changed_sql, cursor_sql, SyncCursor, SyncPosition};
+use common::db::version::{row_version, same_version};
+use common::db::workflow::TaskAction;
+use common::error::ConflictError;
+use entity::form_template::Entity as FormTemplate;
+use entity::project::{Column as ProjectColumn, Entity as Project, Model as ProjectModel};
+use models::dto::cts::request::form::{AddFormData, UpdateFormData};
+use models::dto::cts::request::sync::{SyncPullDto, SyncPushDto, SyncPushItem};
+use models::dto::cts::response::sync::{SyncForm, SyncPullResult, SyncPushResult, SyncPushStatus};
+use models::dto::sys::response::sys_user::ResponseUser;
+
+use crate::service::cts::form::{add_data, update_data};
+use crate::service::cts::form_template::find_version;
+use crate::service::cts::workflow::check_role;
+
+/// 每次同步默认返回的任务和数据数量
+const DEFAULT_PULL_LIMIT: u64 = 1000;
+
+/// 每次同步最多返回的任务和数据数量
+const MAX_PULL_LIMIT: u64 = 5000;
+
+/// 增量同步，返回上次同步后变化的表单、任务和数据，删除的任务和数据只返回编号
+/// 任务和数据每次最多返回limit条，还有数据时使用返回的游标继续同步，不能审核的角色只同步自己的任务和数据
+/// @param user 当前用户
+/// @param table_id 数据表编号
+/// @param data 同步参数
+pub async fn pull(
+    user: ResponseUser,
+    table_id: String,
+    data: SyncPullDto,
+) -> Result<SyncPullResult> {
+    let db = get_db().await;
+    let project = find_project(&db, &table_id).await?;
+    let cursor = match &data.cursor {
+        None => None,
+        Some(cursor) => Some(SyncCursor::parse(cursor)?),
+    };
+    let limit = data
+        .limit
+        .unwrap_or(DEFAULT_PULL_LIMIT)
+        .clamp(1, MAX_PULL_LIMIT);
+    let user_id = match check_role(&user, TaskAction::Review) {
+        Ok(_) => None,
+        Err(_) => Some(user.id.as_str()),
+    };
+    // 本次同步的截止时间，使用数据库时间并提前一段时间
+    let until = find_cursor(&db).await?;
+    let form = find_form(&db, &project, cursor.as_ref().map(|cursor| cursor.since())).await?;
+    let (tasks_since, data_since) = match &cursor {
+        None => (None, None),
+        Some(cursor) => (Some(&cursor.tasks), Some(&cursor.data)),
+    };
+    let task_table_name = get_table_name(&table_id, false);
+    let tasks = find_changed(&db, &task_table_name, tasks_since, until, user_id, limit).await?;
+    let data_table_name = get_table_name(&table_id, true);
+    let data = find_changed(&db, &data_table_name, data_since, until, user_id, limit).await?;
+    let cursor = SyncCursor {
+        tasks: tasks.position,
+        data: data.position,
+    };
+    Ok(SyncPullResult {
+        cursor: cursor.to_string(),
+        more: tasks.more || data.more,
+        form,
+        tasks: tasks.changed,
+        data: data.changed,
+        deleted_tasks: tasks.deleted,
+        deleted_data: data.deleted,
+    })
+}
+
+/// 提交离线数据，每条数据单独保存并返回处理结果
+/// 服务器数据和提交的数据相同时不重复保存，服务器数据在离线期间被修改时返回冲突
+/// @param user 当前用户
+/// @param table_id 数据表编号
+/// @param data 离线提交的数据
+pub async fn push(
+    user: ResponseUser,
+    table_id: String,
+    data: SyncPushDto,
+) -> Result<Vec<SyncPushResult>> {
+    let db = get_db().await;
+    find_project(&db, &table_id).await?;
+    let mut result = Vec::with_capacity(data.items.len());
+    for item in data.items.into_iter() {
+        let task_id = item.task_id.clone();
+        let item_result = match push_item(&db, &user, &table_id, item).await {
+            Ok(item_result) => item_result,
+            Err(error) => SyncPushResult {
+                task_id,
+                status: SyncPushStatus::Error,
+                version: None,
+                current: None,
+                message: Some(error.to_string()),
+            },
+        };
+        result.push(item_result);
+    }
+    Ok(result)
+}
+
+/// 处理一条离线提交
+async fn push_item(
+    db: &DatabaseConnection,
+    user: &ResponseUser,
+    table_id: &str,
+    item: SyncPushItem,
+) -> Result<SyncPushResult> {
+    let table_name = get_table_name(table_id, true);
+    let task_id = item.task_id.clone();
+    let response = |status, current: Option<Value>, message: Option<&str>| SyncPushResult {
+        task_id: task_id.clone(),
+        status,
+        version: current.as_ref().and_then(row_version),
+        current,
+        message: message.map(|message| message.to_string()),
+    };
+    // 查询服务器数据，包括删除的数据
+    let current = CtsSelect::table(&table_name)
+        .find_by_id(&item.task_id)
+        .one(db)
+        .await?;
+    match current {
+        None if item.base_version.is_some() => Ok(response(
+            SyncPushStatus::Conflict,
+            None,
+            Some("服务器数据已经删除"),
+        )),
+        None => {
+            let data = AddFormData {
+                task_id: item.task_id.clone(),
+                name: table_id.to_string(),
+                data: item.data,
+            };
+            add_data(user.clone(), data).await?;
+            let current = find_record(db, &table_name, &item.task_id).await?;
+            Ok(response(SyncPushStatus::Applied, current, None))
+        }
+        Some(current) => {
+            let deleted_at = current.get(FormCommonField::DeletedAt.to_string());
+            if deleted_at.is_some_and(|deleted_at| !deleted_at.is_null()) {
+                return Ok(response(
+                    SyncPushStatus::Conflict,
+                    Some(current),
+                    Some("服务器数据已经删除"),
+                ));
+            }
+            if same_data(&current, &item.data) {
+                return Ok(response(SyncPushStatus::Unchanged, Some(current), None));
+            }
+            let base_version = item.base_version.as_deref().unwrap_or_default();
+            let changed = match row_version(&current) {
+                Some(version) => !same_version(base_version, &version),
+                None => item.base_version.is_some(),
+            };
+            if changed {
+                return Ok(response(
+                    SyncPushStatus::Conflict,
+                    Some(current),
+                    Some("服务器数据在离线期间已经修改"),
+                ));
+            }
+            // 更新时在事务中再次检查版本，防止检查后其他用户修改数据
+            let data = UpdateFormData {
+                task_id: item.task_id.clone(),
+                name: table_id.to_string(),
+                data: item.data,
+                version: item.base_version.clone(),
+            };
+            if let Err(err) = update_data(user.clone(), data).await {
+                let conflict = err.downcast::<ConflictError>()?;
+                return Ok(response(
+                    SyncPushStatus::Conflict,
+                    Some(conflict.current),
+                    Some("服务器数据在离线期间已经修改"),
+                ));
+            }
+            let current = find_record(db, &table_name, &item.task_id).await?;
+            Ok(response(SyncPushStatus::Applied, current, None))
+        }
+    }
+}
+
+/// 查询本次同步游标
+async fn find_cursor(db: &DatabaseConnection) -> Result<NaiveDateTime> {
+    let result = db.query_one(cursor_sql()).await?;
+    match result {
+        None => bail!("查询同步游标失败"),
+        Some(row) => Ok(row.try_get("", "cursor")?),
+    }
+}
+
+/// 根据数据表编号查询项目
+async fn find_project(db: &DatabaseConnection, table_id: &str) -> Result<ProjectModel> {
+    let project = Project::find()
+        .filter(ProjectColumn::DataTableName.eq(table_id))
+        .filter(ProjectColumn::DeletedAt.is_null())
+        .one(db)
+        .await?;
+    match project {
+        None => bail!("数据表：{}，对应的项目不存在", table_id),
+        Some(project) => Ok(project),
+    }
+}
+
+/// 查询项目使用的表单，上次同步后没有变化时返回None
+/// 项目固定版本时使用版本内容，否则使用表单当前内容
+async fn find_form(
+    db: &DatabaseConnection,
+    project: &ProjectModel,
+    since: Option<NaiveDateTime>,
+) -> Result<Option<SyncForm>> {
+    let (version, content, changed_at) = match &project.form_template_version {
+        Some(version) => {
+            let form_version = find_version(db, &project.form_template_id, Some(version)).await?;
+            (
+                form_version.version,
+                form_version.content,
+                form_version.created_at,
+            )
+        }
+        None => {
+            let form_template = FormTemplate::find_by_id(project.form_template_id.clone())
+                .one(db)
+                .await?;
+            match form_template {
+                Some(form) if form.content.is_some() => (
+                    form.version,
+                    form.content.unwrap_or_default(),
+                    form.updated_at.unwrap_or(form.created_at),
+                ),
+                _ => bail!("表单不存在"),
+            }
+        }
+    };
+    // 项目修改后可能更换了表单版本
+    let changed_at = changed_at.max(project.updated_at.unwrap_or(project.created_at));
+    match since {
+        Some(since) if changed_at <= since => Ok(None),
+        _ => Ok(Some(SyncForm { version, content })),
+    }
+}
+
+/// 一张表的增量同步结果
+struct Changed {
+    // 新增和修改的数据
+    changed: Vec<Value>,
+    // 删除的数据编号
+    deleted: Vec<String>,
+    // 下次同步位置
+    position: SyncPosition,
+    // 是否还有数据
+    more: bool,
+}
+
+/// 查询同步位置之后变化的数据，删除的数据只返回编号
+/// 多查询一条判断是否还有数据，还有数据时下次从最后一条数据继续，否则从本次游标继续
+/// @param db 数据库连接
+/// @param table_name 表名
+/// @param since 上次同步位置
+/// @param until 本次同步游标
+/// @param user_id 只查询该用户的数据，为空时查询全部数据
+/// @param limit 查询数量
+async fn find_changed(
+    db: &DatabaseConnection,
+    table_name: &str,
+    since: Option<&SyncPosition>,
+    until: NaiveDateTime,
+    user_id: Option<&str>,
+    limit: u64,
+) -> Result<Changed> {
+    let schema = TableSchema::load(db, table_name).await?;
+    let statement = changed_sql(&schema, since, until, user_id, limit + 1)?;
+    let mut rows = JsonValue::find_by_statement(statement).all(db).await?;
+    let more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+    let deleted_at = FormCommonField::DeletedAt.to_string();
+    let id = FormCommonField::Id.to_string();
+    let position = match rows.last() {
+        Some(row) if more => SyncPosition {
+            time: change_time(row)?,
+            id: row
+                .get(&id)
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string()),
+        },
+        _ => SyncPosition {
+            time: until,
+            id: None,
+        },
+    };
+    let mut changed = Vec::new();
+    let mut deleted = Vec::new();
+    for row in rows.into_iter() {
+        match row.get(&deleted_at) {
+            Some(value) if !value.is_null() => {
+                if let Some(id) = row.get(&id).and_then(|id| id.as_str()) {
+                    deleted.push(id.to_string());
+                }
+            }
+            _ => changed.push(row),
+        }
+    }
+    Ok(Changed {
+        changed,
+        deleted,
+        position,
+        more,
+    })
+}
+
+/// 数据最后变更时间，取创建、更新和删除时间中最大的值
+fn change_time(row: &Value) -> Result<NaiveDateTime> {
+    let mut result = None;
+    for field in [
+        FormCommonField::CreatedAt,
+        FormCommonField::UpdatedAt,
+        FormCommonField::DeletedAt,
+    ] {
+        let time = match row.get(field.to_string()) {
+            Some(value) if !value.is_null() => serde_json::from_value(value.clone())?,
+            _ => continue,
+        };
+        result = result.max(Some(time));
+    }
+    match result {
+        None => bail!("数据没有变更时间"),
+        Some(time) => Ok(time),
+    }
+}
+
+/// 根据编号查询数据，包括删除的数据
+async fn find_record(db: &DatabaseConnection, table_name: &str, id: &str) -> Result<Option<Value>> {
+    CtsSelect::table(table_name).find_by_id(id).one(db).await
+}
+
+/// 提交的字段值是否和服务器数据全部相同，数值按照数值比较
+fn same_data(current: &Value, data: &Value) -> bool {
+    let data = match data {
+        Value::Object(data) => data,
+        _ => return false,
+    };
+    data.iter().all(|(key, value)| match current.get(key) {
+        Some(current) => same_value(current, value),
+        None => false,
+    })
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    if a == b {
+        return true;
+    }
+    let number = |value: &Value| match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    matches!((number(a), number(b)), (Some(a), Some(b)) if a == b)
+}
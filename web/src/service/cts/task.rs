@@ -1,14 +1,33 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
 use anyhow::{bail, Result};
-use sea_orm::PaginatorTrait;
+use chrono::Local;
+use sea_orm::sea_query::Cond;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, TransactionTrait,
+};
 use serde_json::Value;
 
-use common::db::form::get_table_name;
-use common::db::get_db;
+use common::db::filter::{FilterOp, FilterTree};
+use common::db::form::{get_table_name, FormCommonField};
+use common::db::history::{execute_with_history, HistoryAction};
+use common::db::schema::TableSchema;
 use common::db::select::CtsSelect;
-use models::dto::cts::request::task::SearchTask;
+use common::db::workflow::TaskAction;
+use common::db::{get_db, update_where_sql};
+use entity::sys_user::{Column as SysUserColumn, Entity as SysUser};
+use models::dto::cts::request::task::{AssignTaskDto, SearchTask, SplitTaskDto};
+use models::dto::cts::response::task::AssignCount;
+use models::dto::sys::response::sys_user::ResponseUser;
 use models::dto::{handler_page, PageResult};
 
+use crate::service::cts::project::coordinate;
+use crate::service::cts::workflow::{apply_all, check_role};
+
 const IS_DATA: bool = false;
+/// 平均分配任务时，每条语句更新的任务数量
+const ASSIGN_BATCH_SIZE: usize = 1000;
 
 /// 根据任务编号查询数据
 /// @param id 编号
@@ -23,8 +42,14 @@ pub async fn get_by_id(table_id: String, id: String) -> Result<Option<Value>> {
 }
 
 /// 根据任务编号删除数据
+/// @param user_id 操作用户编号
 /// @param id 编号
-pub async fn delete_by_id(table_id: String, id: String, force: bool) -> Result<String> {
+pub async fn delete_by_id(
+    user_id: String,
+    table_id: String,
+    id: String,
+    force: bool,
+) -> Result<String> {
     let db = get_db().await;
     // 查询数据是否存在
     let table_name = get_table_name(&table_id, IS_DATA);
@@ -34,10 +59,9 @@ pub async fn delete_by_id(table_id: String, id: String, force: bool) -> Result<S
         .await?;
     if result.is_some() {
         // 判断是否强制删除，如果是删除数据，如果不是更新删除字段
-        let _ = CtsSelect::table(&table_name)
-            .delete_by_id(&id, force)
-            .execute(&db)
-            .await?;
+        let mut cts_select = CtsSelect::table(&table_name);
+        cts_select.user(&user_id);
+        let _ = cts_select.delete_by_id(&id, force).execute(&db).await?;
         Ok(id)
     } else {
         bail!("无法删除，数据不存在")
@@ -45,13 +69,16 @@ pub async fn delete_by_id(table_id: String, id: String, force: bool) -> Result<S
 }
 
 /// 添加任务
+/// @param user_id 操作用户编号
 /// @param data任务对象
-pub async fn add(table_id: String, data: Value) -> Result<String> {
+pub async fn add(user_id: String, table_id: String, data: Value) -> Result<String> {
     // 获取数据库连接
     let db = get_db().await;
     let table_name = get_table_name(&table_id, IS_DATA);
     let mut data_id = String::from("0");
-    let _ = CtsSelect::table(&table_name)
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.user(&user_id);
+    let _ = cts_select
         .add(data, |id| data_id.clone_from(id))?
         .execute(&db)
         .await?;
@@ -60,8 +87,16 @@ pub async fn add(table_id: String, data: Value) -> Result<String> {
 }
 
 /// 更新任务信息
+/// @param user_id 操作用户编号
 /// @param data 任务对象
-pub async fn update(table_id: String, id: String, data: Value) -> Result<String> {
+/// @param version 读取任务时的版本，None不检查
+pub async fn update(
+    user_id: String,
+    table_id: String,
+    id: String,
+    data: Value,
+    version: Option<String>,
+) -> Result<String> {
     // 获取数据库连接
     let db = get_db().await;
     // 查询是否有数据
@@ -75,10 +110,13 @@ pub async fn update(table_id: String, id: String, data: Value) -> Result<String>
             bail!("数据不存在，无法更新")
         }
         Some(_) => {
-            let _ = CtsSelect::table(&table_name)
-                .update(&id, data)?
-                .execute(&db)
-                .await?;
+            let mut cts_select = CtsSelect::table(&table_name);
+            cts_select.user(&user_id);
+            cts_select.version(version);
+            // 检查版本和更新数据在同一个事务中执行
+            let tx = db.begin().await?;
+            let _ = cts_select.update(&id, data)?.execute(&tx).await?;
+            tx.commit().await?;
             Ok(id)
         }
     }
@@ -93,24 +131,22 @@ pub async fn search(table_id: String, data: SearchTask) -> Result<PageResult<Val
     cts_select.columns(data.fields);
     // 设置查询条件
     if let Some(wheres) = data.wheres {
-        for where_str in wheres.iter() {
-            cts_select.filter(where_str);
-        }
+        cts_select.filter(wheres);
     }
     cts_select.defualt_filter();
     // 设置排序
     if let Some(order_bys) = data.orders {
         for order_by in order_bys.iter() {
-            cts_select.order_by(order_by);
+            cts_select.order_by(order_by)?;
         }
     }
-    let select = cts_select.select();
+    let select = cts_select.select(&db).await?;
     // 查询数据数量
     let total = select.count(&db).await?;
     let (page_no, page_size) = handler_page(data.page);
     // 创建查询对象
-    cts_select.order_by("status desc");
-    let select = cts_select.select();
+    cts_select.order_by("status desc")?;
+    let select = cts_select.select(&db).await?;
     // 分页对象
     let paginate = select.paginate(&db, page_size);
     // 页数
@@ -125,3 +161,260 @@ pub async fn search(table_id: String, data: SearchTask) -> Result<PageResult<Val
     let result = PageResult::new(list, total, pages, page_no);
     Ok(result)
 }
+
+/// 查询分配给当前用户的任务
+/// @param table_id 任务表编号
+/// @param user_id 当前用户编号
+/// @param data 类型 SearchTask
+pub async fn search_mine(
+    table_id: String,
+    user_id: String,
+    mut data: SearchTask,
+) -> Result<PageResult<Value>> {
+    let user_filter = FilterTree::field(
+        &FormCommonField::UserId.to_string(),
+        FilterOp::Eq,
+        Value::String(user_id),
+    );
+    data.wheres = Some(match data.wheres {
+        Some(wheres) => FilterTree::And {
+            and: vec![wheres, user_filter],
+        },
+        None => user_filter,
+    });
+    search(table_id, data).await
+}
+
+/// 分配任务，根据任务编号或者查询条件选择任务，用户编号为空时取消分配
+/// 只分配未分配和已分配的任务，未分配的任务流转为已分配，取消分配时已分配的任务流转为未分配
+/// @param user 当前用户
+/// @param table_id 任务表编号
+/// @param data 分配参数
+/// return 分配的任务数量
+pub async fn assign(user: ResponseUser, table_id: String, data: AssignTaskDto) -> Result<u64> {
+    let action = match data.user_id {
+        Some(_) => TaskAction::Assign,
+        None => TaskAction::Unassign,
+    };
+    check_role(&user, action)?;
+    let db = get_db().await;
+    let table_name = get_table_name(&table_id, IS_DATA);
+    let schema = TableSchema::load(&db, &table_name).await?;
+    if data.ids.is_none() && data.wheres.is_none() {
+        bail!("请指定分配的任务编号或者查询条件")
+    }
+    if let Some(user_id) = &data.user_id {
+        check_users(&db, std::slice::from_ref(user_id)).await?;
+    }
+    // 先根据查询条件锁定任务，流转状态和分配用户都使用锁定的任务编号
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.columns(Some(vec![FormCommonField::Id.to_string()]));
+    if let Some(ids) = data.ids {
+        cts_select.filter(ids_filter(ids));
+    }
+    if let Some(wheres) = data.wheres {
+        cts_select.filter(wheres);
+    }
+    cts_select.filter(action.status_filter());
+    cts_select.defualt_filter();
+    cts_select.lock();
+    let tx = db.begin().await?;
+    let ids = task_ids(&cts_select.find().all(&tx).await?);
+    assign_ids(&tx, &schema, &table_id, action, &user.id, data.user_id, &ids).await?;
+    tx.commit().await?;
+    Ok(ids.len() as u64)
+}
+
+/// 将任务平均分配给多个用户，任务按照经度和纬度排序后分成连续的几段，每个用户一段
+/// 相邻的任务分配给同一个用户，没有经纬度的任务排在最后，只分配未分配和已分配的任务
+/// @param user 当前用户
+/// @param table_id 任务表编号
+/// @param data 分配参数
+pub async fn split(
+    user: ResponseUser,
+    table_id: String,
+    data: SplitTaskDto,
+) -> Result<Vec<AssignCount>> {
+    check_role(&user, TaskAction::Assign)?;
+    let db = get_db().await;
+    let table_name = get_table_name(&table_id, IS_DATA);
+    let schema = TableSchema::load(&db, &table_name).await?;
+    let mut user_ids: Vec<String> = Vec::new();
+    for user_id in data.user_ids.into_iter() {
+        if !user_ids.contains(&user_id) {
+            user_ids.push(user_id);
+        }
+    }
+    if user_ids.is_empty() {
+        bail!("请指定分配任务的用户")
+    }
+    check_users(&db, &user_ids).await?;
+    // 查询需要分配的任务编号和经纬度
+    let id = FormCommonField::Id.to_string();
+    let (lon, lat) = (
+        FormCommonField::Lon.to_string(),
+        FormCommonField::Lat.to_string(),
+    );
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.columns(Some(vec![id.clone(), lon.clone(), lat.clone()]));
+    if let Some(wheres) = data.wheres {
+        cts_select.filter(wheres);
+    }
+    if data.unassigned.unwrap_or(false) {
+        cts_select.filter(FilterTree::field(
+            &FormCommonField::UserId.to_string(),
+            FilterOp::IsNull,
+            Value::Bool(true),
+        ));
+    }
+    cts_select.filter(TaskAction::Assign.status_filter());
+    cts_select.defualt_filter();
+    cts_select.lock();
+    let tx = db.begin().await?;
+    let rows = cts_select.find().all(&tx).await?;
+    let mut tasks: Vec<(String, Option<(f64, f64)>)> = rows
+        .iter()
+        .filter_map(|row| {
+            let task_id = row.get(&id)?.as_str()?.to_string();
+            let point = match (coordinate(row.get(&lon)), coordinate(row.get(&lat))) {
+                (Some(lon), Some(lat)) => Some((lon, lat)),
+                _ => None,
+            };
+            Some((task_id, point))
+        })
+        .collect();
+    tasks.sort_by(|a, b| match (a.1, b.1) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    // 每个用户的任务数量相差不超过1
+    let (size, remainder) = (tasks.len() / user_ids.len(), tasks.len() % user_ids.len());
+    let mut tasks = tasks.into_iter().map(|(task_id, _)| task_id);
+    let mut result = Vec::with_capacity(user_ids.len());
+    for (index, user_id) in user_ids.into_iter().enumerate() {
+        let count = if index < remainder { size + 1 } else { size };
+        let ids: Vec<String> = tasks.by_ref().take(count).collect();
+        let assign_user_id = Some(user_id.clone());
+        let action = TaskAction::Assign;
+        assign_ids(&tx, &schema, &table_id, action, &user.id, assign_user_id, &ids).await?;
+        result.push(AssignCount { user_id, count });
+    }
+    tx.commit().await?;
+    Ok(result)
+}
+
+/// 分配锁定的任务，先流转状态再更新分配用户
+/// @param db 数据库事务
+/// @param schema 任务表结构
+/// @param table_id 任务表编号
+/// @param action 分配或者取消分配
+/// @param operator_id 操作用户编号
+/// @param user_id 分配的用户编号，取消分配时为空
+/// @param ids 任务编号
+async fn assign_ids<C>(
+    db: &C,
+    schema: &TableSchema,
+    table_id: &str,
+    action: TaskAction,
+    operator_id: &str,
+    user_id: Option<String>,
+    ids: &[String],
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    for chunk in ids.chunks(ASSIGN_BATCH_SIZE) {
+        let condition = ids_filter(chunk.to_vec()).condition(schema)?;
+        apply_all(db, schema, table_id, action, operator_id, condition.clone()).await?;
+        let statement = assign_sql(schema, user_id.clone(), condition)?;
+        let table_name = &schema.table_name;
+        execute_with_history(db, statement, table_name, HistoryAction::Update, Some(operator_id))
+            .await?;
+    }
+    Ok(())
+}
+
+/// 查询结果中的任务编号
+fn task_ids(rows: &[Value]) -> Vec<String> {
+    let id = FormCommonField::Id.to_string();
+    rows.iter()
+        .filter_map(|row| row.get(&id)?.as_str())
+        .map(|id| id.to_string())
+        .collect()
+}
+
+/// 检查任务是否可以由当前用户提交，分配给其他用户的任务不能提交
+/// @param db 数据库连接
+/// @param table_id 任务表编号
+/// @param task_id 任务编号
+/// @param user_id 当前用户编号
+pub(crate) async fn check_task_user<C>(
+    db: &C,
+    table_id: &str,
+    task_id: &str,
+    user_id: &str,
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let table_name = get_table_name(table_id, IS_DATA);
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.defualt_filter();
+    let task = match cts_select.find_by_id(task_id).one(db).await? {
+        None => bail!("任务：{}，不存在", task_id),
+        Some(task) => task,
+    };
+    match task.get(FormCommonField::UserId.to_string()) {
+        Some(Value::String(assigned)) if !assigned.is_empty() && assigned != user_id => {
+            bail!("任务：{}，已经分配给其他用户", task_id)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 检查用户是否存在
+async fn check_users<C>(db: &C, user_ids: &[String]) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let ids: HashSet<&String> = user_ids.iter().collect();
+    let users = SysUser::find()
+        .filter(SysUserColumn::Id.is_in(ids.iter().map(|id| id.as_str())))
+        .filter(SysUserColumn::DeletedAt.is_null())
+        .all(db)
+        .await?;
+    for id in ids {
+        if !users.iter().any(|user| &user.id == id) {
+            bail!("用户：{}，不存在", id)
+        }
+    }
+    Ok(())
+}
+
+/// 任务编号条件
+fn ids_filter(ids: Vec<String>) -> FilterTree {
+    FilterTree::field(
+        &FormCommonField::Id.to_string(),
+        FilterOp::In,
+        Value::Array(ids.into_iter().map(Value::String).collect()),
+    )
+}
+
+/// 更新任务分配用户的sql
+fn assign_sql(
+    schema: &TableSchema,
+    user_id: Option<String>,
+    condition: Cond,
+) -> Result<sea_orm::Statement> {
+    let fields = [
+        FormCommonField::UserId.to_string(),
+        FormCommonField::UpdatedAt.to_string(),
+    ];
+    let row = [
+        user_id.map(|user_id| user_id.into()),
+        Some(Local::now().naive_local().into()),
+    ];
+    update_where_sql(schema, &fields, &row, condition)
+}
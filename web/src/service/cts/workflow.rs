@@ -0,0 +1,238 @@
+use anyhow::{bail, Result};
+use chrono::Local;
+use sea_orm::sea_query::Cond;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use common::db::form::{get_table_name, FormCommonField, TaskStatus};
+use common::db::get_db;
+use common::db::history::{execute_with_history, HistoryAction};
+use common::db::schema::TableSchema;
+use common::db::select::CtsSelect;
+use common::db::workflow::{status_update_sql, TaskAction};
+use entity::task_transition::{
+    ActiveModel as TransitionActiveModel, Column as TransitionColumn, Entity as TaskTransition,
+};
+use models::dto::cts::request::task::TransitionTaskDto;
+use models::dto::cts::response::task::ResponseTaskTransition;
+use models::dto::sys::response::sys_user::ResponseUser;
+
+use crate::service::cts::task::check_task_user;
+
+/// 批量记录流转时，每条语句插入的记录数量
+const RECORD_BATCH_SIZE: usize = 1000;
+
+/// 执行任务流转，检查角色权限和审核意见
+/// @param user 当前用户
+/// @param table_id 任务表编号
+/// @param task_id 任务编号
+/// @param data 流转参数
+/// return 流转后的状态值
+pub async fn transition(
+    user: ResponseUser,
+    table_id: String,
+    task_id: String,
+    data: TransitionTaskDto,
+) -> Result<i32> {
+    check_role(&user, data.action)?;
+    let comment = data.comment.filter(|comment| !comment.trim().is_empty());
+    if data.action.requires_comment() && comment.is_none() {
+        bail!("执行：{}，必须填写审核意见", data.action)
+    }
+    let db = get_db().await;
+    let tx = db.begin().await?;
+    let status = apply(&tx, &table_id, &task_id, data.action, &user.id, comment).await?;
+    tx.commit().await?;
+    Ok(status.value())
+}
+
+/// 查询任务的流转记录，按照时间排序
+/// @param table_id 任务表编号
+/// @param task_id 任务编号
+pub async fn transitions(table_id: String, task_id: String) -> Result<Vec<ResponseTaskTransition>> {
+    let db = get_db().await;
+    let result = TaskTransition::find()
+        .filter(TransitionColumn::TableId.eq(table_id))
+        .filter(TransitionColumn::TaskId.eq(task_id))
+        .order_by_asc(TransitionColumn::CreatedAt)
+        .all(&db)
+        .await?
+        .into_iter()
+        .map(ResponseTaskTransition::from)
+        .collect();
+    Ok(result)
+}
+
+/// 检查当前用户的角色是否可以执行流转动作
+/// @param user 当前用户
+/// @param action 流转动作
+pub(crate) fn check_role(user: &ResponseUser, action: TaskAction) -> Result<()> {
+    let (role_id, role_name) = match &user.role {
+        Some(role) => (role.id.as_str(), role.name.as_str()),
+        None => ("", ""),
+    };
+    if !action.allowed(role_id, role_name) {
+        bail!("当前角色不能执行：{}", action)
+    }
+    Ok(())
+}
+
+/// 检查当前用户是否可以修改任务的数据，可以审核的角色可以修改所有数据
+/// 其他角色需要可以提交，只能修改分配给自己的任务，审核中和审核通过的任务不能修改
+/// @param db 数据库事务
+/// @param user 当前用户
+/// @param table_id 任务表编号
+/// @param task_id 任务编号
+/// return 当前用户的角色是否可以审核
+pub(crate) async fn check_edit<C>(
+    db: &C,
+    user: &ResponseUser,
+    table_id: &str,
+    task_id: &str,
+) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    if check_role(user, TaskAction::Review).is_ok() {
+        return Ok(true);
+    }
+    check_role(user, TaskAction::Submit)?;
+    check_task_user(db, table_id, task_id, &user.id).await?;
+    let table_name = get_table_name(table_id, false);
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.defualt_filter();
+    cts_select.lock();
+    let task = match cts_select.find_by_id(task_id).one(db).await? {
+        None => bail!("任务：{}，不存在", task_id),
+        Some(task) => task,
+    };
+    let status = task
+        .get(FormCommonField::Status.to_string())
+        .and_then(|status| status.as_i64());
+    match TaskStatus::from_value(status)? {
+        TaskStatus::UnderReview | TaskStatus::Approved => {
+            bail!("任务：{}，正在审核或者已经审核通过，不能修改", task_id)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// 流转单个任务，更新任务状态并记录流转
+/// 读取任务时锁定任务，同时流转同一个任务时依次执行，后执行的使用流转后的状态
+/// @param db 数据库事务
+/// @param table_id 任务表编号
+/// @param task_id 任务编号
+/// @param action 流转动作
+/// @param user_id 操作用户编号
+/// @param comment 审核意见
+pub(crate) async fn apply<C>(
+    db: &C,
+    table_id: &str,
+    task_id: &str,
+    action: TaskAction,
+    user_id: &str,
+    comment: Option<String>,
+) -> Result<TaskStatus>
+where
+    C: ConnectionTrait,
+{
+    let table_name = get_table_name(table_id, false);
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.defualt_filter();
+    cts_select.lock();
+    let task = match cts_select.find_by_id(task_id).one(db).await? {
+        None => bail!("任务：{}，不存在", task_id),
+        Some(task) => task,
+    };
+    let status = task
+        .get(FormCommonField::Status.to_string())
+        .and_then(|status| status.as_i64());
+    let from = TaskStatus::from_value(status)?;
+    let to = action.next(from)?;
+    let mut map_value = Map::new();
+    map_value.insert(
+        FormCommonField::Status.to_string(),
+        Value::Number(to.value().into()),
+    );
+    let mut cts_select = CtsSelect::table(&table_name);
+    cts_select.user(user_id);
+    let _ = cts_select
+        .update(task_id, Value::Object(map_value))?
+        .execute(db)
+        .await?;
+    let record = new_record(table_id, task_id, action, from, to, user_id, comment);
+    TaskTransition::insert(record).exec(db).await?;
+    Ok(to)
+}
+
+/// 批量流转满足条件的任务，不能执行该动作或者状态不变的任务不流转
+/// @param db 数据库连接
+/// @param schema 任务表结构
+/// @param table_id 任务表编号
+/// @param action 流转动作
+/// @param user_id 操作用户编号
+/// @param condition 查询条件
+/// return 流转的任务数量
+pub(crate) async fn apply_all<C>(
+    db: &C,
+    schema: &TableSchema,
+    table_id: &str,
+    action: TaskAction,
+    user_id: &str,
+    condition: Cond,
+) -> Result<usize>
+where
+    C: ConnectionTrait,
+{
+    let mut count = 0;
+    for from in TaskStatus::ALL {
+        let to = match action.next(from) {
+            Ok(to) if to != from => to,
+            _ => continue,
+        };
+        let statement = status_update_sql(schema, from, to, condition.clone())?;
+        let ids = execute_with_history(
+            db,
+            statement,
+            &schema.table_name,
+            HistoryAction::Update,
+            Some(user_id),
+        )
+        .await?;
+        count += ids.len();
+        for chunk in ids.chunks(RECORD_BATCH_SIZE) {
+            let records = chunk
+                .iter()
+                .map(|task_id| new_record(table_id, task_id, action, from, to, user_id, None));
+            TaskTransition::insert_many(records).exec(db).await?;
+        }
+    }
+    Ok(count)
+}
+
+/// 创建流转记录
+fn new_record(
+    table_id: &str,
+    task_id: &str,
+    action: TaskAction,
+    from: TaskStatus,
+    to: TaskStatus,
+    user_id: &str,
+    comment: Option<String>,
+) -> TransitionActiveModel {
+    TransitionActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        table_id: Set(table_id.to_string()),
+        task_id: Set(task_id.to_string()),
+        action: Set(action.to_string()),
+        from_status: Set(from.value()),
+        to_status: Set(to.value()),
+        comment: Set(comment),
+        user_id: Set(user_id.to_string()),
+        created_at: Set(Local::now().naive_local()),
+    }
+}
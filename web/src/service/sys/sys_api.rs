@@ -1,11 +1,16 @@
 use anyhow::{bail, Result};
 use chrono::Local;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
 use sea_orm::ActiveValue::Set;
 use uuid::Uuid;
 
 use common::db::get_db;
+use common::db::version::{check_version, version};
 use entity::sys_api::{ActiveModel, Column as SysApiColumn, Entity as SysApi};
+use middleware::permission::invalidate_all;
 use models::dto::{handler_page, PageResult};
 use models::dto::sys::request::sys_api::{AddApiDto, SearchApiDto, UpdateApiDto};
 use models::dto::sys::response::sys_api::ResponseApi;
@@ -43,6 +48,8 @@ pub async fn delete_by_id(id: String, force: bool) -> Result<String> {
                 // 删除Api
                 let delete_result = SysApi::delete_by_id(id)
                     .exec(&db).await?;
+                // 清除角色api缓存
+                invalidate_all().await;
                 Ok(format!("{}", delete_result.rows_affected))
             }
             false => {
@@ -51,6 +58,8 @@ pub async fn delete_by_id(id: String, force: bool) -> Result<String> {
                 current.deleted_at = Set(Some(Local::now().naive_local()));
                 // 更新删除字段数据
                 let update_result = current.update(&db).await?;
+                // 清除角色api缓存
+                invalidate_all().await;
                 Ok(update_result.id)
             }
         }
@@ -87,16 +96,26 @@ pub async fn add(data: AddApiDto) -> Result<String> {
 
 /// 更新Api信息
 /// @param update_api 待更新的Api对象
-pub async fn update(id: String, update_api: UpdateApiDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_api: UpdateApiDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
         bail!("编号不能为空".to_string())
     }
     // 查询租户信息
-    let result = SysApi::find_by_id(id).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = SysApi::find_by_id(id).lock_exclusive().one(&tx).await?;
     // 更新组织信息
     if let Some(current) = result {
+        let current_version = version(current.updated_at, current.created_at);
+        let response = ResponseApi::from(current.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
         let mut current: ActiveModel = current.into();
         // 名称是否为空
         if update_api.name.is_some() {
@@ -125,7 +144,10 @@ pub async fn update(id: String, update_api: UpdateApiDto) -> Result<String> {
         // 更新时间
         current.updated_at = Set(Some(Local::now().naive_local()));
         // 更新数据
-        let update_data = current.update(&db).await?;
+        let update_data = current.update(&tx).await?;
+        tx.commit().await?;
+        // 清除角色api缓存
+        invalidate_all().await;
         Ok(update_data.id)
     } else {
         bail!("Api数据不存在，无法更新".to_string())
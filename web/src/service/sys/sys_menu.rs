@@ -2,11 +2,13 @@ use anyhow::{bail, Result};
 use chrono::Local;
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, QueryFilter, QueryOrder, QuerySelect,
+    TransactionTrait,
 };
 use uuid::Uuid;
 
 use common::db::get_db;
+use common::db::version::{check_version, version};
 use common::error::CtsError;
 use entity::sys_menu::{ActiveModel, Column as SysMenuColumn, Entity as SysMenu};
 use entity::sys_role_menu::{Column as SysRoleMenuColumn, Entity as SysRoleMenu};
@@ -130,16 +132,26 @@ pub async fn add(data: AddMenuDto) -> Result<String> {
 
 /// 更新菜单信息
 /// @param update_role 待更新的菜单对象
-pub async fn update(id: String, update_menu: UpdateMenuDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_menu: UpdateMenuDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
         bail!("菜单编号不能为空".to_string())
     }
     // 查询菜单信息
-    let result = SysMenu::find_by_id(id.clone()).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = SysMenu::find_by_id(id.clone()).lock_exclusive().one(&tx).await?;
     // 更新菜单信息
     if let Some(current) = result {
+        let current_version = version(current.updated_at, current.created_at);
+        let response = ResponseMenu::from(current.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
         if SYSTEM_MENU == current.default_menu {
             bail!("默认菜单不能修改".to_string())
         }
@@ -200,8 +212,6 @@ pub async fn update(id: String, update_menu: UpdateMenuDto) -> Result<String> {
         }
         // 更新时间
         current.updated_at = Set(Some(Local::now().naive_local()));
-        // 获取事务对象
-        let tx = db.begin().await?;
         if update_menu.sort.is_some() {
             let new_sort = update_menu.sort.unwrap();
             // 查询sort位置以及以后的数据
@@ -2,6 +2,7 @@ use anyhow::{Ok, Result};
 use common::db::get_db;
 use entity::sys_api::{Column as SysApiColumn, Entity as SysApi};
 use entity::sys_menu::{Column as SysMenuColumn, Entity as SysMenu};
+use middleware::permission::invalidate_role;
 
 use entity::sys_role_api::{
     ActiveModel as ActiveSysRoleApi, Column as SysRoleApiColumn, Entity as SysRoleApi,
@@ -82,6 +83,8 @@ pub async fn set_api(apis_role: RoleApiDto) -> Result<String> {
     let _insert_result = SysRoleApi::insert_many(list).exec(&tx).await?;
     // 提交数据
     tx.commit().await?;
+    // 清除角色api缓存
+    invalidate_role(&role_id).await;
     Ok("授权Api成功".to_string())
 }
 
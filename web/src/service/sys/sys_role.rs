@@ -4,14 +4,19 @@ use anyhow::{bail, Result};
 use chrono::Local;
 use models::dto::sys::response::sys_user::ResponseUser;
 use sea_orm::ActiveValue::Set;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
 use uuid::Uuid;
 
 use crate::service::has_tenant;
 use crate::service::sys::ADMIN_ID;
 use common::db::get_db;
+use common::db::version::{check_version, version};
 use entity::sys_role::{ActiveModel, Column as SysRoleColumn, Entity as SysRole};
 use entity::sys_tenant::{Column as SysTenantColumn, Entity as SysTenant};
+use middleware::permission::invalidate_role;
 use models::dto::sys::request::sys_role::{AddRoleDto, SearchRoleDto, UpdateRoleDto};
 use models::dto::sys::response::sys_role::ResponseRole;
 use models::dto::sys::response::sys_tenant::ResponseTenant;
@@ -61,7 +66,8 @@ pub async fn delete_by_id(id: String, force: bool) -> Result<String> {
             match force {
                 true => {
                     // 删除角色
-                    let delete_result = SysRole::delete_by_id(id).exec(&db).await?;
+                    let delete_result = SysRole::delete_by_id(id.clone()).exec(&db).await?;
+                    invalidate_role(&id).await;
                     Ok(format!("{}", delete_result.rows_affected))
                 }
                 false => {
@@ -70,6 +76,7 @@ pub async fn delete_by_id(id: String, force: bool) -> Result<String> {
                     current.deleted_at = Set(Some(Local::now().naive_local()));
                     // 更新删除字段数据
                     let update_result = current.update(&db).await?;
+                    invalidate_role(&update_result.id).await;
                     Ok(update_result.id)
                 }
             }
@@ -108,7 +115,12 @@ pub async fn add(data: AddRoleDto) -> Result<String> {
 
 /// 更新角色信息
 /// @param update_role 待更新的角色对象
-pub async fn update(id: String, update_role: UpdateRoleDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_role: UpdateRoleDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
@@ -118,9 +130,14 @@ pub async fn update(id: String, update_role: UpdateRoleDto) -> Result<String> {
         bail!("超级管理员角色无法更新".to_string())
     }
     // 查询角色信息
-    let result = SysRole::find_by_id(id.clone()).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = SysRole::find_by_id(id.clone()).lock_exclusive().one(&tx).await?;
     // 更新角色信息
     if let Some(current) = result {
+        let current_version = version(current.updated_at, current.created_at);
+        let response = ResponseRole::from(current.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
         let mut current: ActiveModel = current.into();
         // 名称是否为空
         if update_role.name.is_some() {
@@ -137,7 +154,8 @@ pub async fn update(id: String, update_role: UpdateRoleDto) -> Result<String> {
         // 更新时间
         current.updated_at = Set(Some(Local::now().naive_local()));
         // 更新数据
-        let update_data = current.update(&db).await?;
+        let update_data = current.update(&tx).await?;
+        tx.commit().await?;
         Ok(update_data.id)
     } else {
         bail!("角色数据不存在，无法更新".to_string())
@@ -172,6 +190,7 @@ pub async fn update_status(id: String, status: i32) -> Result<String> {
         current_data.updated_at = Set(Some(Local::now().naive_local()));
         // 更新状态
         let ok = current_data.update(&db).await?;
+        invalidate_role(&ok.id).await;
         Ok(ok.id)
     } else {
         bail!("角色数据不存在，无法更新".to_string())
@@ -1,10 +1,14 @@
 use anyhow::{bail, Result};
 use chrono::Local;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
 use sea_orm::ActiveValue::Set;
 use uuid::Uuid;
 use sea_orm::QueryOrder;
 use common::db::get_db;
+use common::db::version::{check_version, version};
 use entity::sys_tenant::{ActiveModel, Column as SysTenantColumn, Entity as SysTenant};
 use models::dto::{handler_page, PageResult};
 use models::dto::sys::request::sys_tenant::{AddTenantDto, SearchTenantDto, UpdateTenantDto};
@@ -86,16 +90,26 @@ pub async fn add(data: AddTenantDto) -> Result<String> {
 
 /// 更新租户信息
 /// @param update_role 待更新的租户对象
-pub async fn update(id: String, update_tenant: UpdateTenantDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_tenant: UpdateTenantDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
         bail!("编号不能为空".to_string())
     }
     // 查询租户信息
-    let result = SysTenant::find_by_id(id).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = SysTenant::find_by_id(id).lock_exclusive().one(&tx).await?;
     // 更新组织信息
     if let Some(current) = result {
+        let current_version = version(current.updated_at, current.created_at);
+        let response = ResponseTenant::from(current.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
         let mut current: ActiveModel = current.into();
         // 名称是否为空
         if update_tenant.name.is_some() {
@@ -120,7 +134,8 @@ pub async fn update(id: String, update_tenant: UpdateTenantDto) -> Result<String
         // 更新时间
         current.updated_at = Set(Some(Local::now().naive_local()));
         // 更新数据
-        let update_data = current.update(&db).await?;
+        let update_data = current.update(&tx).await?;
+        tx.commit().await?;
         Ok(update_data.id)
     } else {
         bail!("租户数据不存在，无法更新".to_string())
@@ -5,14 +5,17 @@ use chrono::Local;
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
 };
 use uuid::Uuid;
 
 use common::db::get_db;
+use common::db::version::{check_version, version};
 use common::md5::generate_md5;
 use entity::sys_role::{Column as SysRoleColumn, Entity as SysRole};
 use entity::sys_tenant::{Column as SysTenantColumn, Entity as SysTenant};
 use entity::sys_user::{ActiveModel, Column as SysUserColumn, Entity as SysUser};
+use middleware::permission::invalidate_role;
 use models::dto::sys::request::sys_user::{AddUserDto, SearchUserDto, UpdateUserDto};
 use models::dto::sys::response::sys_role::ResponseRole;
 use models::dto::sys::response::sys_tenant::ResponseTenant;
@@ -129,7 +132,12 @@ pub async fn add(data: AddUserDto) -> Result<String> {
 
 /// 更新用户信息
 /// @param update_user 待更新的用户对象
-pub async fn update(id: String, update_user: UpdateUserDto) -> Result<String> {
+/// @param row_version 读取数据时的版本，None不检查
+pub async fn update(
+    id: String,
+    update_user: UpdateUserDto,
+    row_version: Option<String>,
+) -> Result<String> {
     let db = get_db().await;
     // 判断id是否存在
     if id.is_empty() {
@@ -139,10 +147,16 @@ pub async fn update(id: String, update_user: UpdateUserDto) -> Result<String> {
         bail!("超级用户无法更新".to_string())
     }
     // 查询用户信息
-    let result = SysUser::find_by_id(id.clone()).one(&db).await?;
+    // 锁定数据，检查版本和更新在同一个事务中执行
+    let tx = db.begin().await?;
+    let result = SysUser::find_by_id(id.clone()).lock_exclusive().one(&tx).await?;
     // todo 角色是否存在
     // 更新用户名
     if let Some(current_user) = result {
+        let current_version = version(current_user.updated_at, current_user.created_at);
+        let response = ResponseUser::from(current_user.clone());
+        check_version(row_version.as_deref(), Some(&current_version), &response)?;
+        let old_role_id = current_user.role_id.clone();
         let mut current_user: ActiveModel = current_user.into();
         // 昵称是否为空
         if update_user.nickname.is_some() {
@@ -176,7 +190,13 @@ pub async fn update(id: String, update_user: UpdateUserDto) -> Result<String> {
         // 更新时间
         current_user.updated_at = Set(Some(Local::now().naive_local()));
         // 更新数据
-        let update_data = current_user.update(&db).await?;
+        let update_data = current_user.update(&tx).await?;
+        tx.commit().await?;
+        // 角色变更后清除原角色和新角色的api缓存
+        if old_role_id != update_data.role_id {
+            invalidate_role(&old_role_id).await;
+            invalidate_role(&update_data.role_id).await;
+        }
         Ok(update_data.id)
     } else {
         bail!("用户数据不存在，无法更新".to_string())